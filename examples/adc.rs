@@ -18,7 +18,7 @@ use stm32_hal2::{
     dma::{self, Dma, DmaChannel, DmaInput, DmaInterrupt, DmaPeriph, DmaWriteBuf},
     gpio::{Pin, PinMode, Port},
     low_power, pac,
-    timer::{BasicTimer, MasterModeSelection},
+    timer::{MasterModeSelection, Timer},
 };
 
 static mut ADC_READ_BUF: [u16; 2] = [0; 2];
@@ -64,8 +64,10 @@ fn main() -> ! {
     adc.enable_interrupt(AdcInterrupt::EndOfSequence);
 
     // If you wish to sample at a fixed rate, consider using a basic timer (TIM6 or TIM7)
-    let mut adc_timer = BasicTimer::new(
-        dp.TIM6, 100., // Frequency in Hz.
+    let mut adc_timer = Timer::new_tim6(
+        dp.TIM6,
+        100., // Frequency in Hz.
+        Default::default(),
         &clock_cfg,
     );
 
@@ -84,20 +86,13 @@ fn main() -> ! {
 
     let mut dma_buf = [0];
 
-    dma::mux(DmaChannel::C1, DmaInput::Adc1);
+    let dma_ch = dma::claim_channel(DmaPeriph::Dma1, DmaChannel::C1).unwrap();
+    dma::mux(DmaPeriph::Dma1, DmaChannel::C1, DmaInput::Adc1);
 
     // Begin a DMA transfer. Note that the `DmaChannel` we pass here is only used on
     // MCUs that use `DMAMUX`, eg L5, G0, and G4. For those, you need to run `mux`, to
     // set the channel: `dma::mux(DmaPeriph::Dma1, DmaChannel::C1, MuxInput::Adc1);
-    unsafe {
-        adc.read_dma(
-            &mut dma_buf,
-            &[chan_num],
-            DmaChannel::C1,
-            Default::default(),
-            DmaPeriph::Dma1,
-        )
-    };
+    unsafe { adc.read_dma(&mut dma_buf, &[chan_num], &dma_ch, Default::default()) };
 
     // Wait for the transfer to complete. Ie by handling the channel's transfer-complete
     // interrupt in an ISR, which is enabled by the `read_dma` command.
@@ -124,12 +119,11 @@ fn main() -> ! {
         batt_curr_adc.read_dma(
             &mut ADC_READ_BUF,
             &[17, 12],
-            DmaChannel::C2,
+            &dma::claim_channel_for(DmaInput::Adc2).unwrap(),
             ChannelCfg {
                 circular: dma::Circular::Enabled,
                 ..Default::default()
             },
-            &mut dma,
         );
     }
 
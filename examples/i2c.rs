@@ -13,7 +13,7 @@ use cortex_m_rt::entry;
 use critical_section::{with, Mutex};
 use stm32_hal2::{
     clocks::Clocks,
-    dma::{self, Dma, DmaChannel, DmaInterrupt, DmaPeriph, DmaWriteBuf},
+    dma::{self, ChannelToken, Dma, DmaChannel, DmaInterrupt, DmaPeriph, DmaWriteBuf},
     gpio::{Pin, PinMode, Port},
     i2c::{I2c, I2cConfig, I2cSpeed, NoiseFilter},
     low_power, pac,
@@ -25,6 +25,10 @@ static mut READ_BUF: [u8; 8] = [0; 8];
 
 const ADDR: u8 = 0x48;
 
+// The I2C peripheral, and the DMA channel it reads on, are moved here so the DMA ISRs can use them.
+static I2C: Mutex<RefCell<Option<I2c<pac::I2C1>>>> = Mutex::new(RefCell::new(None));
+static RX_CH: Mutex<RefCell<Option<ChannelToken>>> = Mutex::new(RefCell::new(None));
+
 #[entry]
 fn main() -> ! {
     // Set up CPU peripherals
@@ -72,19 +76,14 @@ fn main() -> ! {
     // Associate DMA channels with I2C1: One for transmit; one for receive.
     // Note that mux is not used on F3, F4, and most L4s: DMA channels are hard-coded
     // to peripherals on those platforms.
+    let tx_ch = dma::claim_channel(DmaPeriph::Dma1, DmaChannel::C6).unwrap();
+    let rx_ch = dma::claim_channel(DmaPeriph::Dma1, DmaChannel::C7).unwrap();
     dma::mux(DmaPeriph::Dma1, DmaChannel::C6, DmaInput::I2c1Tx);
     dma::mux(DmaPeriph::Dma1, DmaChannel::C7, DmaInput::I2c1Rx);
 
     // Write to DMA, requesting readings
     unsafe {
-        i2c.write_dma(
-            ADDR,
-            &WRITE_BUF,
-            false,
-            DmaChannel::C6,
-            Default::default(),
-            DmaPeriph::Dma1,
-        );
+        i2c.write_dma(ADDR, &WRITE_BUF, false, &tx_ch, Default::default());
     }
 
     // Alternatively, use the blocking, non-DMA I2C API` (Also supports `embedded-hal` traits):
@@ -96,6 +95,12 @@ fn main() -> ! {
     i2c.write_read(ADDR, &[conversion_reg], &mut read_buf).ok();
     let reading = i16::from_be_bytes([read_buf[0], read_buf[1]]);
 
+    with(|cs| {
+        // Make the I2C peripheral and the read channel global, for use in the DMA ISR.
+        I2C.borrow(cs).replace(Some(i2c));
+        RX_CH.borrow(cs).replace(Some(rx_ch));
+    });
+
     // Unmask the interrupt line. See the `DMA_CH6` and `DMA_CH78` interrupt handlers below.
     unsafe {
         NVIC::unmask(DmaPeriph::Dma1, pac::Interrupt::DMA1_CH6);
@@ -129,17 +134,20 @@ fn DMA1_CH7() {
     );
 
     // Once the write is complete, command a transfer to receive the readings.
-    // todo: Need a way to access the `I2c` struct from this ISR context.
-    // See other examples for info on how to do this.
-    unsafe {
-        i2c.read_dma(
-            ADDR,
-            &mut READ_BUF,
-            DmaChannel::C7,
-            Default::default(),
-            DmaPeriph::Dma1,
-        );
-    }
+    with(|cs| {
+        let mut i = I2C.borrow(cs).borrow_mut();
+        let i2c = i.as_mut().unwrap();
+        let rx_ch = RX_CH.borrow(cs).borrow();
+
+        unsafe {
+            i2c.read_dma(
+                ADDR,
+                &mut READ_BUF,
+                rx_ch.as_ref().unwrap(),
+                Default::default(),
+            );
+        }
+    });
 }
 
 // same panicking *behavior* as `panic-probe` but doesn't print a panic message
@@ -43,7 +43,7 @@ use stm32_hal2::{
     gpio::{OutputType, Pin, PinMode, Port, Pull},
     pac::{self, interrupt, DAC, DFSDM, DMA1, SAI1},
     prelude::*,
-    timer::{BasicTimer, MasterModeSelection, Timer, TimerInterrupt},
+    timer::{MasterModeSelection, Timer, TimerInterrupt},
 };
 
 const FS: u32 = 48_000;
@@ -134,9 +134,10 @@ mod app {
 
         // Tim6 and Tim7 are internally connected to the DAC and are able to drive it through their
         // trigger outputs.
-        let mut dac_timer = BasicTimer::new(
+        let mut dac_timer = Timer::new_tim6(
             dp.TIM6,
             clock_cfg.sai1_speed() as f32 / (64. * 2.),
+            Default::default(),
             &clock_cfg,
         );
 
@@ -176,13 +177,12 @@ mod app {
             dfsdm.read_dma(
                 &mut INPUT_BUF_L,
                 Filter::F0,
-                DmaChannel::C0,
+                &dma::claim_channel(DmaPeriph::Dma1, DmaChannel::C0).unwrap(),
                 dma::ChannelCfg {
                     circular: dma::Circular::Enabled,
                     priority: dma::Priority::High,
                     ..Default::default()
                 },
-                &mut dma,
             );
         }
 
@@ -190,13 +190,12 @@ mod app {
             dfsdm.read_dma(
                 &mut INPUT_BUF_R,
                 Filter::F1,
-                DmaChannel::C1,
+                &dma::claim_channel(DmaPeriph::Dma1, DmaChannel::C1).unwrap(),
                 dma::ChannelCfg {
                     circular: dma::Circular::Enabled,
                     priority: dma::Priority::High,
                     ..Default::default()
                 },
-                &mut dma,
             );
         }
 
@@ -209,12 +208,11 @@ mod app {
             dac.write_dma(
                 &OUTPUT_BUF_L,
                 DacChannel::C1,
-                DmaChannel::C2,
+                &dma::claim_channel(DmaPeriph::Dma1, DmaChannel::C2).unwrap(),
                 dma::ChannelCfg {
                     circular: dma::Circular::Enabled,
                     ..Default::default()
                 },
-                &mut dma,
             );
         }
 
@@ -12,7 +12,7 @@ use stm32_hal2::{
     self,
     clocks::{Clocks},
     gpio::{Pin, PinMode, Port},
-    timer::{Timer, BasicTimer, TimerInterrupt, MasterModeSelection},
+    timer::{Timer, TimerInterrupt, MasterModeSelection},
     pac::{self, TIM4, TIM2},
     dac::{Dac, DacChannel, SawtoothConfig, SawtoothDirection, Trigger},
 };
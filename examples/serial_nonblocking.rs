@@ -16,7 +16,7 @@ use cortex_m_rt::entry;
 use critical_section::{with, Mutex};
 use stm32_hal2::{
     clocks::Clocks,
-    dma::{self, Dma, DmaChannel, DmaConfig, DmaPeriph},
+    dma::{self, ChannelToken, Dma, DmaChannel, DmaConfig, DmaPeriph},
     gpio::{Pin, PinMode, Port},
     low_power,
     pac::{self, interrupt},
@@ -75,13 +75,15 @@ fn main() -> ! {
     // will depend on the format of data you are reading and writing. Specifically, pay
     // attention to how you know when a message starts and ends, if not of a fixed size.
     let mut dma = Dma::new(dp.DMA1);
+    // Claim the channel, so no other driver can use it.
+    let rx_ch = dma::claim_channel(DMA_PERIPH, DMA_CH).unwrap();
     // This DMA MUX step isn't required on F3, F4, and most L4 variants.
-    dma::mux(DMA_PERIPH, DMA_CH, DmaInput::Usart1Tx);
+    dma::mux(DMA_PERIPH, DMA_CH, DmaInput::Usart1Rx);
     dma.enable_interrupt(DMA_CH, DmaInterrupt::TransferComplete);
 
     // Example of how to start a DMA transfer:
     unsafe {
-        uart.read_dma(&mut RX_BUF, DMA_CH, ChannelCfg::default(), DMA_PERIPH);
+        uart.read_dma(&mut RX_BUF, &rx_ch, ChannelCfg::default());
     }
 
     loop {
@@ -130,9 +132,11 @@ fn DMA1_CH1() {
         // This is true for writes; not sure if required for reads as well.
         dma::stop(DMA_PERIPH, DMA_CH);
 
-        // Start a  new transfer, if appropriate for the protocol you're using.
+        // Start a  new transfer, if appropriate for the protocol you're using. (We claimed this
+        // channel in `main`)
         unsafe {
-            uart.read_dma(&mut RX_BUF, DMA_CH, ChannelCfg::default(), DMA_PERIPH);
+            let rx_ch = ChannelToken::steal(DMA_PERIPH, DMA_CH);
+            uart.read_dma(&mut RX_BUF, &rx_ch, ChannelCfg::default());
         }
     });
 }
@@ -13,7 +13,7 @@ use cortex_m_rt::entry;
 use critical_section::{with, Mutex};
 use stm32_hal2::{
     clocks::Clocks,
-    dma::{self, Dma, DmaChannel, DmaInput, DmaInterrupt, DmaPeriph, DmaWriteBuf},
+    dma::{self, ChannelToken, Dma, DmaChannel, DmaInput, DmaInterrupt, DmaPeriph, DmaWriteBuf},
     gpio::{self, Pin, PinMode, Port},
     low_power,
     pac::{self, interrupt},
@@ -68,17 +68,21 @@ fn main() -> ! {
     // Set up DMA, for nonblocking (generally faster) conversion transfers:
     let mut dma = Dma::new(&mut dp.DMA1, &dp.RCC);
 
-    // Associate a pair of DMA channels with SPI1: One for transmit; one for receive.
-    // Note that mux is not used on F3, F4, and most L4s: DMA channels are hard-coded
+    // Claim a pair of DMA channels, and associate them with SPI1: One for transmit; one for
+    // receive. Note that mux is not used on F3, F4, and most L4s: DMA channels are hard-coded
     // to peripherals on those platforms.
+    let tx_ch = dma::claim_channel(DmaPeriph::Dma2, DmaChannel::C1).unwrap();
+    let rx_ch = dma::claim_channel(DmaPeriph::Dma2, DmaChannel::C2).unwrap();
     dma::mux(DmaPeriph::Dma2, DmaChannel::C1, DmaInput::Spi1Tx);
     dma::mux(DmaPeriph::Dma2, DmaChannel::C2, DmaInput::Spi1Rx);
+    // Alternatively, let the HAL pick free channels, and run `mux` for us:
+    // let tx_ch = dma::claim_channel_for(DmaInput::Spi1Tx).unwrap();
 
     cs.set_low();
 
     unsafe {
         // Write to SPI, using DMA.
-        // spi.write_dma(&write_buf, DmaChannel::C1, Default::default(), DmaPeriph::Dma2);
+        // spi.write_dma(&write_buf, &tx_ch, Default::default());
 
         // Read (transfer) from SPI, using DMA.
         spi.transfer_dma(
@@ -86,11 +90,10 @@ fn main() -> ! {
             // read 3 bytes.
             &SPI_WRITE_BUF,
             &mut SPI_READ_BUF,  // Read buf, where the data will go
-            DmaChannel::C1,     // Write channel
-            DmaChannel::C2,     // Read channel
+            &tx_ch,             // Write channel
+            &rx_ch,             // Read channel
             Default::default(), // Write channel config
             Default::default(), // Read channel config
-            DmaPeriph::Dma2,
         );
     }
 
@@ -134,10 +137,17 @@ fn DMA1_CH2() {
     with(|cs| {
         defmt::println!("SPI DMA read complete");
         access_global!(SPI, spi, cs);
-        spi.stop_dma(DmaChannel::C1, Some(DmaChannel::C2), DmaPeriph::Dma2);
+        // We claimed these channels in `main`.
+        let (tx_ch, rx_ch) = unsafe {
+            (
+                ChannelToken::steal(DmaPeriph::Dma2, DmaChannel::C1),
+                ChannelToken::steal(DmaPeriph::Dma2, DmaChannel::C2),
+            )
+        };
+        spi.stop_dma(&tx_ch, Some(&rx_ch));
 
         // See also this convenience function, which clears the interrupt and stops othe Txfer.:
-        spi.cleanup_dma(DmaPeriph::Dma2, DmaChannel::C1, Some(DmaChannel::C2));
+        spi.cleanup_dma(&tx_ch, Some(&rx_ch));
 
         unsafe {
             // Ignore byte 0, which is the reg we passed during the write.
@@ -27,7 +27,7 @@ use stm32_hal2::{
     adc::{self, Adc, AdcChannel},
     clocks::Clocks,
     dac::{Dac, DacChannel, DacBits},
-    dma::{Dma, DmaPeriph, DmaChannel, DmaInput, DmaInterrupt, DmaReadBuf, DmaWriteBuf},
+    dma::{self, Dma, DmaPeriph, DmaChannel, DmaInput, DmaInterrupt, DmaReadBuf, DmaWriteBuf},
     flash::Flash,
     gpio::{Edge, Pin, Port, PinMode, OutputType, Pull},
     i2c::I2c,
@@ -159,8 +159,10 @@ fn main() -> ! {
     uart.read(&mut uart_buffer, &mut dma);
 
     // Or, read and write using DMA:
-    uart.write_dma(&[1, 2, 3, 4], DmaChannel::C2, Default::default(), DmaPeriph::Dma1);
-    uart.read_dma(&mut uart_buffer, DmaChannel::C3, Default::default(), DmaPeriph::Dma1);
+    let tx_ch = dma::claim_channel_for(DmaInput::Usart1Tx).unwrap();
+    let rx_ch = dma::claim_channel_for(DmaInput::Usart1Rx).unwrap();
+    uart.write_dma(&[1, 2, 3, 4], &tx_ch, Default::default());
+    uart.read_dma(&mut uart_buffer, &rx_ch, Default::default());
 
     // Set up the Analog-to-digital converter
     let _adc_pin = Pin::new(Port::B, 5, PinMode::Analog);
@@ -12,8 +12,8 @@ use stm32_hal2::{
     gpio::{Edge, Pin, PinMode, Port},
     low_power, pac,
     timer::{
-        Alignment, BasicTimer, CaptureCompare, CountDir, InputSlaveMode, InputTrigger,
-        MasterModeSelection, OutputCompare, TimChannel, Timer, TimerConfig, TimerInterrupt,
+        Alignment, CaptureCompare, CountDir, InputSlaveMode, InputTrigger, MasterModeSelection,
+        OutputCompare, Polarity, PwmInputCfg, TimChannel, Timer, TimerConfig, TimerInterrupt,
     },
 };
 
@@ -49,12 +49,25 @@ fn main() -> ! {
     pwm_timer.set_input_capture(
         TimChannel::C2,
         CaptureCompare::InputTi1,
-        InputTrigger::Internal0,
-        InputSlaveMode::Disabled,
-        true,
-        false,
+        Polarity::ActiveHigh,
+        Polarity::ActiveHigh,
+        0b0011, // Input filter
     );
 
+    // Measure a PWM signal's frequency and duty cycle on CH1, eg from a fan tachometer or RC
+    // receiver. Use the counter prescaler to set the longest period that can be measured.
+    let mut pwm_input_timer = Timer::new_tim4(dp.TIM4, 1., Default::default(), &clock_cfg);
+    pwm_input_timer.set_pwm_input(&PwmInputCfg {
+        filter: 0b0011,
+        psc: 99,
+        ..Default::default()
+    });
+    pwm_input_timer.enable();
+
+    if let Some((freq, duty)) = pwm_input_timer.read_pwm_input() {
+        println!("PWM input frequency: {} Hz, duty cycle: {}%", freq, duty);
+    }
+
     pwm_timer.enable();
 
     // Change the duty cycle. The argument is the auto-reload value (ARR).
@@ -90,9 +103,10 @@ fn main() -> ! {
     println!("Time elapsed since timer start: {}", seconds_elapsed);
 
     // Set up a basic timer, eg for DAC triggering
-    let mut dac_timer = BasicTimer::new(
+    let mut dac_timer = Timer::new_tim6(
         dp.TIM6,
         clock_cfg.sai1_speed() as f32 / (64. * 8.),
+        Default::default(),
         &clock_cfg,
     );
 
@@ -105,7 +119,7 @@ fn main() -> ! {
     // arbitrary waveforms.
 
     let mut dma = Dma::new(dp.DMA1);
-    dma::mux(DmaPeriph::Dma1, DmaChannel::C1, DmaInput::Tim3Up);
+    let dma_ch = dma::claim_channel_for(DmaInput::Tim3Up).unwrap();
     timer.enable_interrupt(TimerInterrupt::UpdateDma);
 
     timer.rotors.write_dma_burst(
@@ -114,10 +128,9 @@ fn main() -> ! {
         /// RM register table, and dividing by 4.
         13,
         4, // Burst le. Eg if updating 4 channels.
-        DmaChannel::C1,
+        &dma_ch,
         Default::default(),
         true, // true if a 32-bit timer.
-        DmaPeriph::Dma1,
     );
 
     // todo: realistic examples of various uses of timers etc.
@@ -33,7 +33,7 @@ use stm32_hal2::{
     dma::{self, Dma, DmaChannel},
     gpio::{OutputType, Pin, PinMode, Port},
     low_power, pac,
-    timer::{BasicTimer, MasterModeSelection, TimerDevice},
+    timer::{MasterModeSelection, Timer},
 };
 
 // Length of the lookup table used to generate sin waves etc.
@@ -92,7 +92,7 @@ fn main() -> ! {
     // trigger outputs.
     // We set this timer to the sample rate in Hz.
     // This timer triggers a transfer of one word from the DMA buffer into the DAC output register.
-    let mut dac_timer = BasicTimer::new(dp.TIM6, TimerDevice::T6, timer_freq, &clock_cfg);
+    let mut dac_timer = Timer::new_tim6(dp.TIM6, timer_freq, Default::default(), &clock_cfg);
 
     //  The update event is selected as a trigger output (TRGO). For instance a
     // master timer can then be used as a prescaler for a slave timer.
@@ -106,7 +106,7 @@ fn main() -> ! {
 
     let mut dma = Dma::new(dp.DMA1);
 
-    dma::mux(DmaChannel::C3, dma::DmaInput::DacCh1);
+    let dma_ch = dma::claim_channel_for(dma::DmaInput::DacCh1).unwrap();
 
     // Load the Sine LUT into a DMA circular buffer, which will send a 16-byte word of data
     // to the DAC on each timer trigger. Because it's a circular buffer, it will start at
@@ -117,13 +117,7 @@ fn main() -> ! {
     };
 
     unsafe {
-        dac.write_dma(
-            &lut::SIN_X,
-            DacChannel::C1,
-            DmaChannel::C3,
-            channel_cfg,
-            &mut dma,
-        );
+        dac.write_dma(&lut::SIN_X, DacChannel::C1, &dma_ch, channel_cfg);
     }
 
     dac.enable(DacChannel::C1);
@@ -6,10 +6,12 @@ use cfg_if::cfg_if;
 use cortex_m::{asm, delay::Delay};
 use paste::paste;
 
+#[cfg(all(feature = "h7", not(feature = "h7b3")))]
+use crate::bdma::Bdma;
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "f4", feature = "l552", feature = "h5")))]
-use crate::dma::{self, ChannelCfg, DmaChannel};
+#[cfg(not(any(feature = "f4", feature = "h5")))]
+use crate::dma::{self, ChannelCfg, ChannelToken};
 use crate::{
     clocks::Clocks,
     pac::{self, RCC},
@@ -969,18 +971,22 @@ macro_rules! hal {
                 });
             }
 
-            #[cfg(not(any(feature = "f4", feature = "l552", feature = "h5")))]
+            #[cfg(not(any(feature = "f4", feature = "h5")))]
             /// Take a reading, using DMA. Sets conversion sequence; no need to set it directly.
-            /// Note that the `channel` argument is unused on F3 and L4, since it is hard-coded,
-            /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
+            /// On F3 and L4, the `channel` token must be for the channel hard-wired to this
+            /// peripheral on its DMA controller, since it can't be configured using the DMAMUX
+            /// peripheral; this panics otherwise. `dma::claim_channel_for()` claims the right one.
+            /// On H7, set `channel_cfg.double_buf` for gapless, double-buffered readings; see
+            /// `dma::set_inactive_buf()`.
             pub unsafe fn read_dma(
                 &mut self, buf: &mut [u16],
                 adc_channels: &[u8],
-                dma_channel: DmaChannel,
+                dma_channel: &ChannelToken,
                 channel_cfg: ChannelCfg,
-                dma_periph: dma::DmaPeriph,
                 // dma: &mut Dma<D>
             ) {
+                let dma_periph = dma_channel.periph();
+
             // where
             //     D: Deref<Target = dma_p::RegisterBlock>,
             // {
@@ -999,37 +1005,28 @@ macro_rules! hal {
                 self.regs.cfgr.modify(|_, w| {
                     // Note: To use non-DMA after this has been set, need to configure manually.
                     // ie set back to 0b00.
-                    w.dmngt().bits(if channel_cfg.circular == dma::Circular::Enabled { 0b11 } else { 0b01 })
+                    // Double-buffer DMA runs in circular mode, so needs the ADC's circular mode too.
+                    let circular = channel_cfg.circular == dma::Circular::Enabled
+                        || channel_cfg.double_buf.is_some();
+                    w.dmngt().bits(if circular { 0b11 } else { 0b01 })
                 });
 
-                // L44 RM, Table 41. "DMA1 requests for each channel
-                // todo: DMA2 support.
+                // L44 RM, Tables 41 and 42. "DMA1 (and DMA2) requests for each channel"
+                #[cfg(not(any(feature = "f3", feature = "l4")))]
+                let dma_channel = dma_channel.channel();
                 #[cfg(any(feature = "f3", feature = "l4"))]
-                let dma_channel = match self.device {
-                    AdcDevice::One => DmaInput::Adc1.dma1_channel(),
-                    AdcDevice::Two => DmaInput::Adc2.dma1_channel(),
+                let dma_channel = dma::check_fixed_channel(dma_channel, dma_periph, match self.device {
+                    AdcDevice::One => DmaInput::Adc1.fixed_channel(dma_periph),
+                    AdcDevice::Two => DmaInput::Adc2.fixed_channel(dma_periph),
                     _ => panic!("DMA on ADC beyond 2 is not supported. If it is for your MCU, please submit an issue \
                 or PR on Github.")
-                };
+                });
 
                 #[cfg(feature = "l4")]
-                match dma_periph {
-                    dma::DmaPeriph::Dma1 => {
-                        let mut regs = unsafe { &(*pac::DMA1::ptr()) };
-                        match self.device {
-                            AdcDevice::One => dma::channel_select(&mut regs, DmaInput::Adc1),
-                            AdcDevice::Two => dma::channel_select(&mut regs, DmaInput::Adc2),
-                            _ => unimplemented!(),
-                        }
-                    }
-                    dma::DmaPeriph::Dma2 => {
-                        let mut regs = unsafe { &(*pac::DMA2::ptr()) };
-                        match self.device {
-                            AdcDevice::One => dma::channel_select(&mut regs, DmaInput::Adc1),
-                            AdcDevice::Two => dma::channel_select(&mut regs, DmaInput::Adc2),
-                            _ => unimplemented!(),
-                        }
-                    }
+                match self.device {
+                    AdcDevice::One => dma::channel_select(dma_periph, DmaInput::Adc1),
+                    AdcDevice::Two => dma::channel_select(dma_periph, DmaInput::Adc2),
+                    _ => unimplemented!(),
                 }
 
                 let mut seq_len = 0;
@@ -1115,6 +1112,34 @@ macro_rules! hal {
                 }
             }
 
+            #[cfg(not(any(feature = "f4", feature = "h5")))]
+            /// Take readings using DMA, taking ownership of the buffer and the ADC for the duration
+            /// of the transfer. Use the returned `Transfer` to check for completion, and to get them
+            /// back. See `read_dma` for details.
+            pub fn read_dma_owned(
+                mut self, buf: &'static mut [u16],
+                adc_channels: &[u8],
+                dma_channel: ChannelToken,
+                channel_cfg: ChannelCfg,
+            ) -> dma::Transfer<Self, &'static mut [u16]> {
+                unsafe { self.read_dma(buf, adc_channels, &dma_channel, channel_cfg.single_buf()) };
+
+                dma::Transfer::new(self, buf, dma_channel).with_cleanup(Self::end_dma)
+            }
+
+            #[cfg(not(any(feature = "f4", feature = "h5")))]
+            /// Stop conversions, and disable DMA requests, once an owned transfer has ended.
+            fn end_dma(&mut self) {
+                // DMAEN, DMACFG and DMNGT can only be written when ADSTART=0 and JADSTART=0.
+                self.stop_conversions();
+
+                #[cfg(not(feature = "h7"))]
+                self.regs.cfgr.modify(|_, w| w.dmaen().clear_bit());
+
+                #[cfg(feature = "h7")]
+                self.regs.cfgr.modify(|_, w| w.dmngt().bits(0b00));
+            }
+
             /// Enable a specific type of ADC interrupt.
             pub fn enable_interrupt(&mut self, interrupt: AdcInterrupt) {
                 self.regs.ier.modify(|_, w| match interrupt {
@@ -1208,7 +1233,59 @@ cfg_if! {
     if #[cfg(feature = "h7")] {
         hal!(ADC1, ADC12_COMMON, adc1, 12);
         hal!(ADC2, ADC12_COMMON, adc2, 12);
-        hal!(ADC3, ADC3_COMMON, adc3, 3);
+    }
+}
+
+// H7B3 doesn't have ADC3.
+#[cfg(all(feature = "h7", not(feature = "h7b3")))]
+hal!(ADC3, ADC3_COMMON, adc3, 3);
+
+#[cfg(all(feature = "h7", not(feature = "h7b3")))]
+impl Adc<pac::ADC3> {
+    /// Take a reading using the BDMA. ADC3 is in the D3 domain, so its DMA requests are only
+    /// available on DMAMUX2; connect it to the BDMA channel with
+    /// `dma::mux2(.., DmaInput2::Adc3, ..)`. `buf` must be in SRAM4, since the BDMA can't access
+    /// other memory.
+    pub unsafe fn read_dma_bdma(
+        &mut self,
+        buf: &mut [u16],
+        adc_channels: &[u8],
+        dma_channel: dma::DmaChannel,
+        channel_cfg: ChannelCfg,
+        bdma: &mut Bdma,
+    ) {
+        let (ptr, len) = (buf.as_mut_ptr(), buf.len());
+        // See comments in `read_dma`.
+        self.stop_conversions();
+
+        self.regs.cfgr.modify(|_, w| {
+            w.dmngt()
+                .bits(if channel_cfg.circular == dma::Circular::Enabled {
+                    0b11
+                } else {
+                    0b01
+                })
+        });
+
+        let mut seq_len = 0;
+        for (i, ch) in adc_channels.iter().enumerate() {
+            self.set_sequence(*ch, i as u8 + 1);
+            seq_len += 1;
+        }
+        self.set_sequence_len(seq_len);
+
+        self.regs.cr.modify(|_, w| w.adstart().set_bit()); // Start
+
+        bdma.cfg_channel(
+            dma_channel,
+            &self.regs.dr as *const _ as u32,
+            ptr as u32,
+            len as u16,
+            dma::Direction::ReadFromPeriph,
+            dma::DataSize::S16,
+            dma::DataSize::S16,
+            channel_cfg,
+        );
     }
 }
 
@@ -0,0 +1,232 @@
+//! Support for the Basic Direct Memory Access (BDMA) controller, on H7. The BDMA sits in the D3
+//! domain, and serves D3 peripherals such as LPUART1, SPI6, I2C4, SAI4, and ADC3. Its requests
+//! are routed through DMAMUX2; use `dma::mux2()` to connect a `DmaInput2` to a BDMA channel. BDMA
+//! channel n is served by DMAMUX2 channel n. See H743 RM, section 17: BDMA.
+//!
+//! Note that the BDMA can only access SRAM4 and the backup SRAM; buffers used with it must be
+//! placed there, eg using a linker section.
+
+use core::sync::atomic::{self, Ordering};
+
+use cfg_if::cfg_if;
+
+use crate::{
+    dma::{ChannelCfg, Circular, DataSize, Direction, DmaChannel, DmaInterrupt},
+    pac::RCC,
+    util::rcc_en_reset,
+};
+
+cfg_if! {
+    // On H7B3, BDMA2 is the controller connected to DMAMUX2. (BDMA1 serves DFSDM2)
+    if #[cfg(feature = "h7b3")] {
+        use crate::pac::{bdma2 as bdma, BDMA2 as BDMA};
+    } else {
+        use crate::pac::{bdma, BDMA};
+    }
+}
+
+/// Represents the Basic Direct Memory Access (BDMA) peripheral.
+pub struct Bdma {
+    pub regs: BDMA,
+}
+
+impl Bdma {
+    /// Initialize the BDMA peripheral, including enabling and resetting
+    /// its RCC peripheral clock.
+    pub fn new(regs: BDMA) -> Self {
+        let rcc = unsafe { &(*RCC::ptr()) };
+        #[cfg(feature = "h7b3")]
+        rcc_en_reset!(ahb4, bdma2, rcc);
+        #[cfg(not(feature = "h7b3"))]
+        rcc_en_reset!(ahb4, bdma, rcc);
+
+        Self { regs }
+    }
+
+    /// Configure a BDMA channel. See H743 RM, section 17.3.3. Sets the Transfer Complete
+    /// interrupt. The FIFO and burst settings of `ChannelCfg` are ignored, since the BDMA
+    /// doesn't have them.
+    pub fn cfg_channel(
+        &mut self,
+        channel: DmaChannel,
+        periph_addr: u32,
+        mem_addr: u32,
+        num_data: u16,
+        direction: Direction,
+        periph_size: DataSize,
+        mem_size: DataSize,
+        cfg: ChannelCfg,
+    ) {
+        cfg_channel(
+            &self.regs,
+            channel,
+            periph_addr,
+            mem_addr,
+            num_data,
+            direction,
+            periph_size,
+            mem_size,
+            cfg,
+        )
+    }
+
+    /// Stop a BDMA transfer, if in progress.
+    pub fn stop(&mut self, channel: DmaChannel) {
+        stop_internal(&self.regs, channel);
+    }
+
+    /// Clear an interrupt flag.
+    pub fn clear_interrupt(&mut self, channel: DmaChannel, interrupt: DmaInterrupt) {
+        clear_interrupt_internal(&self.regs, channel, interrupt);
+    }
+
+    pub fn transfer_is_complete(&mut self, channel: DmaChannel) -> bool {
+        self.regs.isr.read().bits() & (1 << flag_pos(channel, DmaInterrupt::TransferComplete)) != 0
+    }
+
+    /// Enable a specific type of interrupt.
+    pub fn enable_interrupt(&mut self, channel: DmaChannel, interrupt: DmaInterrupt) {
+        enable_interrupt_internal(&self.regs, channel, interrupt, true);
+    }
+
+    /// Disable a specific type of interrupt.
+    pub fn disable_interrupt(&mut self, channel: DmaChannel, interrupt: DmaInterrupt) {
+        enable_interrupt_internal(&self.regs, channel, interrupt, false);
+    }
+}
+
+/// Bit position of a channel's flag, in ISR and IFCR. Each channel uses 4 bits: GIF, TCIF, HTIF
+/// and TEIF. Note that the PAC numbers these fields from 1, while the RM numbers channels from 0.
+fn flag_pos(channel: DmaChannel, interrupt: DmaInterrupt) -> u8 {
+    let offset = match interrupt {
+        DmaInterrupt::TransferComplete => 1,
+        DmaInterrupt::HalfTransfer => 2,
+        DmaInterrupt::TransferError => 3,
+        // No FIFO or direct mode on BDMA; the global flag is the closest match.
+        _ => 0,
+    };
+
+    channel as u8 * 4 + offset
+}
+
+/// Configure a BDMA channel. See H743 RM, section 17.3.3: "Programming transfers".
+pub fn cfg_channel(
+    regs: &bdma::RegisterBlock,
+    channel: DmaChannel,
+    periph_addr: u32,
+    mem_addr: u32,
+    num_data: u16,
+    direction: Direction,
+    periph_size: DataSize,
+    mem_size: DataSize,
+    cfg: ChannelCfg,
+) {
+    let ch = &regs.ch[channel as usize];
+
+    // "The register fields/bits MEM2MEM, PL[1:0], MSIZE[1:0], PSIZE[1:0], MINC, PINC, and DIR
+    // are read-only when EN = 1"
+    ch.cr.modify(|_, w| w.en().clear_bit());
+    while ch.cr.read().en().bit_is_set() {}
+
+    // Clear the channel's flags left over from a previous transfer: GIF, TCIF, HTIF and TEIF.
+    regs.ifcr
+        .write(|w| unsafe { w.bits(0b1111 << (channel as u8 * 4)) });
+
+    // 1. Set the peripheral register address in the BDMA_CPARx register.
+    ch.par.write(|w| unsafe { w.bits(periph_addr) });
+
+    atomic::compiler_fence(Ordering::SeqCst);
+
+    // 2. Set the memory address in the BDMA_CM0ARx register.
+    ch.m0ar.write(|w| unsafe { w.bits(mem_addr) });
+
+    // 3. Configure the total number of data to transfer in the BDMA_CNDTRx register.
+    ch.ndtr.write(|w| w.ndt().bits(num_data));
+
+    // 4. Configure the parameters listed below in the BDMA_CCRx register, then
+    // 5. Activate the channel by setting the EN bit in the BDMA_CCRx register.
+    // (The circular mode must not be used in memory-to-memory mode.)
+    let mem2mem = matches!(direction, Direction::MemToMem) && cfg.circular == Circular::Disabled;
+
+    ch.cr.modify(|_, w| unsafe {
+        w.pl().bits(cfg.priority as u8);
+        w.mem2mem().bit(mem2mem);
+        // 0: read from peripheral. In memory-to-memory mode, the peripheral address is the source.
+        w.dir().bit(matches!(direction, Direction::ReadFromMem));
+        w.circ().bit(cfg.circular as u8 != 0);
+        w.pinc().bit(cfg.periph_incr as u8 != 0);
+        w.minc().bit(cfg.mem_incr as u8 != 0);
+        w.psize().bits(periph_size as u8);
+        w.msize().bits(mem_size as u8);
+        w.tcie().set_bit();
+        w.en().set_bit()
+    });
+}
+
+fn stop_internal(regs: &bdma::RegisterBlock, channel: DmaChannel) {
+    let cr = &regs.ch[channel as usize].cr;
+    cr.modify(|_, w| w.en().clear_bit());
+    while cr.read().en().bit_is_set() {}
+}
+
+fn clear_interrupt_internal(
+    regs: &bdma::RegisterBlock,
+    channel: DmaChannel,
+    interrupt: DmaInterrupt,
+) {
+    regs.ifcr
+        .write(|w| unsafe { w.bits(1 << flag_pos(channel, interrupt)) });
+}
+
+fn enable_interrupt_internal(
+    regs: &bdma::RegisterBlock,
+    channel: DmaChannel,
+    interrupt: DmaInterrupt,
+    enable: bool,
+) {
+    let cr = &regs.ch[channel as usize].cr;
+
+    // "It must not be written when the channel is enabled (EN = 1)."
+    let originally_enabled = cr.read().en().bit_is_set();
+    if originally_enabled {
+        cr.modify(|_, w| w.en().clear_bit());
+        while cr.read().en().bit_is_set() {}
+    }
+
+    match interrupt {
+        DmaInterrupt::TransferError => cr.modify(|_, w| w.teie().bit(enable)),
+        DmaInterrupt::HalfTransfer => cr.modify(|_, w| w.htie().bit(enable)),
+        DmaInterrupt::TransferComplete => cr.modify(|_, w| w.tcie().bit(enable)),
+        // No FIFO or direct mode on BDMA.
+        _ => (),
+    }
+
+    if originally_enabled {
+        cr.modify(|_, w| w.en().set_bit());
+        while cr.read().en().bit_is_clear() {}
+    }
+}
+
+/// Stop a BDMA transfer, if in progress.
+pub fn stop(channel: DmaChannel) {
+    let regs = unsafe { &(*BDMA::ptr()) };
+    stop_internal(regs, channel);
+}
+
+/// Enable a specific type of interrupt.
+pub fn enable_interrupt(channel: DmaChannel, interrupt: DmaInterrupt) {
+    let regs = unsafe { &(*BDMA::ptr()) };
+    enable_interrupt_internal(regs, channel, interrupt, true);
+}
+
+/// Disable a specific type of interrupt.
+pub fn disable_interrupt(channel: DmaChannel, interrupt: DmaInterrupt) {
+    let regs = unsafe { &(*BDMA::ptr()) };
+    enable_interrupt_internal(regs, channel, interrupt, false);
+}
+
+/// Clear an interrupt flag.
+pub fn clear_interrupt(channel: DmaChannel, interrupt: DmaInterrupt) {
+    let regs = unsafe { &(*BDMA::ptr()) };
+    clear_interrupt_internal(regs, channel, interrupt);
+}
@@ -1156,6 +1156,11 @@ impl Clocks {
         self.hclk() / self.d2_prescaler2.value() as u32
     }
 
+    /// Get the APB4 (D3 domain) peripheral clock frequency, in hz.
+    pub fn apb4(&self) -> u32 {
+        self.hclk() / self.d3_prescaler.value() as u32
+    }
+
     /// Get the frequency used by APB2 timers, in hz
     pub fn apb2_timer(&self) -> u32 {
         if let ApbPrescaler::Div1 = self.d2_prescaler2 {
@@ -20,8 +20,8 @@ cfg_if! {
 
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "f4", feature = "l552")))]
-use crate::dma::{self, ChannelCfg, DmaChannel};
+#[cfg(not(feature = "h5"))]
+use crate::dma::{self, ChannelCfg, ChannelToken};
 #[cfg(feature = "g0")]
 use crate::pac::DMA as DMA1;
 #[cfg(not(feature = "g0"))]
@@ -428,45 +428,41 @@ where
     /// Send values to the DAC using DMA. Each trigger (Eg using a timer; the basic timers Tim6
     /// and Tim7 are designed for DAC triggering) sends one word from the buffer to the DAC's
     /// output.
-    /// Note that the `dma_channel` argument is unused on F3 and L4, since it is hard-coded,
-    /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
+    /// On F3 and L4, the `dma_channel` token must be for the channel hard-wired to this
+    /// peripheral on its DMA controller, since it can't be configured using the DMAMUX
+    /// peripheral; this panics otherwise. `dma::claim_channel_for()` claims the right one.
+    #[cfg(not(feature = "h5"))]
     pub unsafe fn write_dma(
         &mut self,
         buf: &[u16],
         dac_channel: DacChannel,
-        dma_channel: DmaChannel,
+        dma_channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
         // dma: &mut Dma<D>,
     ) {
+        let dma_periph = dma_channel.periph();
+
         // where
         // D: Deref<Target = dma_p::RegisterBlock>,
         // {
         let (ptr, len) = (buf.as_ptr(), buf.len());
 
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let dma_channel = dma_channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let dma_channel = match dac_channel {
-            DacChannel::C1 => DmaInput::Dac1Ch1.dma1_channel(),
-            DacChannel::C2 => DmaInput::Dac1Ch2.dma1_channel(),
-        };
+        let dma_channel = dma::check_fixed_channel(
+            dma_channel,
+            dma_periph,
+            match dac_channel {
+                DacChannel::C1 => DmaInput::Dac1Ch1.fixed_channel(dma_periph),
+                DacChannel::C2 => DmaInput::Dac1Ch2.fixed_channel(dma_periph),
+            },
+        );
 
         #[cfg(feature = "l4")]
-        match dma_periph {
-            dma::DmaPeriph::Dma1 => {
-                let mut regs = unsafe { &(*DMA1::ptr()) };
-                match dac_channel {
-                    DacChannel::C1 => dma::channel_select(&mut regs, DmaInput::Dac1Ch1),
-                    DacChannel::C2 => dma::channel_select(&mut regs, DmaInput::Dac1Ch2),
-                }
-            }
-            dma::DmaPeriph::Dma2 => {
-                let mut regs = unsafe { &(*pac::DMA2::ptr()) };
-                match dac_channel {
-                    DacChannel::C1 => dma::channel_select(&mut regs, DmaInput::Dac1Ch1),
-                    DacChannel::C2 => dma::channel_select(&mut regs, DmaInput::Dac1Ch2),
-                };
-            }
+        match dac_channel {
+            DacChannel::C1 => dma::channel_select(dma_periph, DmaInput::Dac1Ch1),
+            DacChannel::C2 => dma::channel_select(dma_periph, DmaInput::Dac1Ch2),
         }
 
         // H743 RM, section 26.4.8: DMA requests
@@ -581,6 +577,40 @@ where
         }
     }
 
+    /// Send values to the DAC using DMA, taking ownership of the buffer and the DAC for the
+    /// duration of the transfer. Use the returned `Transfer` to check for completion, and to get
+    /// them back. See `write_dma` for details.
+    #[cfg(not(feature = "h5"))]
+    pub fn write_dma_owned(
+        mut self,
+        buf: &'static [u16],
+        dac_channel: DacChannel,
+        dma_channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static [u16]> {
+        unsafe { self.write_dma(buf, dac_channel, &dma_channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, dma_channel).with_cleanup(Self::end_dma)
+    }
+
+    /// Disable DMA requests on both channels, once an owned transfer has ended.
+    #[cfg(not(feature = "h5"))]
+    fn end_dma(&mut self) {
+        #[cfg(feature = "g4")]
+        self.regs.dac_cr.modify(|_, w| {
+            #[cfg(not(feature = "wl"))]
+            w.dmaen2().clear_bit();
+            w.dmaen1().clear_bit()
+        });
+
+        #[cfg(not(feature = "g4"))]
+        self.regs.cr.modify(|_, w| {
+            #[cfg(not(feature = "wl"))]
+            w.dmaen2().clear_bit();
+            w.dmaen1().clear_bit()
+        });
+    }
+
     /// Set the DAC output voltage.
     pub fn write_voltage(&mut self, channel: DacChannel, volts: f32) {
         let max_word = match self.cfg.bits {
@@ -24,8 +24,8 @@ cfg_if! {
 
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "f4", feature = "l552")))]
-use crate::dma::{self, ChannelCfg, DmaChannel};
+#[cfg(not(feature = "f4"))]
+use crate::dma::{self, ChannelCfg, ChannelToken};
 use crate::pac::DMA1;
 
 #[derive(Clone, Copy)]
@@ -1160,104 +1160,47 @@ where
     /// You need to shift the result 8 bits to the result after reading the values from memory
     /// to discard the other fields. (The integer signing is unchanged, since the 24-bit integer data
     /// is aligned to the left of the 32-bit register, which maps to an `i32` here.)
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
+    ///
+    /// On H7, set `channel_cfg.double_buf` for gapless, double-buffered readings; see
+    /// `dma::set_inactive_buf()`.
+    #[cfg(not(feature = "f4"))]
     pub unsafe fn read_dma(
         &mut self,
         buf: &mut [i32],
         filter: Filter,
-        dma_channel: DmaChannel,
+        dma_channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
         // dma: &mut Dma<D>,
     ) {
+        let dma_periph = dma_channel.periph();
+
         // where
         //     D: Deref<Target = dma_p::RegisterBlock>,
         // {
         let (ptr, len) = (buf.as_mut_ptr(), buf.len());
 
+        #[cfg(not(feature = "f3"))]
+        let dma_channel = dma_channel.channel();
         #[cfg(feature = "f3")]
-        let dma_channel = match filter {
-            Filter::F0 => DmaInput::Dfsdm1F0.dma1_channel(),
-            Filter::F1 => DmaInput::Dfsdm1F1.dma1_channel(),
-        };
+        let dma_channel = dma::check_fixed_channel(
+            dma_channel,
+            dma::DmaPeriph::Dma1,
+            match filter {
+                Filter::F0 => DmaInput::Dfsdm1F0.dma1_channel(),
+                Filter::F1 => DmaInput::Dfsdm1F1.dma1_channel(),
+            },
+        );
 
         #[cfg(feature = "l4")]
-        match dma_periph {
-            dma::DmaPeriph::Dma1 => {
-                let mut regs = unsafe { &(*DMA1::ptr()) };
-                match filter {
-                    Filter::F0 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F0),
-                    Filter::F1 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F1),
-                    Filter::F2 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F2),
-                    Filter::F3 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F3),
-                };
-            }
-            dma::DmaPeriph::Dma2 => {
-                let mut regs = unsafe { &(*pac::DMA2::ptr()) };
-                match filter {
-                    Filter::F0 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F0),
-                    Filter::F1 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F1),
-                    Filter::F2 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F2),
-                    Filter::F3 => dma::channel_select(&mut regs, DmaInput::Dfsdm1F3),
-                };
-            }
-        }
-
         match filter {
-            Filter::F0 => {
-                cfg_if! {
-                    if #[cfg(any(feature = "l5"))] {
-                        let cr1 = &self.regs.flt0cr1;
-                    } else if #[cfg(any(feature = "l4"))] {
-                        let cr1 = &self.regs.dfsdm0_cr1;
-                    } else {
-                        let cr1 = &self.regs.flt0.cr1;
-                    }
-                }
-                cr1.modify(|_, w| w.rdmaen().set_bit())
-            }
-            #[cfg(feature = "l4x6")]
-            Filter::F1 => (),
-            #[cfg(not(feature = "l4x6"))]
-            Filter::F1 => {
-                cfg_if! {
-                    if #[cfg(any(feature = "l5"))] {
-                        let cr1 = &self.regs.flt1cr1;
-                    } else if #[cfg(any(feature = "l4"))] {
-                        // let cr1 = &self.regs.dfsdm1_cr1;
-                    } else {
-                        let cr1 = &self.regs.flt1.cr1;
-                    }
-                }
-                cr1.modify(|_, w| w.rdmaen().set_bit())
-            }
-            Filter::F2 => {
-                cfg_if! {
-                    if #[cfg(any(feature = "l5"))] {
-                        let cr1 = &self.regs.flt2cr1;
-                    } else if #[cfg(any(feature = "l4"))] {
-                        let cr1 = &self.regs.dfsdm2_cr1;
-                    } else {
-                        let cr1 = &self.regs.flt2.cr1;
-                    }
-                }
-                cr1.modify(|_, w| w.rdmaen().set_bit())
-            }
-
-            Filter::F3 => {
-                cfg_if! {
-                    if #[cfg(any(feature = "l5"))] {
-                        let cr1 = &self.regs.flt3cr1;
-                    } else if #[cfg(any(feature = "l4"))] {
-                        let cr1 = &self.regs.dfsdm3_cr1;
-                    } else {
-                        let cr1 = &self.regs.flt3.cr1;
-                    }
-                }
-                cr1.modify(|_, w| w.rdmaen().set_bit())
-            }
+            Filter::F0 => dma::channel_select(dma_periph, DmaInput::Dfsdm1F0),
+            Filter::F1 => dma::channel_select(dma_periph, DmaInput::Dfsdm1F1),
+            Filter::F2 => dma::channel_select(dma_periph, DmaInput::Dfsdm1F2),
+            Filter::F3 => dma::channel_select(dma_periph, DmaInput::Dfsdm1F3),
         }
 
+        self.set_dma_enabled(filter, true);
+
         let periph_addr = match filter {
             Filter::F0 => {
                 cfg_if! {
@@ -1357,6 +1300,90 @@ where
         }
     }
 
+    /// Read regular conversion data using DMA, taking ownership of the buffer and the DFSDM for
+    /// the duration of the transfer. Use the returned `Transfer` to check for completion, and to
+    /// get them back. See `read_dma` for details.
+    #[cfg(not(feature = "f4"))]
+    pub fn read_dma_owned(
+        mut self,
+        buf: &'static mut [i32],
+        filter: Filter,
+        dma_channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static mut [i32]> {
+        unsafe { self.read_dma(buf, filter, &dma_channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, dma_channel).with_cleanup(Self::end_dma)
+    }
+
+    /// Enable or disable a filter's regular conversion DMA requests. Sets the `FLTxCR1` register,
+    /// `RDMAEN` field.
+    #[cfg(not(feature = "f4"))]
+    fn set_dma_enabled(&mut self, filter: Filter, enabled: bool) {
+        match filter {
+            Filter::F0 => {
+                cfg_if! {
+                    if #[cfg(any(feature = "l5"))] {
+                        let cr1 = &self.regs.flt0cr1;
+                    } else if #[cfg(any(feature = "l4"))] {
+                        let cr1 = &self.regs.dfsdm0_cr1;
+                    } else {
+                        let cr1 = &self.regs.flt0.cr1;
+                    }
+                }
+                cr1.modify(|_, w| w.rdmaen().bit(enabled))
+            }
+            #[cfg(feature = "l4x6")]
+            Filter::F1 => (),
+            #[cfg(not(feature = "l4x6"))]
+            Filter::F1 => {
+                cfg_if! {
+                    if #[cfg(any(feature = "l5"))] {
+                        let cr1 = &self.regs.flt1cr1;
+                    } else if #[cfg(any(feature = "l4"))] {
+                        // let cr1 = &self.regs.dfsdm1_cr1;
+                    } else {
+                        let cr1 = &self.regs.flt1.cr1;
+                    }
+                }
+                cr1.modify(|_, w| w.rdmaen().bit(enabled))
+            }
+            Filter::F2 => {
+                cfg_if! {
+                    if #[cfg(any(feature = "l5"))] {
+                        let cr1 = &self.regs.flt2cr1;
+                    } else if #[cfg(any(feature = "l4"))] {
+                        let cr1 = &self.regs.dfsdm2_cr1;
+                    } else {
+                        let cr1 = &self.regs.flt2.cr1;
+                    }
+                }
+                cr1.modify(|_, w| w.rdmaen().bit(enabled))
+            }
+
+            Filter::F3 => {
+                cfg_if! {
+                    if #[cfg(any(feature = "l5"))] {
+                        let cr1 = &self.regs.flt3cr1;
+                    } else if #[cfg(any(feature = "l4"))] {
+                        let cr1 = &self.regs.dfsdm3_cr1;
+                    } else {
+                        let cr1 = &self.regs.flt3.cr1;
+                    }
+                }
+                cr1.modify(|_, w| w.rdmaen().bit(enabled))
+            }
+        }
+    }
+
+    /// Disable DMA requests on all filters, once an owned transfer has ended.
+    #[cfg(not(feature = "f4"))]
+    fn end_dma(&mut self) {
+        for filter in [Filter::F0, Filter::F1, Filter::F2, Filter::F3] {
+            self.set_dma_enabled(filter, false);
+        }
+    }
+
     /// Enable a specific type of interrupt. See H743 RM, section 30.5: DFSDM interrupts
     pub fn enable_interrupt(&mut self, interrupt_type: DfsdmInterrupt, channel: Filter) {
         // todo: Macro to reduce DRY here?
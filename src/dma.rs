@@ -1,5 +1,9 @@
 //! Support for the Direct Memory Access (DMA) peripheral. This module handles initialization, and transfer
 //! configuration for DMA. The `Dma::cfg_channel` method is called by modules that use DMA.
+//!
+//! On F4, `DmaChannel` refers to a stream. Each stream serves one peripheral request at a time,
+//! selected with `channel_select()`, prior to starting a transfer. F4 I2C (`i2c_f4`) and ADC
+//! don't have DMA methods yet.
 
 // todo: This module could be greatly simplified if [this issue](https://github.com/stm32-rs/stm32-rs/issues/610)
 // todo is addressed: Ie H7 PAC approach adopted by other modules.
@@ -14,14 +18,16 @@
 // }
 
 use core::{
+    cell::Cell,
     ops::Deref,
     sync::atomic::{self, Ordering},
 };
 
+use cortex_m::interrupt::{self, Mutex};
+
 use crate::{
     pac::{self, RCC},
     util::rcc_en_reset,
-    MAX_ITERS,
 };
 
 cfg_if! {
@@ -59,14 +65,22 @@ pub enum DmaPeriph {
     Dma2,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 #[repr(usize)]
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "h7", feature = "l5")))]
 /// A list of DMA input sources. The integer values represent their DMAMUX register value, on
 /// MCUs that use this. G4 RM, Table 91: DMAMUX: Assignment of multiplexer inputs to resources.
 pub enum DmaInput {
     // This (on G4) goes up to 115. For now, just implement things we're likely
     // to use in this HAL. Make sure this is compatible beyond G4.
+    #[cfg(any(feature = "g0", feature = "g4", feature = "wb", feature = "wl"))]
+    ReqGen0 = 1,
+    #[cfg(any(feature = "g0", feature = "g4", feature = "wb", feature = "wl"))]
+    ReqGen1 = 2,
+    #[cfg(any(feature = "g0", feature = "g4", feature = "wb", feature = "wl"))]
+    ReqGen2 = 3,
+    #[cfg(any(feature = "g0", feature = "g4", feature = "wb", feature = "wl"))]
+    ReqGen3 = 4,
     Adc1 = 5,
     Dac1Ch1 = 6,
     Dac1Ch2 = 7,
@@ -146,7 +160,231 @@ pub enum DmaInput {
     Dfsdm1F3 = 206,
 }
 
-// todo: Trigger, synchronization etc mappings. Perhaps DmaTrigger, DmaSync enums etc.
+#[derive(Copy, Clone)]
+#[repr(usize)]
+#[cfg(feature = "l5")]
+/// A list of DMA input sources. The integer values represent their DMAMUX register value.
+/// L552 RM, Table 54: DMAMUX1: Assignment of multiplexer inputs to resources.
+pub enum DmaInput {
+    ReqGen0 = 1,
+    ReqGen1 = 2,
+    ReqGen2 = 3,
+    ReqGen3 = 4,
+    Adc1 = 5,
+    Adc2 = 6,
+    Dac1Ch1 = 7,
+    Dac1Ch2 = 8,
+    Tim6Up = 9,
+    Tim7Up = 10,
+    Spi1Rx = 11,
+    Spi1Tx = 12,
+    Spi2Rx = 13,
+    Spi2Tx = 14,
+    Spi3Rx = 15,
+    Spi3Tx = 16,
+    I2c1Rx = 17,
+    I2c1Tx = 18,
+    I2c2Rx = 19,
+    I2c2Tx = 20,
+    I2c3Rx = 21,
+    I2c3Tx = 22,
+    I2c4Rx = 23,
+    I2c4Tx = 24,
+    Usart1Rx = 25,
+    Usart1Tx = 26,
+    Usart2Rx = 27,
+    Usart2Tx = 28,
+    Usart3Rx = 29,
+    Usart3Tx = 30,
+    Uart4Rx = 31,
+    Uart4Tx = 32,
+    Uart5Rx = 33,
+    Uart5Tx = 34,
+    Lpuart1Rx = 35,
+    Lpuart1Tx = 36,
+    Sai1A = 37,
+    Sai1B = 38,
+    Sai2A = 39,
+    Sai2B = 40,
+    Octospi1 = 41,
+    Tim1Ch1 = 42,
+    Tim1Ch2 = 43,
+    Tim1Ch3 = 44,
+    Tim1Ch4 = 45,
+    TimUp = 46,
+    Tim1Trig = 47,
+    Tim1Com = 48,
+    Tim8Ch1 = 49,
+    Tim8Ch2 = 50,
+    Tim8Ch3 = 51,
+    Tim8Ch4 = 52,
+    Tim8Up = 53,
+    Tim8Trig = 54,
+    Tim8Com = 55,
+    Tim2Ch1 = 56,
+    Tim2Ch2 = 57,
+    Tim2Ch3 = 58,
+    Tim2Ch4 = 59,
+    Tim2Up = 60,
+    Tim3Ch1 = 61,
+    Tim3Ch2 = 62,
+    Tim3Ch3 = 63,
+    Tim3Ch4 = 64,
+    Tim3Up = 65,
+    Tim3Trig = 66,
+    Tim4Ch1 = 67,
+    Tim4Ch2 = 68,
+    Tim4Ch3 = 69,
+    Tim4Ch4 = 70,
+    Tim4Up = 71,
+    Dfsdm1F0 = 86,
+    Dfsdm1F1 = 87,
+    Dfsdm1F2 = 88,
+    Dfsdm1F3 = 89,
+}
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+#[cfg(any(feature = "g0", feature = "g4", feature = "l5"))]
+/// A list of DMAMUX synchronization inputs, set in CxCR.SYNC_ID. The integer values represent
+/// their DMAMUX register value. G0 RM, Table 60: DMAMUX: Assignment of synchronization inputs to
+/// resources. (Table 93 on G4 RM; Table 56 on L552 RM)
+pub enum DmaSync {
+    Exti0 = 0,
+    Exti1 = 1,
+    Exti2 = 2,
+    Exti3 = 3,
+    Exti4 = 4,
+    Exti5 = 5,
+    Exti6 = 6,
+    Exti7 = 7,
+    Exti8 = 8,
+    Exti9 = 9,
+    Exti10 = 10,
+    Exti11 = 11,
+    Exti12 = 12,
+    Exti13 = 13,
+    Exti14 = 14,
+    Exti15 = 15,
+    DmamuxEvt0 = 16,
+    DmamuxEvt1 = 17,
+    DmamuxEvt2 = 18,
+    DmamuxEvt3 = 19,
+    Lptim1Out = 20,
+    #[cfg(any(feature = "g0", feature = "l5"))]
+    Lptim2Out = 21,
+    #[cfg(feature = "g0")]
+    Tim14Oc = 22,
+    #[cfg(feature = "l5")]
+    Lptim3Out = 22,
+}
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+#[cfg(any(feature = "wb", feature = "wl"))]
+/// A list of DMAMUX synchronization inputs, set in CxCR.SYNC_ID. The integer values represent
+/// their DMAMUX register value. WL RM, Table 45: DMAMUX: Assignment of synchronization inputs
+/// to resources.
+pub enum DmaSync {
+    Exti0 = 0,
+    Exti1 = 1,
+    Exti2 = 2,
+    Exti3 = 3,
+    Exti4 = 4,
+    Exti5 = 5,
+    Exti6 = 6,
+    Exti7 = 7,
+    Exti8 = 8,
+    Exti9 = 9,
+    Exti10 = 10,
+    Exti11 = 11,
+    Exti12 = 12,
+    Exti13 = 13,
+    Exti14 = 14,
+    Exti15 = 15,
+    DmamuxEvt0 = 16,
+    DmamuxEvt1 = 17,
+    Lptim1Out = 18,
+    Lptim2Out = 19,
+    #[cfg(feature = "wl")]
+    Lptim3Out = 20,
+}
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+#[cfg(feature = "h7")]
+/// A list of DMAMUX1 synchronization inputs, set in CxCR.SYNC_ID. The integer values represent
+/// their DMAMUX register value. H743 RM, Table 122: DMAMUX1: Assignment of synchronization inputs
+/// to resources.
+pub enum DmaSync {
+    DmamuxEvt0 = 0,
+    DmamuxEvt1 = 1,
+    DmamuxEvt2 = 2,
+    Lptim1Out = 3,
+    Lptim2Out = 4,
+    Lptim3Out = 5,
+    Exti0 = 6,
+    Tim12Trgo = 7,
+}
+
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// DMAMUX request generator trigger inputs, set in RGxCR.SIG_ID. On the MCUs we support, the
+/// trigger input assignments are the same as the synchronization input ones. (eg G0 RM, Table 59,
+/// and H743 RM, Table 120)
+pub type DmaTrigger = DmaSync;
+
+#[derive(Copy, Clone, PartialEq)]
+#[repr(u8)]
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Edge of a DMAMUX synchronization or trigger input to act on. Sets CxCR.SPOL, or RGxCR.GPOL.
+pub enum SyncPolarity {
+    /// No event; ie no synchronization or trigger detection.
+    NoEvent = 0b00,
+    Rising = 0b01,
+    Falling = 0b10,
+    Both = 0b11,
+}
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// A DMAMUX request generator channel. Each one produces DMA requests from a trigger input; route
+/// them to a DMA channel using `mux()`, with the associated `DmaInput::ReqGen` variant.
+pub enum ReqGen {
+    G0 = 0,
+    G1 = 1,
+    G2 = 2,
+    G3 = 3,
+    #[cfg(feature = "h7")]
+    G4 = 4,
+    #[cfg(feature = "h7")]
+    G5 = 5,
+    #[cfg(feature = "h7")]
+    G6 = 6,
+    #[cfg(feature = "h7")]
+    G7 = 7,
+}
 
 #[derive(Copy, Clone)]
 #[repr(usize)]
@@ -156,6 +394,14 @@ pub enum DmaInput {
 /// (Table 118 in RM0468)
 /// Note that this is only for DMAMUX1
 pub enum DmaInput {
+    ReqGen0 = 1,
+    ReqGen1 = 2,
+    ReqGen2 = 3,
+    ReqGen3 = 4,
+    ReqGen4 = 5,
+    ReqGen5 = 6,
+    ReqGen6 = 7,
+    ReqGen7 = 8,
     Adc1 = 9,
     Adc2 = 10,
     Tim1Ch1 = 11,
@@ -252,6 +498,7 @@ pub enum DmaInput2 {
     I3crTx = 14,
     Sai4A = 15,
     Sai4B = 16,
+    Adc3 = 17,
 }
 
 impl DmaInput {
@@ -305,6 +552,64 @@ impl DmaInput {
         }
     }
 
+    #[cfg(any(feature = "f3", feature = "l4"))]
+    /// Select the hard set DMA2 channel associated with a given input source. See L44 RM, Table 42,
+    /// and F303 RM, Table 79. Panics if the input isn't served by DMA2.
+    pub fn dma2_channel(&self) -> DmaChannel {
+        match self {
+            Self::Spi3Rx => DmaChannel::C1,
+            Self::Spi3Tx => DmaChannel::C2,
+            #[cfg(feature = "f3")]
+            Self::Uart4Rx => DmaChannel::C3,
+            #[cfg(feature = "f3")]
+            Self::Uart4Tx => DmaChannel::C5,
+            #[cfg(feature = "l4")]
+            Self::Uart4Rx => DmaChannel::C5,
+            #[cfg(feature = "l4")]
+            Self::Uart4Tx => DmaChannel::C3,
+            #[cfg(feature = "l4")]
+            Self::Uart5Rx => DmaChannel::C2,
+            #[cfg(feature = "l4")]
+            Self::Uart5Tx => DmaChannel::C1,
+            #[cfg(feature = "l4")]
+            Self::Sai1A => DmaChannel::C1,
+            #[cfg(feature = "l4")]
+            Self::Sai1B => DmaChannel::C2,
+            #[cfg(feature = "l4")]
+            Self::Adc1 => DmaChannel::C3,
+            #[cfg(feature = "l4")]
+            Self::Adc2 => DmaChannel::C4,
+            #[cfg(feature = "l4")]
+            Self::Spi1Rx => DmaChannel::C3,
+            #[cfg(feature = "l4")]
+            Self::Spi1Tx => DmaChannel::C4,
+            #[cfg(feature = "l4")]
+            Self::Usart1Rx => DmaChannel::C7,
+            #[cfg(feature = "l4")]
+            Self::Usart1Tx => DmaChannel::C6,
+            #[cfg(feature = "l4")]
+            Self::Lpuart1Rx => DmaChannel::C7,
+            #[cfg(feature = "l4")]
+            Self::Lpuart1Tx => DmaChannel::C6,
+            #[cfg(feature = "l4")]
+            Self::I2c1Rx => DmaChannel::C6,
+            #[cfg(feature = "l4")]
+            Self::I2c1Tx => DmaChannel::C7,
+            _ => panic!("{:?} has no DMA2 channel on this MCU; use DMA1.", self),
+        }
+    }
+
+    #[cfg(any(feature = "f3", feature = "l4"))]
+    /// Select the hard set channel associated with a given input source, on the DMA controller
+    /// used. See `dma1_channel` and `dma2_channel`.
+    pub fn fixed_channel(&self, periph: DmaPeriph) -> DmaChannel {
+        match periph {
+            DmaPeriph::Dma1 => self.dma1_channel(),
+            #[cfg(not(feature = "f3x4"))]
+            DmaPeriph::Dma2 => self.dma2_channel(),
+        }
+    }
+
     #[cfg(feature = "l4")]
     /// Find the value to set in the DMA_CSELR register, for L4. Ie, channel select value for a given DMA input.
     /// See L44 RM, Table 41.
@@ -352,6 +657,204 @@ impl DmaInput {
             _ => unimplemented!(),
         }
     }
+
+    #[cfg(feature = "l4")]
+    /// Find the value to set in DMA2's CSELR register, for L4. See L44 RM, Table 42. Panics if the
+    /// input isn't served by DMA2.
+    pub fn dma2_channel_select(&self) -> u8 {
+        match self {
+            Self::Sai1A => 0b001,
+            Self::Sai1B => 0b001,
+            Self::Uart4Rx => 0b010,
+            Self::Uart4Tx => 0b010,
+            Self::Uart5Rx => 0b010,
+            Self::Uart5Tx => 0b010,
+            Self::Usart1Rx => 0b010,
+            Self::Usart1Tx => 0b010,
+            Self::Spi3Rx => 0b011,
+            Self::Spi3Tx => 0b011,
+            Self::Adc1 => 0b000,
+            Self::Adc2 => 0b000,
+            Self::Spi1Rx => 0b100,
+            Self::Spi1Tx => 0b100,
+            Self::Lpuart1Rx => 0b100,
+            Self::Lpuart1Tx => 0b100,
+            Self::I2c1Rx => 0b101,
+            Self::I2c1Tx => 0b101,
+            _ => panic!(
+                "{:?} has no DMA2 channel select value on this MCU; use DMA1.",
+                self
+            ),
+        }
+    }
+
+    #[cfg(feature = "f4")]
+    /// Find the value to set in a stream's CR.CHSEL field, for F4. Ie, the channel that connects
+    /// a given DMA input to a stream. Note that each input is only available on certain streams, on
+    /// either DMA1 or DMA2. See RM0090, Tables 42 and 43, or RM0383, Tables 27 and 28. Returns `None`
+    /// for inputs that aren't listed there.
+    pub fn chsel(&self) -> Option<u8> {
+        match self {
+            // DMA2, streams 0 and 4.
+            Self::Adc1 => Some(0),
+            // DMA1, stream 5.
+            Self::Dac1Ch1 => Some(7),
+            // DMA1, stream 6.
+            Self::Dac1Ch2 => Some(7),
+            // DMA1, stream 1.
+            Self::Tim6Up => Some(7),
+            // DMA1, streams 2 and 4.
+            Self::Tim7Up => Some(1),
+            // DMA2, streams 0 and 2.
+            Self::Spi1Rx => Some(3),
+            // DMA2, streams 3 and 5.
+            Self::Spi1Tx => Some(3),
+            // DMA1, stream 3.
+            Self::Spi2Rx => Some(0),
+            // DMA1, stream 4.
+            Self::Spi2Tx => Some(0),
+            // DMA1, streams 0 and 2.
+            Self::Spi3Rx => Some(0),
+            // DMA1, streams 5 and 7.
+            Self::Spi3Tx => Some(0),
+            // DMA1, streams 0 and 5.
+            Self::I2c1Rx => Some(1),
+            // DMA1, streams 6 and 7.
+            Self::I2c1Tx => Some(1),
+            // DMA1, streams 2 and 3.
+            Self::I2c2Rx => Some(7),
+            // DMA1, stream 7.
+            Self::I2c2Tx => Some(7),
+            // DMA1, stream 2.
+            Self::I2c3Rx => Some(3),
+            // DMA1, stream 4.
+            Self::I2c3Tx => Some(3),
+            // DMA2, streams 2 and 5.
+            Self::Usart1Rx => Some(4),
+            // DMA2, stream 7.
+            Self::Usart1Tx => Some(4),
+            // DMA1, stream 5.
+            Self::Usart2Rx => Some(4),
+            // DMA1, stream 6.
+            Self::Usart2Tx => Some(4),
+            // DMA1, stream 1.
+            Self::Usart3Rx => Some(4),
+            // DMA1, stream 3. (Also stream 4, on channel 7)
+            Self::Usart3Tx => Some(4),
+            // DMA1, stream 2.
+            Self::Uart4Rx => Some(4),
+            // DMA1, stream 4.
+            Self::Uart4Tx => Some(4),
+            // DMA1, stream 0.
+            Self::Uart5Rx => Some(4),
+            // DMA1, stream 7.
+            Self::Uart5Tx => Some(4),
+            // DMA2, streams 2 and 3.
+            Self::Adc2 => Some(1),
+            // DMA2, streams 0 and 1.
+            Self::Adc3 => Some(2),
+            // DMA2, streams 1, 2 (CH1 and CH2), 3, 4 (CH4, TRIG and COM), 5 (UP), and 6.
+            Self::Tim1Ch1
+            | Self::Tim1Ch2
+            | Self::Tim1Ch3
+            | Self::Tim1Ch4
+            | Self::TimUp
+            | Self::Tim1Trig
+            | Self::Tim1Com => Some(6),
+            // DMA2, streams 1 (UP), 2, 3, 4, and 7 (CH4, TRIG and COM).
+            Self::Tim8Ch1
+            | Self::Tim8Ch2
+            | Self::Tim8Ch3
+            | Self::Tim8Ch4
+            | Self::Tim8Up
+            | Self::Tim8Trig
+            | Self::Tim8Com => Some(7),
+            // DMA1, streams 1 (CH3, UP), 5 (CH1), 6 (CH2, CH4), and 7 (UP, CH4).
+            Self::Tim2Ch1 | Self::Tim2Ch2 | Self::Tim2Ch3 | Self::Tim2Ch4 | Self::Tim2Up => Some(3),
+            // DMA1, streams 2 (CH4, UP), 4 (CH1, TRIG), 5 (CH2), and 7 (CH3).
+            Self::Tim3Ch1
+            | Self::Tim3Ch2
+            | Self::Tim3Ch3
+            | Self::Tim3Ch4
+            | Self::Tim3Up
+            | Self::Tim3Trig => Some(5),
+            // DMA1, streams 0 (CH1), 3 (CH2), 6 (UP) and 7 (CH3).
+            Self::Tim4Ch1 | Self::Tim4Ch2 | Self::Tim4Ch3 | Self::Tim4Up => Some(2),
+            // DMA2, streams 1 and 3 (SAI1_A), and 5 (SAI1_B) on F42x and F43x.
+            Self::Sai1A | Self::Sai1B => Some(0),
+            // DMA2, stream 7 on F446.
+            Self::Quadspi => Some(3),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "f4")]
+    /// The DMA controller, and streams that can serve a given input, using the `chsel()` channel.
+    /// See RM0090, Tables 42 and 43. Returns no streams for inputs not listed there.
+    pub fn streams(&self) -> (DmaPeriph, &'static [DmaChannel]) {
+        use DmaChannel::*;
+        use DmaPeriph::*;
+
+        match self {
+            Self::Adc1 => (Dma2, &[C0, C4]),
+            Self::Dac1Ch1 => (Dma1, &[C5]),
+            Self::Dac1Ch2 => (Dma1, &[C6]),
+            Self::Tim6Up => (Dma1, &[C1]),
+            Self::Tim7Up => (Dma1, &[C2, C4]),
+            Self::Spi1Rx => (Dma2, &[C0, C2]),
+            Self::Spi1Tx => (Dma2, &[C3, C5]),
+            Self::Spi2Rx => (Dma1, &[C3]),
+            Self::Spi2Tx => (Dma1, &[C4]),
+            Self::Spi3Rx => (Dma1, &[C0, C2]),
+            Self::Spi3Tx => (Dma1, &[C5, C7]),
+            Self::I2c1Rx => (Dma1, &[C0, C5]),
+            Self::I2c1Tx => (Dma1, &[C6, C7]),
+            Self::I2c2Rx => (Dma1, &[C2, C3]),
+            Self::I2c2Tx => (Dma1, &[C7]),
+            Self::I2c3Rx => (Dma1, &[C2]),
+            Self::I2c3Tx => (Dma1, &[C4]),
+            Self::Usart1Rx => (Dma2, &[C2, C5]),
+            Self::Usart1Tx => (Dma2, &[C7]),
+            Self::Usart2Rx => (Dma1, &[C5]),
+            Self::Usart2Tx => (Dma1, &[C6]),
+            Self::Usart3Rx => (Dma1, &[C1]),
+            Self::Usart3Tx => (Dma1, &[C3]),
+            Self::Uart4Rx => (Dma1, &[C2]),
+            Self::Uart4Tx => (Dma1, &[C4]),
+            Self::Uart5Rx => (Dma1, &[C0]),
+            Self::Uart5Tx => (Dma1, &[C7]),
+            Self::Adc2 => (Dma2, &[C2, C3]),
+            Self::Adc3 => (Dma2, &[C0, C1]),
+            Self::Tim1Ch1 => (Dma2, &[C1, C3]),
+            Self::Tim1Ch2 => (Dma2, &[C2]),
+            Self::Tim1Ch3 => (Dma2, &[C6]),
+            Self::Tim1Ch4 | Self::Tim1Com => (Dma2, &[C4]),
+            Self::Tim1Trig => (Dma2, &[C0, C4]),
+            Self::TimUp => (Dma2, &[C5]),
+            Self::Tim8Up => (Dma2, &[C1]),
+            Self::Tim8Ch1 => (Dma2, &[C2]),
+            Self::Tim8Ch2 => (Dma2, &[C3]),
+            Self::Tim8Ch3 => (Dma2, &[C4]),
+            Self::Tim8Ch4 | Self::Tim8Trig | Self::Tim8Com => (Dma2, &[C7]),
+            Self::Tim2Ch1 => (Dma1, &[C5]),
+            Self::Tim2Ch2 => (Dma1, &[C6]),
+            Self::Tim2Ch3 => (Dma1, &[C1]),
+            Self::Tim2Ch4 => (Dma1, &[C6, C7]),
+            Self::Tim2Up => (Dma1, &[C1, C7]),
+            Self::Tim3Ch1 | Self::Tim3Trig => (Dma1, &[C4]),
+            Self::Tim3Ch2 => (Dma1, &[C5]),
+            Self::Tim3Ch3 => (Dma1, &[C7]),
+            Self::Tim3Ch4 | Self::Tim3Up => (Dma1, &[C2]),
+            Self::Tim4Ch1 => (Dma1, &[C0]),
+            Self::Tim4Ch2 => (Dma1, &[C3]),
+            Self::Tim4Ch3 => (Dma1, &[C7]),
+            Self::Tim4Up => (Dma1, &[C6]),
+            Self::Sai1A => (Dma2, &[C1, C3]),
+            Self::Sai1B => (Dma2, &[C5]),
+            Self::Quadspi => (Dma2, &[C7]),
+            _ => (Dma1, &[]),
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -381,7 +884,7 @@ pub enum Priority {
 /// adopt H7's approach)
 pub enum DmaChannel {
     // H7 calls these Streams. We use the `Channel` name for consistency.
-    #[cfg(feature = "h7")]
+    #[cfg(any(feature = "f4", feature = "h7"))]
     C0 = 0,
     C1 = 1,
     C2 = 2,
@@ -439,6 +942,42 @@ pub enum DataSize {
     S32 = 0b10,
 }
 
+#[cfg(any(feature = "f4", feature = "h7"))]
+#[derive(Copy, Clone)]
+#[repr(u8)]
+/// FIFO threshold level, as a fraction of the 4-word FIFO. Set in the FCR register's FTH field.
+/// Can only be set when the stream is disabled.
+pub enum FifoThresh {
+    Quarter = 0b00,
+    Half = 0b01,
+    ThreeQuarters = 0b10,
+    Full = 0b11,
+}
+
+#[cfg(any(feature = "f4", feature = "h7"))]
+#[derive(Copy, Clone)]
+#[repr(u8)]
+/// Memory and peripheral burst transfer configuration. Set in the CR register's MBURST and
+/// PBURST fields. Bursts are only available when the FIFO is enabled. Can only be set when
+/// the stream is disabled.
+pub enum Burst {
+    Single = 0b00,
+    Incr4 = 0b01,
+    Incr8 = 0b10,
+    Incr16 = 0b11,
+}
+
+/// DMA error
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DmaError {
+    /// The DMA input can't be served by this controller. (F4)
+    UnsupportedInput,
+    /// A transfer error occurred, eg a bus error from an invalid address. The channel is disabled
+    /// by hardware. (TEIF)
+    Transfer,
+}
+
 #[derive(Copy, Clone)]
 /// Interrupt type. Set in CCR using TEIE, HTIE, and TCIE bits.
 /// Can only be set when channel is disabled.
@@ -446,16 +985,16 @@ pub enum DmaInterrupt {
     TransferError,
     HalfTransfer,
     TransferComplete,
-    #[cfg(feature = "h7")]
+    #[cfg(any(feature = "f4", feature = "h7"))]
     DirectModeError,
-    #[cfg(feature = "h7")]
+    #[cfg(any(feature = "f4", feature = "h7"))]
     FifoError,
 }
 
 /// Reduce DRY over channels when configuring a channel's CCR.
 /// We must use a macro here, since match arms balk at the incompatible
 /// types of `CCR1`, `CCR2` etc.
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "f4", feature = "h7")))]
 macro_rules! set_ccr {
     ($ccr:expr, $priority:expr, $direction:expr, $circular:expr, $periph_incr:expr, $mem_incr:expr, $periph_size:expr, $mem_size:expr) => {
         // "The register fields/bits MEM2MEM, PL[1:0], MSIZE[1:0], PSIZE[1:0], MINC, PINC, and DIR
@@ -470,13 +1009,20 @@ macro_rules! set_ccr {
             $ccr.modify(|_, w| w.mem2mem().clear_bit());
         }
 
+        // "The circular mode must not be used in memory-to-memory mode."
+        let mem2mem = matches!($direction, Direction::MemToMem) && $circular == Circular::Disabled;
+
         $ccr.modify(|_, w| unsafe {
             // – the channel priority
             w.pl().bits($priority as u8);
+            // – memory-to-memory mode. The channel starts transferring as soon as it's enabled,
+            // without waiting for a peripheral request.
+            w.mem2mem().bit(mem2mem);
             // – the data transfer direction
             // This bit [DIR] must be set only in memory-to-peripheral and peripheral-to-memory modes.
-            // 0: read from peripheral
-            w.dir().bit($direction as u8 != 0);
+            // 0: read from peripheral. "This is still valid in a memory-to-memory mode", ie the
+            // peripheral address is the source.
+            w.dir().bit(matches!($direction, Direction::ReadFromMem));
             // – the circular mode
             w.circ().bit($circular as u8 != 0);
             // – the peripheral and memory incremented mode
@@ -499,7 +1045,7 @@ macro_rules! set_ccr {
 }
 
 /// Reduce DRY over channels when configuring a channel's interrupts.
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "f4", feature = "h7")))]
 macro_rules! enable_interrupt {
     ($ccr:expr, $interrupt_type:expr) => {
         // "It must not be written when the channel is enabled (EN = 1)."
@@ -523,7 +1069,7 @@ macro_rules! enable_interrupt {
 }
 
 /// Reduce DRY over channels when configuring a channel's interrupts.
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "f4", feature = "h7")))]
 macro_rules! disable_interrupt {
     ($ccr:expr, $interrupt_type:expr) => {
         // "It must not be written when the channel is disabled (EN = 1)."
@@ -563,6 +1109,57 @@ pub struct ChannelCfg {
     /// Whether we increment the buffer address on data word transfer; generally (and by default)
     /// enabled.
     pub mem_incr: IncrMode,
+    /// FIFO threshold. If `None`, the FIFO is disabled, and the stream operates in direct mode,
+    /// where each DMA request transfers one data item. Defaults to `None`.
+    #[cfg(any(feature = "f4", feature = "h7"))]
+    pub fifo_thresh: Option<FifoThresh>,
+    /// Memory burst size. Must be `Single` in direct mode. Defaults to `Single`.
+    #[cfg(any(feature = "f4", feature = "h7"))]
+    pub mem_burst: Burst,
+    /// Peripheral burst size. Must be `Single` in direct mode. Defaults to `Single`.
+    #[cfg(any(feature = "f4", feature = "h7"))]
+    pub periph_burst: Burst,
+    /// Address of a second memory buffer, to enable double-buffer mode. The stream alternates
+    /// between the buffer passed when starting the transfer (M0AR) and this one (M1AR), each time
+    /// one is full, and runs in circular mode. Both buffers must be the same length. Use
+    /// `set_inactive_buf` to swap in a new buffer while the other is in use. Defaults to `None`.
+    /// Ignored by the `_owned` DMA methods, since the `Transfer` doesn't own the second buffer.
+    #[cfg(any(feature = "f4", feature = "h7"))]
+    pub double_buf: Option<u32>,
+    /// Secure and privileged attributes of the channel, when TrustZone is enabled. Defaults to
+    /// non-secure, and unprivileged.
+    #[cfg(feature = "l5")]
+    pub security: ChannelSecurity,
+}
+
+#[cfg(feature = "l5")]
+#[derive(Clone, Default)]
+/// Secure and privileged channel attributes; set in the CCR SECM, SSEC, DSEC and PRIV bits.
+/// The secure bits can only be written by secure software, and are ignored otherwise. See
+/// L552 RM, section 12.4.2: DMA secure/non-secure channel.
+pub struct ChannelSecurity {
+    /// The channel is secure. (SECM)
+    pub secure: bool,
+    /// Source transfers are secure. Only applies to secure channels. (SSEC)
+    pub src_secure: bool,
+    /// Destination transfers are secure. Only applies to secure channels. (DSEC)
+    pub dest_secure: bool,
+    /// The channel is privileged. (PRIV)
+    pub privileged: bool,
+}
+
+impl ChannelCfg {
+    /// This config, with double-buffer mode disabled. Used by the `_owned` DMA methods, since a raw
+    /// second buffer address isn't safe to pass through them.
+    pub(crate) fn single_buf(self) -> Self {
+        #[cfg(any(feature = "f4", feature = "h7"))]
+        return Self {
+            double_buf: None,
+            ..self
+        };
+        #[cfg(not(any(feature = "f4", feature = "h7")))]
+        self
+    }
 }
 
 impl Default for ChannelCfg {
@@ -573,6 +1170,16 @@ impl Default for ChannelCfg {
             // Increment the buffer address, not the peripheral address.
             periph_incr: IncrMode::Disabled,
             mem_incr: IncrMode::Enabled,
+            #[cfg(any(feature = "f4", feature = "h7"))]
+            fifo_thresh: None,
+            #[cfg(any(feature = "f4", feature = "h7"))]
+            mem_burst: Burst::Single,
+            #[cfg(any(feature = "f4", feature = "h7"))]
+            periph_burst: Burst::Single,
+            #[cfg(any(feature = "f4", feature = "h7"))]
+            double_buf: None,
+            #[cfg(feature = "l5")]
+            security: Default::default(),
         }
     }
 }
@@ -658,11 +1265,6 @@ where
         )
     }
 
-    #[cfg(feature = "l4")]
-    pub(crate) fn channel_select(&mut self, input: DmaInput) {
-        channel_select(&mut self.regs, input);
-    }
-
     /// Stop a DMA transfer, if in progress.
     pub fn stop(&mut self, channel: DmaChannel) {
         stop_internal(&mut self.regs, channel);
@@ -674,7 +1276,7 @@ where
     }
 
     // todo: G0 removed from this fn due to a bug introduced in PAC 0.13
-    #[cfg(not(any(feature = "h7", feature = "g0")))]
+    #[cfg(not(any(feature = "f4", feature = "h7", feature = "g0")))]
     pub fn transfer_is_complete(&mut self, channel: DmaChannel) -> bool {
         let isr_val = self.regs.isr.read();
         match channel {
@@ -692,7 +1294,7 @@ where
         }
     }
 
-    #[cfg(feature = "h7")]
+    #[cfg(any(feature = "f4", feature = "h7"))]
     pub fn transfer_is_complete(&mut self, channel: DmaChannel) -> bool {
         match channel {
             DmaChannel::C0 => self.regs.lisr.read().tcif0().bit_is_set(),
@@ -713,7 +1315,7 @@ where
 
     /// Disable a specific type of interrupt.
     /// todo: Non-H7 version too!
-    #[cfg(feature = "h7")]
+    #[cfg(any(feature = "f4", feature = "h7"))]
     pub fn disable_interrupt(&mut self, channel: DmaChannel, interrupt: DmaInterrupt) {
         // Can only be set when the channel is disabled.
         // todo: Is this true for disabling interrupts true, re the channel must be disabled?
@@ -743,9 +1345,29 @@ where
     }
 }
 
+/// Spacing between a channel's registers, and the next channel's, on L5.
+#[cfg(feature = "l5")]
+const L5_CHANNEL_STRIDE: usize = 0x14;
+
+/// Get a channel's CCR register, on L5. This works around a PAC error, where the registers of
+/// channels 2-8 have the fields of their neighbours. (eg `ccr2` has the `ndt` field). Their addresses
+/// are correct, so we use channel 1's register types, offset by the channel spacing.
+#[cfg(feature = "l5")]
+fn ccr_l5(regs: &dma1::RegisterBlock, channel: DmaChannel) -> &dma1::CCR1 {
+    let offset = L5_CHANNEL_STRIDE * (channel as usize - 1);
+    unsafe { &*((&regs.ccr1 as *const _ as *const u8).add(offset) as *const dma1::CCR1) }
+}
+
+/// Get a channel's CNDTR register, on L5. See `ccr_l5`.
+#[cfg(feature = "l5")]
+fn cndtr_l5(regs: &dma1::RegisterBlock, channel: DmaChannel) -> &dma1::CNDTR1 {
+    let offset = L5_CHANNEL_STRIDE * (channel as usize - 1);
+    unsafe { &*((&regs.cndtr1 as *const _ as *const u8).add(offset) as *const dma1::CNDTR1) }
+}
+
 /// Configure a DMA channel. See L4 RM 0394, section 11.4.4. Sets the Transfer Complete
 /// interrupt. This is the function called by various module `read_dma` and `write_dma` functions.
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "f4", feature = "h7")))]
 pub fn cfg_channel<D>(
     regs: &mut D,
     channel: DmaChannel,
@@ -761,6 +1383,11 @@ pub fn cfg_channel<D>(
 {
     // See the comments in the H7 variant for a description of what's going on.
 
+    // Disable the channel, so its registers can be written, then clear any flags left over from a
+    // previous transfer.
+    stop_internal(regs, channel);
+    clear_flags_internal(regs, channel);
+
     unsafe {
         match channel {
             DmaChannel::C1 => {
@@ -943,10 +1570,13 @@ pub fn cfg_channel<D>(
         }
     }
 
-    #[cfg(any(feature = "l5", feature = "wl"))]
+    #[cfg(feature = "wl")]
     let num_data = num_data as u32;
 
-    #[cfg(not(feature = "l5"))] // todo: PAC ommission? ndt fields missing for diff ndt regs.
+    #[cfg(feature = "l5")]
+    cndtr_l5(regs, channel).write(|w| unsafe { w.ndt().bits(num_data as u32) });
+
+    #[cfg(not(feature = "l5"))]
     unsafe {
         match channel {
             DmaChannel::C1 => {
@@ -1029,6 +1659,34 @@ pub fn cfg_channel<D>(
         }
     }
 
+    #[cfg(feature = "l5")]
+    {
+        let ccr = ccr_l5(regs, channel);
+
+        // "SECM, SSEC, DSEC and PRIV are read-only when EN = 1"
+        ccr.modify(|_, w| w.en().clear_bit());
+        while ccr.read().en().bit_is_set() {}
+
+        ccr.modify(|_, w| {
+            w.secm().bit(cfg.security.secure);
+            w.ssec().bit(cfg.security.src_secure);
+            w.dsec().bit(cfg.security.dest_secure);
+            w.priv_().bit(cfg.security.privileged)
+        });
+
+        set_ccr!(
+            ccr,
+            cfg.priority,
+            direction,
+            cfg.circular,
+            cfg.periph_incr,
+            cfg.mem_incr,
+            periph_size,
+            mem_size
+        );
+    }
+
+    #[cfg(not(feature = "l5"))]
     match channel {
         DmaChannel::C1 => {
             cfg_if! {
@@ -1184,13 +1842,14 @@ pub fn cfg_channel<D>(
 
 /// Configure a DMA channel. See L4 RM 0394, section 11.4.4. Sets the Transfer Complete
 /// interrupt. This is the function called by various module `read_dma` and `write_dma` functions.
-#[cfg(feature = "h7")]
+#[cfg(any(feature = "f4", feature = "h7"))]
 pub fn cfg_channel<D>(
     regs: &mut D,
     channel: DmaChannel,
     periph_addr: u32,
     mem_addr: u32,
-    num_data: u32,
+    #[cfg(feature = "f4")] num_data: u16,
+    #[cfg(feature = "h7")] num_data: u32,
     direction: Direction,
     periph_size: DataSize,
     mem_size: DataSize,
@@ -1206,6 +1865,11 @@ pub fn cfg_channel<D>(
         .modify(|_, w| w.en().clear_bit());
     while regs.st[channel as usize].cr.read().en().bit_is_set() {}
 
+    // RM0090, section 10.3.17: "All the stream dedicated bits set in the status register (DMA_LISR
+    // and DMA_HISR) from the previous data block DMA transfer should be cleared before the stream
+    // can be re-enabled."
+    clear_flags_internal(regs, channel);
+
     // H743 RM Section 15.3.19 The following sequence is needed to configure a DMA stream x:
     // 1. Set the peripheral register address in the DMA_CPARx register.
     // The data is moved from/to this address to/from the memory after the peripheral event,
@@ -1223,14 +1887,35 @@ pub fn cfg_channel<D>(
         .m0ar
         .write(|w| unsafe { w.bits(mem_addr) });
 
-    // todo: m1ar too, if in double-buffer mode.
+    // In double-buffer mode, the second buffer's address goes in DMA_SxM1AR.
+    if let Some(addr) = cfg.double_buf {
+        regs.st[channel as usize]
+            .m1ar
+            .write(|w| unsafe { w.bits(addr) });
+    }
 
     // 3. Configure the total number of data to transfer in the DMA_CNDTRx register.
     // After each data transfer, this value is decremented.
+    #[cfg(feature = "f4")]
+    regs.st[channel as usize]
+        .ndtr
+        .write(|w| w.ndt().bits(num_data));
+    #[cfg(feature = "h7")]
     regs.st[channel as usize]
         .ndtr
         .write(|w| unsafe { w.bits(num_data) });
 
+    // "When memory-to-memory mode is used, the Circular and direct modes are not allowed."
+    let mem2mem = matches!(direction, Direction::MemToMem);
+
+    // Configure the FIFO. In direct mode (The default), each peripheral request transfers
+    // a single data item; the FIFO threshold, and bursts aren't used.
+    regs.st[channel as usize].fcr.modify(|_, w| {
+        w.dmdis().bit(cfg.fifo_thresh.is_some() || mem2mem);
+        w.fth()
+            .bits(cfg.fifo_thresh.unwrap_or(FifoThresh::Half) as u8)
+    });
+
     // 4. Configure the parameters listed below in the DMA_CCRx register:
     // (These are listed below by their corresponding reg write code)
 
@@ -1254,8 +1939,6 @@ pub fn cfg_channel<D>(
 
     // (See remainder of steps in `set_ccr()!` macro.
 
-    // todo: Let user set mem2mem mode?
-
     // See the [Embedonomicon section on DMA](https://docs.rust-embedded.org/embedonomicon/dma.html)
     // for info on why we use `compiler_fence` here:
     // "We use Ordering::Release to prevent all preceding memory operations from being moved
@@ -1274,16 +1957,24 @@ pub fn cfg_channel<D>(
         w.pl().bits(cfg.priority as u8);
         // – the data transfer direction
         // This bit [DIR] must be set only in memory-to-peripheral and peripheral-to-memory modes.
-        // 0: read from peripheral
+        // 0: read from peripheral. 0b10: memory-to-memory, with the peripheral address as the source.
         w.dir().bits(direction as u8);
-        // – the circular mode
-        w.circ().bit(cfg.circular as u8 != 0);
+        // – the circular mode. "When the double-buffer mode is enabled, the circular mode is
+        // automatically enabled"; we set it explicitly to match.
+        w.circ()
+            .bit(!mem2mem && (cfg.circular as u8 != 0 || cfg.double_buf.is_some()));
+        // – double-buffer mode, starting with the M0AR buffer as the current target.
+        w.dbm().bit(!mem2mem && cfg.double_buf.is_some());
+        w.ct().clear_bit();
         // – the peripheral and memory incremented mode
         w.pinc().bit(cfg.periph_incr as u8 != 0);
         w.minc().bit(cfg.mem_incr as u8 != 0);
         // – the peripheral and memory data size
         w.psize().bits(periph_size as u8);
         w.msize().bits(mem_size as u8);
+        // – the memory and peripheral burst sizes. These must be single transfers in direct mode.
+        w.mburst().bits(cfg.mem_burst as u8);
+        w.pburst().bits(cfg.periph_burst as u8);
         // – the interrupt enable at half and/or full transfer and/or transfer error
         w.tcie().set_bit();
         // (See `Step 5` above.)
@@ -1297,7 +1988,7 @@ pub fn cfg_channel<D>(
 }
 
 /// Stop a DMA transfer, if in progress.
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "f4", feature = "h7")))]
 fn stop_internal<D>(regs: &mut D, channel: DmaChannel)
 where
     D: Deref<Target = dma1::RegisterBlock>,
@@ -1309,6 +2000,14 @@ where
     // To correctly stop and disable a channel, the software clears the EN bit of the DMA_CCRx
     // register.
 
+    #[cfg(feature = "l5")]
+    {
+        let ccr = ccr_l5(regs, channel);
+        ccr.modify(|_, w| w.en().clear_bit());
+        while ccr.read().en().bit_is_set() {}
+    }
+
+    #[cfg(not(feature = "l5"))]
     match channel {
         DmaChannel::C1 => {
             cfg_if! {
@@ -1412,7 +2111,7 @@ where
 }
 
 /// Stop a DMA transfer, if in progress.
-#[cfg(feature = "h7")]
+#[cfg(any(feature = "f4", feature = "h7"))]
 fn stop_internal<D>(regs: &mut D, channel: DmaChannel)
 where
     D: Deref<Target = dma1::RegisterBlock>,
@@ -1457,6 +2156,82 @@ pub fn stop(periph: DmaPeriph, channel: DmaChannel) {
     }
 }
 
+#[cfg(any(feature = "f4", feature = "h7"))]
+#[derive(Copy, Clone, PartialEq)]
+/// In double-buffer mode, the buffer the stream is currently transferring to or from.
+/// Read from the CR register, CT field.
+pub enum BufTarget {
+    /// The buffer at the M0AR address; ie the one passed when starting the transfer.
+    Buf0,
+    /// The buffer at the M1AR address; ie `ChannelCfg::double_buf`.
+    Buf1,
+}
+
+#[cfg(any(feature = "f4", feature = "h7"))]
+fn current_target_internal<D>(regs: &D, channel: DmaChannel) -> BufTarget
+where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    if regs.st[channel as usize].cr.read().ct().bit_is_set() {
+        BufTarget::Buf1
+    } else {
+        BufTarget::Buf0
+    }
+}
+
+#[cfg(any(feature = "f4", feature = "h7"))]
+fn set_inactive_buf_internal<D>(regs: &D, channel: DmaChannel, addr: u32)
+where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    // H743 RM, section 15.3.10: "A write to the DMA_SxM0AR register is possible only when the
+    // CT bit is set to 1, [and] a write to the DMA_SxM1AR register is possible only when the CT
+    // bit is set to 0." Otherwise, the stream is disabled, and a transfer error is flagged.
+    let st = &regs.st[channel as usize];
+    match current_target_internal(regs, channel) {
+        BufTarget::Buf0 => st.m1ar.write(|w| unsafe { w.bits(addr) }),
+        BufTarget::Buf1 => st.m0ar.write(|w| unsafe { w.bits(addr) }),
+    }
+}
+
+#[cfg(any(feature = "f4", feature = "h7"))]
+/// In double-buffer mode, returns the buffer the stream is currently using. The other one
+/// may be read, or replaced with `set_inactive_buf`.
+pub fn current_target(periph: DmaPeriph, channel: DmaChannel) -> BufTarget {
+    match periph {
+        DmaPeriph::Dma1 => {
+            let regs = unsafe { &(*DMA1::ptr()) };
+            current_target_internal(&regs, channel)
+        }
+        DmaPeriph::Dma2 => {
+            let regs = unsafe { &(*pac::DMA2::ptr()) };
+            current_target_internal(&regs, channel)
+        }
+    }
+}
+
+#[cfg(any(feature = "f4", feature = "h7"))]
+/// In double-buffer mode, point the buffer not currently in use to a new address; the stream
+/// switches to it once the current buffer is full. Run this shortly after the transfer complete
+/// interrupt, so it's done well before the switch. The new buffer must be the same length as
+/// the original ones.
+///
+/// This is unsafe, since the DMA stream writes to, or reads from `addr` without any checks: It must
+/// point to a buffer of the transfer's length, that stays valid until the stream is stopped, or
+/// switches away from it.
+pub unsafe fn set_inactive_buf(periph: DmaPeriph, channel: DmaChannel, addr: u32) {
+    match periph {
+        DmaPeriph::Dma1 => {
+            let regs = unsafe { &(*DMA1::ptr()) };
+            set_inactive_buf_internal(&regs, channel, addr);
+        }
+        DmaPeriph::Dma2 => {
+            let regs = unsafe { &(*pac::DMA2::ptr()) };
+            set_inactive_buf_internal(&regs, channel, addr);
+        }
+    }
+}
+
 fn clear_interrupt_internal<D>(regs: &mut D, channel: DmaChannel, interrupt: DmaInterrupt)
 where
     D: Deref<Target = dma1::RegisterBlock>,
@@ -1506,7 +2281,7 @@ where
                     DmaInterrupt::TransferComplete => w.tcif8().set_bit(),
                 }
             });
-        } else if #[cfg(feature = "h7")] {
+        } else if #[cfg(any(feature = "f4", feature = "h7"))] {
             match channel {
                 DmaChannel::C0 => match interrupt {
                     DmaInterrupt::TransferError => regs.lifcr.write(|w| w.cteif0().set_bit()),
@@ -1617,12 +2392,19 @@ where
 }
 
 /// Enable an interrupt.
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "f4", feature = "h7")))]
 fn enable_interrupt_internal<D>(regs: &mut D, channel: DmaChannel, interrupt: DmaInterrupt)
 where
     D: Deref<Target = dma1::RegisterBlock>,
 {
     // Can only be set when the channel is disabled.
+    #[cfg(feature = "l5")]
+    {
+        let ccr = ccr_l5(regs, channel);
+        enable_interrupt!(ccr, interrupt);
+    }
+
+    #[cfg(not(feature = "l5"))]
     match channel {
         DmaChannel::C1 => {
             cfg_if! {
@@ -1705,12 +2487,19 @@ where
 }
 
 /// Disable an interrupt.
-#[cfg(not(feature = "h7"))]
+#[cfg(not(any(feature = "f4", feature = "h7")))]
 fn disable_interrupt_internal<D>(regs: &mut D, channel: DmaChannel, interrupt: DmaInterrupt)
 where
     D: Deref<Target = dma1::RegisterBlock>,
 {
     // Can only be set when the channel is disabled.
+    #[cfg(feature = "l5")]
+    {
+        let ccr = ccr_l5(regs, channel);
+        disable_interrupt!(ccr, interrupt);
+    }
+
+    #[cfg(not(feature = "l5"))]
     match channel {
         DmaChannel::C1 => {
             cfg_if! {
@@ -1792,7 +2581,7 @@ where
     };
 }
 
-#[cfg(feature = "h7")]
+#[cfg(any(feature = "f4", feature = "h7"))]
 fn enable_interrupt_internal<D>(regs: &mut D, channel: DmaChannel, interrupt: DmaInterrupt)
 where
     D: Deref<Target = dma1::RegisterBlock>,
@@ -1811,7 +2600,7 @@ where
     }
 }
 
-#[cfg(feature = "h7")]
+#[cfg(any(feature = "f4", feature = "h7"))]
 fn disable_interrupt_internal<D>(regs: &mut D, channel: DmaChannel, interrupt: DmaInterrupt)
 where
     D: Deref<Target = dma1::RegisterBlock>,
@@ -1877,97 +2666,765 @@ pub fn clear_interrupt(periph: DmaPeriph, channel: DmaChannel, interrupt: DmaInt
     }
 }
 
-#[cfg(any(
-    feature = "l5",
-    feature = "g0",
-    feature = "g4",
-    feature = "h7",
-    feature = "wb",
-    feature = "wl",
-))]
-/// Configure a specific DMA channel to work with a specific peripheral.
-pub fn mux(periph: DmaPeriph, channel: DmaChannel, input: DmaInput) {
-    // Note: This is similar in API and purpose to `channel_select` above,
-    // for different families. We're keeping it as a separate function instead
-    // of feature-gating within the same function so the name can be recognizable
-    // from the RM etc.
-
-    // G4 example:
-    // "The mapping of resources to DMAMUX is hardwired.
-    // DMAMUX is used with DMA1 and DMA2:
-    // For category 3 and category 4 devices:
-    // •
-    // DMAMUX channels 0 to 7 are connected to DMA1 channels 1 to 8
-    // •
-    // DMAMUX channels 8 to 15 are connected to DMA2 channels 1 to 8
-    // For category 2 devices:
-    // •
-    // DMAMUX channels 0 to 5 are connected to DMA1 channels 1 to 6
-    // •
-    // DMAMUX channels 6 to 11 are connected to DMA2 channels 1 to 6"
-    //
-    // H723/25/33/35"
-    // DMAMUX1 is used with DMA1 and DMA2 in D2 domain
-    // •
-    // DMAMUX1 channels 0 to 7 are connected to DMA1 channels 0 to 7
-    // •
-    // DMAMUX1 channels 8 to 15 are connected to DMA2 channels 0 to 7
-    // (Note: The H7 and G4 cat 3/4 mappings are the same, except for H7's use of 0-7, and G4's use of 1-8.)
-
-    // todo: With this in mind, some of the mappings below are not correct on some G4 variants.
-
-    unsafe {
-        let mux = unsafe { &(*DMAMUX::ptr()) };
+/// Offsets of a DMAv1 channel's CCR, CNDTR, CPAR, and CMAR registers from the start of its
+/// `RegisterBlock`, for channel 1. Each following channel's registers are `CHANNEL_STRIDE` further.
+#[cfg(not(any(feature = "f4", feature = "h7")))]
+const CCR_OFFSET: usize = 0x08;
+#[cfg(not(any(feature = "f4", feature = "h7")))]
+const CNDTR_OFFSET: usize = 0x0c;
+#[cfg(not(any(feature = "f4", feature = "h7")))]
+const CPAR_OFFSET: usize = 0x10;
+#[cfg(not(any(feature = "f4", feature = "h7")))]
+const CMAR_OFFSET: usize = 0x14;
+#[cfg(not(any(feature = "f4", feature = "h7")))]
+const CHANNEL_STRIDE: usize = 0x14;
+
+/// Get a pointer to one of a DMAv1 channel's registers. We use this where the register names and
+/// types differ between families and PACs, eg `ccr1` vs `ch1.cr`, and `cmar1` vs `cm0ar1`.
+#[cfg(not(any(feature = "f4", feature = "h7")))]
+fn chan_reg(regs: &dma1::RegisterBlock, channel: DmaChannel, offset: usize) -> *mut u32 {
+    let offset = offset + CHANNEL_STRIDE * (channel as usize - 1);
+    (regs as *const _ as *const u8).wrapping_add(offset) as *mut u32
+}
 
-        match periph {
-            DmaPeriph::Dma1 => {
-                #[cfg(not(feature = "h7"))]
-                match channel {
-                    // Note the offset by 1, due to mismatch in DMA channels starting at 1, and DMAMUX
-                    // channels starting at 0. Ops tested this is correct on G4.
-                    DmaChannel::C1 => mux.c0cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C2 => mux.c1cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C3 => mux.c2cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C4 => mux.c3cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C5 => mux.c4cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    #[cfg(not(feature = "g0"))]
-                    DmaChannel::C6 => mux.c5cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    #[cfg(not(feature = "g0"))]
-                    DmaChannel::C7 => mux.c6cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    #[cfg(any(feature = "l5", feature = "g4"))]
-                    DmaChannel::C8 => mux.c7cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                }
+/// Read a channel's ISR flags, shifted so the channel's first flag is at bit 0. On F4 and H7,
+/// these are FEIF, _, DMEIF, TEIF, HTIF, TCIF. On other families, GIF, TCIF, HTIF, TEIF.
+/// We read the raw bits, since the flag fields' names differ between families and PACs.
+fn isr_flags_internal<D>(regs: &D, channel: DmaChannel) -> u32
+where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    cfg_if! {
+        if #[cfg(any(feature = "f4", feature = "h7"))] {
+            // Streams 0-3 are in LISR, and 4-7 in HISR, at bits 0, 6, 16, and 22.
+            let (isr, i) = if (channel as u8) < 4 {
+                (regs.lisr.read().bits(), channel as usize)
+            } else {
+                (regs.hisr.read().bits(), channel as usize - 4)
+            };
+            (isr >> [0, 6, 16, 22][i]) & 0b11_1111
+        } else {
+            // Each channel uses 4 bits, from channel 1.
+            (regs.isr.read().bits() >> ((channel as u8 - 1) * 4)) & 0b1111
+        }
+    }
+}
 
-                #[cfg(feature = "h7")]
-                mux.ccr[channel as usize].modify(|_, w| w.dmareq_id().bits(input as u8));
-            }
+/// Clear all of a channel's flags. On F4 and H7: TCIF, HTIF, TEIF, DMEIF, and FEIF. On other
+/// families, GIF, TCIF, HTIF, and TEIF. Flags left over from a previous transfer would otherwise
+/// be read as the new transfer's.
+fn clear_flags_internal<D>(regs: &D, channel: DmaChannel)
+where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    cfg_if! {
+        if #[cfg(any(feature = "f4", feature = "h7"))] {
+            // Streams 0-3 are in LIFCR, and 4-7 in HIFCR, at bits 0, 6, 16, and 22.
+            if (channel as u8) < 4 {
+                let bits = 0b11_1101 << [0, 6, 16, 22][channel as usize];
+                regs.lifcr.write(|w| unsafe { w.bits(bits) });
+            } else {
+                let bits = 0b11_1101 << [0, 6, 16, 22][channel as usize - 4];
+                regs.hifcr.write(|w| unsafe { w.bits(bits) });
+            }
+        } else {
+            // Each channel uses 4 bits, from channel 1.
+            regs.ifcr
+                .write(|w| unsafe { w.bits(0b1111 << ((channel as u8 - 1) * 4)) });
+        }
+    }
+}
+
+/// Returns true if a channel's transfer is complete, or was ended by a transfer error.
+fn transfer_ended_internal<D>(regs: &D, channel: DmaChannel) -> bool
+where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    cfg_if! {
+        if #[cfg(any(feature = "f4", feature = "h7"))] {
+            // TEIF, and TCIF.
+            isr_flags_internal(regs, channel) & 0b10_1000 != 0
+        } else {
+            // TCIF, and TEIF.
+            isr_flags_internal(regs, channel) & 0b1010 != 0
+        }
+    }
+}
+
+fn transfer_error_internal<D>(regs: &D, channel: DmaChannel) -> bool
+where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    // TEIF is bit 3 on all families.
+    isr_flags_internal(regs, channel) & 0b1000 != 0
+}
+
+/// Returns true if a channel's transfer was ended by a transfer error.
+pub fn transfer_error(periph: DmaPeriph, channel: DmaChannel) -> bool {
+    match periph {
+        DmaPeriph::Dma1 => {
+            let regs = unsafe { &(*DMA1::ptr()) };
+            transfer_error_internal(&regs, channel)
+        }
+        #[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+        DmaPeriph::Dma2 => {
+            let regs = unsafe { &(*pac::DMA2::ptr()) };
+            transfer_error_internal(&regs, channel)
+        }
+    }
+}
+
+/// Returns true if a channel's transfer is complete, or was ended by a transfer error. Useful for
+/// checking completion of transfers started without a `Transfer` handle, eg with `mem_copy`.
+pub fn transfer_ended(periph: DmaPeriph, channel: DmaChannel) -> bool {
+    match periph {
+        DmaPeriph::Dma1 => {
+            let regs = unsafe { &(*DMA1::ptr()) };
+            transfer_ended_internal(&regs, channel)
+        }
+        #[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+        DmaPeriph::Dma2 => {
+            let regs = unsafe { &(*pac::DMA2::ptr()) };
+            transfer_ended_internal(&regs, channel)
+        }
+    }
+}
+
+/// Returns the number of data items a channel has left to transfer, from the NDTR register.
+fn remaining_internal<D>(regs: &D, channel: DmaChannel) -> u16
+where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    cfg_if! {
+        if #[cfg(any(feature = "f4", feature = "h7"))] {
+            regs.st[channel as usize].ndtr.read().ndt().bits()
+        } else {
+            unsafe { chan_reg(regs, channel, CNDTR_OFFSET).read_volatile() as u16 }
+        }
+    }
+}
+
+/// Returns the number of data items a channel has left to transfer. This is read from the
+/// NDTR register, which is decremented after each item is transferred. In circular mode, it's
+/// reloaded with the buffer length each time it reaches 0.
+pub fn remaining(periph: DmaPeriph, channel: DmaChannel) -> u16 {
+    match periph {
+        DmaPeriph::Dma1 => {
+            let regs = unsafe { &(*DMA1::ptr()) };
+            remaining_internal(&regs, channel)
+        }
+        #[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+        DmaPeriph::Dma2 => {
+            let regs = unsafe { &(*pac::DMA2::ptr()) };
+            remaining_internal(&regs, channel)
+        }
+    }
+}
+
+/// Set a stopped channel's peripheral address, memory address, and number of data items, then
+/// enable it. Its other settings are left as they were configured by `cfg_channel`.
+fn restart_internal<D>(
+    regs: &D,
+    channel: DmaChannel,
+    periph_addr: u32,
+    mem_addr: u32,
+    num_data: u16,
+) where
+    D: Deref<Target = dma1::RegisterBlock>,
+{
+    cfg_if! {
+        if #[cfg(any(feature = "f4", feature = "h7"))] {
+            let st = &regs.st[channel as usize];
+            clear_flags_internal(regs, channel);
+            st.par.write(|w| unsafe { w.bits(periph_addr) });
+            st.m0ar.write(|w| unsafe { w.bits(mem_addr) });
+            st.ndtr.write(|w| unsafe { w.bits(num_data as u32) });
+            st.cr.modify(|_, w| w.en().set_bit());
+        } else {
+            clear_flags_internal(regs, channel);
+            unsafe {
+                chan_reg(regs, channel, CPAR_OFFSET).write_volatile(periph_addr);
+                chan_reg(regs, channel, CMAR_OFFSET).write_volatile(mem_addr);
+                chan_reg(regs, channel, CNDTR_OFFSET).write_volatile(num_data as u32);
+
+                // CCR bit 0 is EN.
+                let ccr = chan_reg(regs, channel, CCR_OFFSET);
+                ccr.write_volatile(ccr.read_volatile() | 1);
+            }
+        }
+    }
+}
+
+/// A DMA transfer in progress. It owns the buffer, the peripheral it's transferring to or
+/// from, and the channel token, so none of them can be accessed, moved, or reused while the DMA
+/// controller is using them. Created by the `_owned` DMA methods of peripheral modules, eg
+/// `Usart::write_dma_owned`. `T` is the channel token, or a pair of tokens for transfers that use
+/// two channels.
+///
+/// Dropping a `Transfer` before it completes aborts it. For circular transfers, use `abort`
+/// to stop the transfer, and get the buffer, peripheral, and token back.
+pub struct Transfer<P, B, T = ChannelToken> {
+    /// `None` once the buffer, peripheral, and token have been returned.
+    resources: Option<(B, P, T)>,
+    dma_periph: DmaPeriph,
+    channel: DmaChannel,
+    /// An optional second channel, eg for the reception side of a full-duplex transfer.
+    channel2: Option<DmaChannel>,
+    /// Peripheral-specific cleanup run when the transfer ends, eg disabling DMA requests.
+    cleanup: Option<fn(&mut P)>,
+}
+
+impl<P, B> Transfer<P, B> {
+    /// Track a transfer that has already been started on `token`'s channel.
+    pub(crate) fn new(periph: P, buf: B, token: ChannelToken) -> Self {
+        Self {
+            dma_periph: token.periph,
+            channel: token.channel,
+            channel2: None,
+            resources: Some((buf, periph, token)),
+            cleanup: None,
+        }
+    }
+}
+
+impl<P, B> Transfer<P, B, (ChannelToken, ChannelToken)> {
+    /// Track a transfer that has already been started on two channels of the same controller.
+    pub(crate) fn new_pair(periph: P, buf: B, token: ChannelToken, token2: ChannelToken) -> Self {
+        Self {
+            dma_periph: token.periph,
+            channel: token.channel,
+            channel2: Some(token2.channel),
+            resources: Some((buf, periph, (token, token2))),
+            cleanup: None,
+        }
+    }
+}
+
+impl<P, B, T> Transfer<P, B, T> {
+    /// Set peripheral-specific cleanup, to run when the transfer ends or is aborted.
+    pub(crate) fn with_cleanup(mut self, cleanup: fn(&mut P)) -> Self {
+        self.cleanup = Some(cleanup);
+        self
+    }
+
+    /// Returns true if the transfer is complete, or was ended by a transfer error.
+    pub fn is_done(&self) -> bool {
+        transfer_ended(self.dma_periph, self.channel)
+            && self
+                .channel2
+                .map(|ch| transfer_ended(self.dma_periph, ch))
+                .unwrap_or(true)
+    }
+
+    /// Returns true if the transfer was ended by a transfer error.
+    pub fn is_error(&self) -> bool {
+        transfer_error(self.dma_periph, self.channel)
+            || self
+                .channel2
+                .map(|ch| transfer_error(self.dma_periph, ch))
+                .unwrap_or(false)
+    }
+
+    /// Block until the transfer is complete, then return the buffer, peripheral, and token. If it
+    /// was ended by a transfer error, they're returned with the error.
+    pub fn wait(mut self) -> Result<(B, P, T), (DmaError, B, P, T)> {
+        while !self.is_done() {}
+
+        // `finish` clears the flags.
+        let error = self.is_error();
+        let (buf, periph, token) = self.finish();
+
+        if error {
+            Err((DmaError::Transfer, buf, periph, token))
+        } else {
+            Ok((buf, periph, token))
+        }
+    }
+
+    /// Stop the transfer, whether or not it's complete, and return the buffer, peripheral, and
+    /// token.
+    pub fn abort(mut self) -> (B, P, T) {
+        self.finish()
+    }
+
+    /// Stop the channels, clear their flags, and run the peripheral's cleanup.
+    fn finish(&mut self) -> (B, P, T) {
+        for channel in [Some(self.channel), self.channel2].into_iter().flatten() {
+            stop(self.dma_periph, channel);
+            clear_interrupt(self.dma_periph, channel, DmaInterrupt::TransferComplete);
+            clear_interrupt(self.dma_periph, channel, DmaInterrupt::TransferError);
+        }
+
+        // Prevent reads of the buffer from being moved before the transfer is stopped.
+        atomic::compiler_fence(Ordering::Acquire);
+
+        // `resources` is only taken here, and `finish` is only called once.
+        let (buf, mut periph, token) = self.resources.take().unwrap();
+        if let Some(cleanup) = self.cleanup {
+            cleanup(&mut periph);
+        }
+
+        (buf, periph, token)
+    }
+}
+
+impl<P, B, T> Drop for Transfer<P, B, T> {
+    fn drop(&mut self) {
+        if self.resources.is_some() {
+            self.finish();
+        }
+    }
+}
+
+/// A data word that can be copied by DMA in memory-to-memory mode. Sets the transfer's
+/// PSIZE and MSIZE.
+pub trait DmaWord: Copy {
+    const SIZE: DataSize;
+}
+
+impl DmaWord for u8 {
+    const SIZE: DataSize = DataSize::S8;
+}
+
+impl DmaWord for u16 {
+    const SIZE: DataSize = DataSize::S16;
+}
+
+impl DmaWord for u32 {
+    const SIZE: DataSize = DataSize::S32;
+}
+
+/// Start a memory-to-memory transfer of `len` words from `src_addr` to `dest_addr`. If `src_incr`
+/// is false, the same source word is read repeatedly; ie a fill.
+fn start_mem_to_mem<W: DmaWord>(
+    src_addr: u32,
+    dest_addr: u32,
+    len: usize,
+    src_incr: bool,
+    dma_periph: DmaPeriph,
+    channel: DmaChannel,
+    mut channel_cfg: ChannelCfg,
+) {
+    // NDTR is 16 bits, on all families.
+    assert!(
+        len <= u16::MAX as usize,
+        "Memory-to-memory transfers are limited to 65,535 words."
+    );
+
+    // F4 RM, section 9.3.6: "Only the DMA2 controller is able to perform memory-to-memory
+    // transfers."
+    #[cfg(feature = "f4")]
+    assert!(
+        matches!(dma_periph, DmaPeriph::Dma2),
+        "On F4, only DMA2 can perform memory-to-memory transfers."
+    );
+
+    #[cfg(feature = "h7")]
+    let num_data = len as u32;
+    #[cfg(not(feature = "h7"))]
+    let num_data = len as u16;
+
+    // In memory-to-memory mode, the peripheral address is the source.
+    channel_cfg.periph_incr = if src_incr {
+        IncrMode::Enabled
+    } else {
+        IncrMode::Disabled
+    };
+    channel_cfg.mem_incr = IncrMode::Enabled;
+    channel_cfg.circular = Circular::Disabled;
+    #[cfg(any(feature = "f4", feature = "h7"))]
+    {
+        channel_cfg.double_buf = None;
+    }
+
+    match dma_periph {
+        DmaPeriph::Dma1 => {
+            let mut regs = unsafe { &(*DMA1::ptr()) };
+            cfg_channel(
+                &mut regs,
+                channel,
+                src_addr,
+                dest_addr,
+                num_data,
+                Direction::MemToMem,
+                W::SIZE,
+                W::SIZE,
+                channel_cfg,
+            );
+        }
+        #[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+        DmaPeriph::Dma2 => {
+            let mut regs = unsafe { &(*pac::DMA2::ptr()) };
+            cfg_channel(
+                &mut regs,
+                channel,
+                src_addr,
+                dest_addr,
+                num_data,
+                Direction::MemToMem,
+                W::SIZE,
+                W::SIZE,
+                channel_cfg,
+            );
+        }
+    }
+}
+
+/// Copy `src` into `dest` using a DMA channel in memory-to-memory mode, without CPU involvement;
+/// eg for frame buffers. The transfer starts immediately; no peripheral request, or `mux()` is
+/// required. Check for completion with `transfer_ended()`, or the Transfer Complete interrupt.
+/// `src` and `dest` must be the same length; this is limited to 65,535 words.
+/// Circular and double-buffer settings in `channel_cfg` are ignored, since they're not allowed in
+/// this mode. On F4, only DMA2 can perform memory-to-memory transfers; this panics if `channel`
+/// is on DMA1.
+pub unsafe fn mem_copy<W: DmaWord>(
+    src: &[W],
+    dest: &mut [W],
+    channel: &ChannelToken,
+    channel_cfg: ChannelCfg,
+) {
+    assert_eq!(src.len(), dest.len());
+
+    start_mem_to_mem::<W>(
+        src.as_ptr() as u32,
+        dest.as_mut_ptr() as u32,
+        dest.len(),
+        true,
+        channel.periph(),
+        channel.channel(),
+        channel_cfg,
+    );
+}
+
+/// Set every word of `dest` to `value`, using a DMA channel in memory-to-memory mode; eg to clear
+/// a buffer. `value` must remain valid until the transfer is complete. See `mem_copy` for details.
+pub unsafe fn mem_fill<W: DmaWord>(
+    value: &W,
+    dest: &mut [W],
+    channel: &ChannelToken,
+    channel_cfg: ChannelCfg,
+) {
+    start_mem_to_mem::<W>(
+        value as *const W as u32,
+        dest.as_mut_ptr() as u32,
+        dest.len(),
+        false,
+        channel.periph(),
+        channel.channel(),
+        channel_cfg,
+    );
+}
+
+/// Copy `src` into `dest` using DMA, taking ownership of both for the duration of the transfer.
+/// Use the returned `Transfer` to check for completion, and to get `dest` (the buffer), `src`,
+/// and the channel token back. See `mem_copy` for details.
+pub fn mem_copy_owned<W: DmaWord>(
+    src: &'static [W],
+    dest: &'static mut [W],
+    channel: ChannelToken,
+    channel_cfg: ChannelCfg,
+) -> Transfer<&'static [W], &'static mut [W]> {
+    unsafe { mem_copy(src, dest, &channel, channel_cfg) };
+
+    Transfer::new(src, dest, channel)
+}
+
+/// Set every word of `dest` to `value` using DMA, taking ownership of both for the duration of
+/// the transfer. Use the returned `Transfer` to check for completion, and to get `dest` (the
+/// buffer), `value`, and the channel token back. See `mem_fill` for details.
+pub fn mem_fill_owned<W: DmaWord>(
+    value: &'static W,
+    dest: &'static mut [W],
+    channel: ChannelToken,
+    channel_cfg: ChannelCfg,
+) -> Transfer<&'static W, &'static mut [W]> {
+    unsafe { mem_fill(value, dest, &channel, channel_cfg) };
+
+    Transfer::new(value, dest, channel)
+}
+
+/// Channels available for `claim_free_channel()` to pick from, on each controller.
+const CHANNELS: &[DmaChannel] = &[
+    #[cfg(any(feature = "f4", feature = "h7"))]
+    DmaChannel::C0,
+    DmaChannel::C1,
+    DmaChannel::C2,
+    DmaChannel::C3,
+    DmaChannel::C4,
+    DmaChannel::C5,
+    #[cfg(not(feature = "g0"))]
+    DmaChannel::C6,
+    #[cfg(not(feature = "g0"))]
+    DmaChannel::C7,
+    #[cfg(any(feature = "l5", feature = "g4"))]
+    DmaChannel::C8,
+];
+
+/// Channels that have been claimed, and not released; one bit per channel, for DMA1 and DMA2.
+static CLAIMED: Mutex<Cell<[u16; 2]>> = Mutex::new(Cell::new([0; 2]));
+
+/// A claimed DMA channel, on a specific controller. Only one token can exist for a given channel
+/// at a time, so two drivers can't be set up to use the same one. Pass this to peripheral DMA
+/// methods, eg `Usart::write_dma`. Create using `claim_channel()`, `claim_free_channel()`, or
+/// `claim_channel_for()`.
+///
+/// The unsafe DMA methods only borrow the token, so they don't prevent starting a second transfer
+/// on a channel that's still in use. The `_owned` variants take it by value, and return it from
+/// `Transfer::wait` or `Transfer::abort`.
+pub struct ChannelToken {
+    periph: DmaPeriph,
+    channel: DmaChannel,
+}
+
+impl ChannelToken {
+    /// Create a token for a channel, without claiming it. This bypasses the check that the
+    /// channel isn't already in use elsewhere.
+    pub unsafe fn steal(periph: DmaPeriph, channel: DmaChannel) -> Self {
+        Self { periph, channel }
+    }
+
+    /// The DMA controller this channel is on.
+    pub fn periph(&self) -> DmaPeriph {
+        self.periph
+    }
+
+    pub fn channel(&self) -> DmaChannel {
+        self.channel
+    }
+}
+
+/// Claim a specific DMA channel. Returns `None` if it's already been claimed.
+pub fn claim_channel(periph: DmaPeriph, channel: DmaChannel) -> Option<ChannelToken> {
+    let bit = 1 << channel as u16;
+
+    interrupt::free(|cs| {
+        let claimed = CLAIMED.borrow(cs);
+        let mut val = claimed.get();
+
+        if val[periph as usize] & bit != 0 {
+            return None;
+        }
+
+        val[periph as usize] |= bit;
+        claimed.set(val);
+
+        Some(ChannelToken { periph, channel })
+    })
+}
+
+/// Claim the lowest-numbered channel on a DMA controller that hasn't been claimed. Returns `None`
+/// if they're all in use.
+pub fn claim_free_channel(periph: DmaPeriph) -> Option<ChannelToken> {
+    // On F3 and L4, DMA2 only has channels 1 - 5.
+    #[cfg(all(any(feature = "f3", feature = "l4"), not(feature = "f3x4")))]
+    let channels = match periph {
+        DmaPeriph::Dma1 => CHANNELS,
+        DmaPeriph::Dma2 => &CHANNELS[..5],
+    };
+    #[cfg(not(all(any(feature = "f3", feature = "l4"), not(feature = "f3x4"))))]
+    let channels = CHANNELS;
+
+    channels.iter().find_map(|ch| claim_channel(periph, *ch))
+}
+
+/// Release a claimed channel, so it can be claimed again. Stop its transfer first, if one is
+/// in progress.
+pub fn release_channel(token: ChannelToken) {
+    interrupt::free(|cs| {
+        let claimed = CLAIMED.borrow(cs);
+        let mut val = claimed.get();
+        val[token.periph as usize] &= !(1 << token.channel as u16);
+        claimed.set(val);
+    });
+}
+
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Claim a free channel, on DMA1 if available, otherwise DMA2, and route `input` to it
+/// using `mux()`. Returns `None` if no channels are free.
+pub fn claim_channel_for(input: DmaInput) -> Option<ChannelToken> {
+    let token = claim_free_channel(DmaPeriph::Dma1);
+    #[cfg(not(any(
+        all(feature = "g0", not(any(feature = "g0b1", feature = "g0c1"))),
+        feature = "wb"
+    )))]
+    let token = token.or_else(|| claim_free_channel(DmaPeriph::Dma2));
+
+    let token = token?;
+    mux(token.periph, token.channel, input);
+
+    Some(token)
+}
+
+#[cfg(feature = "f4")]
+/// Claim the first free stream that can serve `input`, and select the input on it with
+/// `channel_select()`. Returns `None` if they're all claimed, or if `input` isn't listed in
+/// `DmaInput::streams`.
+pub fn claim_channel_for(input: DmaInput) -> Option<ChannelToken> {
+    let (periph, streams) = input.streams();
+    let token = streams.iter().find_map(|ch| claim_channel(periph, *ch))?;
+
+    // `streams()` only lists inputs that have a CHSEL value.
+    channel_select(periph, token.channel, input).ok();
+
+    Some(token)
+}
+
+#[cfg(any(feature = "f3", feature = "l4"))]
+/// Claim the DMA1 channel hard-set for `input`, and on L4, select the input on it. Returns `None`
+/// if the channel's already been claimed. To use DMA2 instead, eg if the DMA1 channel is busy,
+/// claim `input.dma2_channel()` on DMA2 with `claim_channel`.
+pub fn claim_channel_for(input: DmaInput) -> Option<ChannelToken> {
+    let token = claim_channel(DmaPeriph::Dma1, input.dma1_channel())?;
+
+    #[cfg(feature = "l4")]
+    channel_select(DmaPeriph::Dma1, input);
+
+    Some(token)
+}
+
+#[cfg(any(feature = "f3", feature = "l4"))]
+/// Check that `token` is for `channel` on `periph`, the one hard-wired to a peripheral's request
+/// on F3 and L4, and return the channel. Panics if it isn't, since the transfer would run on a
+/// channel the token doesn't own.
+pub(crate) fn check_fixed_channel(
+    token: &ChannelToken,
+    periph: DmaPeriph,
+    channel: DmaChannel,
+) -> DmaChannel {
+    assert!(
+        token.periph as u8 == periph as u8 && token.channel as u8 == channel as u8,
+        "This DMA channel token isn't for the channel hard-wired to the peripheral. Claim it \
+        with `dma::claim_channel_for()`."
+    );
+    channel
+}
+
+cfg_if! {
+    // The DMAMUX channel DMA2's first channel connects to; ie the number of DMA1 channels.
+    if #[cfg(any(feature = "g431", feature = "g441"))] {
+        // G4 category 2: DMAMUX channels 6 to 11 are connected to DMA2 channels 1 to 6.
+        const DMAMUX_DMA2_OFFSET: usize = 6;
+    } else if #[cfg(any(feature = "g0b1", feature = "g0c1", feature = "wl"))] {
+        // DMAMUX channels 7 to 13 are connected to DMA2 channels 1 to 7. (G0B1/G0C1: 1 to 5)
+        const DMAMUX_DMA2_OFFSET: usize = 7;
+    } else if #[cfg(any(feature = "l5", feature = "g4", feature = "h7"))] {
+        const DMAMUX_DMA2_OFFSET: usize = 8;
+    }
+    // Other G0 variants, and WB, don't have DMA2 on the DMAMUX.
+}
+
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Modify the DMAMUX CxCR register associated with a DMA channel. Most PACs expose these as
+/// individual fields instead of an array, so we match on the DMAMUX channel number. DMA1 channels
+/// map to the first DMAMUX channels, and DMA2 channels follow them.
+macro_rules! modify_mux_ccr {
+    ($mux:expr, $periph:expr, $channel:expr, $f:expr) => {
+        #[cfg(feature = "h7")]
+        let mux_ch = $channel as usize;
+        // Note the offset by 1, due to DMA channels starting at 1, and DMAMUX channels at 0.
+        #[cfg(not(feature = "h7"))]
+        let mux_ch = $channel as usize - 1;
+
+        let mux_ch = match $periph {
+            DmaPeriph::Dma1 => mux_ch,
             #[cfg(not(any(
                 all(feature = "g0", not(any(feature = "g0b1", feature = "g0c1"))),
                 feature = "wb"
             )))]
-            DmaPeriph::Dma2 => {
-                #[cfg(not(feature = "h7"))]
-                match channel {
-                    DmaChannel::C1 => mux.c8cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C2 => mux.c9cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C3 => mux.c10cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C4 => mux.c11cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    DmaChannel::C5 => mux.c12cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    #[cfg(not(feature = "g0"))]
-                    DmaChannel::C6 => mux.c13cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    #[cfg(not(any(feature = "g0", feature = "wb", feature = "wl")))]
-                    DmaChannel::C7 => mux.c14cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                    #[cfg(any(feature = "wb", feature = "wl"))]
-                    DmaChannel::C7 => (), // Maybe no channel 7 on DMA2 on these platforms.
-                    #[cfg(any(feature = "l5", feature = "g4"))]
-                    DmaChannel::C8 => mux.c15cr.modify(|_, w| w.dmareq_id().bits(input as u8)),
-                }
+            DmaPeriph::Dma2 => mux_ch + DMAMUX_DMA2_OFFSET,
+        };
 
-                #[cfg(feature = "h7")]
-                mux.ccr[channel as usize + 8].modify(|_, w| w.dmareq_id().bits(input as u8));
-            }
+        #[cfg(feature = "h7")]
+        $mux.ccr[mux_ch].modify($f);
+
+        #[cfg(not(feature = "h7"))]
+        match mux_ch {
+            0 => $mux.c0cr.modify($f),
+            1 => $mux.c1cr.modify($f),
+            2 => $mux.c2cr.modify($f),
+            3 => $mux.c3cr.modify($f),
+            4 => $mux.c4cr.modify($f),
+            5 => $mux.c5cr.modify($f),
+            6 => $mux.c6cr.modify($f),
+            #[cfg(not(feature = "g0"))]
+            7 => $mux.c7cr.modify($f),
+            #[cfg(not(feature = "g0"))]
+            8 => $mux.c8cr.modify($f),
+            #[cfg(not(feature = "g0"))]
+            9 => $mux.c9cr.modify($f),
+            #[cfg(not(feature = "g0"))]
+            10 => $mux.c10cr.modify($f),
+            #[cfg(not(feature = "g0"))]
+            11 => $mux.c11cr.modify($f),
+            #[cfg(not(feature = "g0"))]
+            12 => $mux.c12cr.modify($f),
+            #[cfg(not(feature = "g0"))]
+            13 => $mux.c13cr.modify($f),
+            #[cfg(any(feature = "l5", feature = "g4"))]
+            14 => $mux.c14cr.modify($f),
+            #[cfg(any(feature = "l5", feature = "g4"))]
+            15 => $mux.c15cr.modify($f),
+            _ => panic!("This DMAMUX channel isn't available on this MCU."),
         }
-    }
+    };
+}
+
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Configure a specific DMA channel to work with a specific peripheral.
+pub fn mux(periph: DmaPeriph, channel: DmaChannel, input: DmaInput) {
+    // Note: This is similar in API and purpose to `channel_select` above,
+    // for different families. We're keeping it as a separate function instead
+    // of feature-gating within the same function so the name can be recognizable
+    // from the RM etc.
+
+    // G4 example:
+    // "The mapping of resources to DMAMUX is hardwired.
+    // DMAMUX is used with DMA1 and DMA2:
+    // For category 3 and category 4 devices:
+    // •
+    // DMAMUX channels 0 to 7 are connected to DMA1 channels 1 to 8
+    // •
+    // DMAMUX channels 8 to 15 are connected to DMA2 channels 1 to 8
+    // For category 2 devices:
+    // •
+    // DMAMUX channels 0 to 5 are connected to DMA1 channels 1 to 6
+    // •
+    // DMAMUX channels 6 to 11 are connected to DMA2 channels 1 to 6"
+    //
+    // H723/25/33/35"
+    // DMAMUX1 is used with DMA1 and DMA2 in D2 domain
+    // •
+    // DMAMUX1 channels 0 to 7 are connected to DMA1 channels 0 to 7
+    // •
+    // DMAMUX1 channels 8 to 15 are connected to DMA2 channels 0 to 7
+    // (Note: The H7 and G4 cat 3/4 mappings are the same, except for H7's use of 0-7, and G4's use of 1-8.)
+
+    let mux = unsafe { &(*DMAMUX::ptr()) };
+
+    modify_mux_ccr!(mux, periph, channel, |_, w| unsafe {
+        w.dmareq_id().bits(input as u8)
+    });
 }
 
 #[cfg(feature = "h7")]
@@ -1976,6 +3433,158 @@ pub fn mux2(periph: DmaPeriph, channel: DmaChannel, input: DmaInput2, mux: &mut
     mux.ccr[channel as usize].modify(|_, w| unsafe { w.dmareq_id().bits(input as u8) });
 }
 
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Synchronize a DMA channel's requests with a DMAMUX synchronization input, eg an EXTI line or
+/// LPTIM output. After each synchronization event, `num_requests` requests are forwarded to the
+/// DMA channel, then the channel waits for the next event. If `event_gen` is set, the DMAMUX
+/// channel generates an event (`DmamuxEvtx`) each time `num_requests` requests have been
+/// forwarded; this can be used as a synchronization or trigger input for other channels.
+/// Run `mux()` for this channel, then this function, before starting the transfer.
+/// `num_requests` must be between 1 and 32. See G4 RM, section 13.3.3: DMAMUX request line multiplexer.
+pub fn mux_sync(
+    periph: DmaPeriph,
+    channel: DmaChannel,
+    sync: DmaSync,
+    polarity: SyncPolarity,
+    num_requests: u8,
+    event_gen: bool,
+) {
+    assert!(
+        (1..=32).contains(&num_requests),
+        "`num_requests` must be between 1 and 32."
+    );
+
+    let mux = unsafe { &(*DMAMUX::ptr()) };
+
+    // "NBREQ[4:0]: Number of DMA requests minus 1 to forward. [...] This field must only be
+    // written when both SE and EGE bits are low."
+    modify_mux_ccr!(mux, periph, channel, |_, w| {
+        w.se().clear_bit();
+        w.ege().clear_bit()
+    });
+
+    modify_mux_ccr!(mux, periph, channel, |_, w| unsafe {
+        w.sync_id().bits(sync as u8);
+        w.spol().bits(polarity as u8);
+        w.nbreq().bits(num_requests - 1)
+    });
+
+    modify_mux_ccr!(mux, periph, channel, |_, w| {
+        w.se().set_bit();
+        w.ege().bit(event_gen)
+    });
+}
+
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Stop synchronizing a DMA channel's requests, and stop its event generation, if set
+/// up with `mux_sync()`.
+pub fn mux_sync_disable(periph: DmaPeriph, channel: DmaChannel) {
+    let mux = unsafe { &(*DMAMUX::ptr()) };
+
+    modify_mux_ccr!(mux, periph, channel, |_, w| {
+        w.se().clear_bit();
+        w.ege().clear_bit()
+    });
+}
+
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Configure and enable a DMAMUX request generator: On each edge of `trigger` matching
+/// `polarity`, it generates `num_requests` DMA requests. Route these to a DMA channel by passing
+/// the associated `DmaInput::ReqGenx` to `mux()`. `num_requests` must be between 1 and 32.
+/// See G4 RM, section 13.3.4: DMAMUX request generator.
+///
+/// For example, to write to a GPIO port's BSRR on an EXTI0 rising edge, without CPU involvement:
+/// run `mux(DmaPeriph::Dma1, DmaChannel::C1, DmaInput::ReqGen0)`, then
+/// `cfg_req_gen(ReqGen::G0, DmaTrigger::Exti0, SyncPolarity::Rising, 1)`, then `gpio::write_dma()`
+/// with that channel.
+pub fn cfg_req_gen(gen: ReqGen, trigger: DmaTrigger, polarity: SyncPolarity, num_requests: u8) {
+    assert!(
+        (1..=32).contains(&num_requests),
+        "`num_requests` must be between 1 and 32."
+    );
+
+    let mux = unsafe { &(*DMAMUX::ptr()) };
+
+    // "GNBREQ[4:0]: Number of DMA requests to be generated (minus 1) [...] This field must
+    // only be written when GE is low."
+    cfg_if! {
+        if #[cfg(feature = "h7")] {
+            let rgcr = &mux.rgcr[gen as usize];
+            rgcr.modify(|_, w| w.ge().clear_bit());
+            rgcr.modify(|_, w| unsafe {
+                w.sig_id().bits(trigger as u8);
+                w.gpol().bits(polarity as u8);
+                w.gnbreq().bits(num_requests - 1)
+            });
+            rgcr.modify(|_, w| w.ge().set_bit());
+        } else {
+            macro_rules! cfg_rgcr {
+                ($rgcr:expr) => {
+                    $rgcr.modify(|_, w| w.ge().clear_bit());
+                    $rgcr.modify(|_, w| unsafe {
+                        w.sig_id().bits(trigger as u8);
+                        w.gpol().bits(polarity as u8);
+                        w.gnbreq().bits(num_requests - 1)
+                    });
+                    $rgcr.modify(|_, w| w.ge().set_bit());
+                };
+            }
+
+            match gen {
+                ReqGen::G0 => { cfg_rgcr!(mux.rg0cr); }
+                ReqGen::G1 => { cfg_rgcr!(mux.rg1cr); }
+                ReqGen::G2 => { cfg_rgcr!(mux.rg2cr); }
+                ReqGen::G3 => { cfg_rgcr!(mux.rg3cr); }
+            }
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "l5",
+    feature = "g0",
+    feature = "g4",
+    feature = "h7",
+    feature = "wb",
+    feature = "wl",
+))]
+/// Disable a DMAMUX request generator.
+pub fn disable_req_gen(gen: ReqGen) {
+    let mux = unsafe { &(*DMAMUX::ptr()) };
+
+    #[cfg(feature = "h7")]
+    mux.rgcr[gen as usize].modify(|_, w| w.ge().clear_bit());
+
+    #[cfg(not(feature = "h7"))]
+    match gen {
+        ReqGen::G0 => mux.rg0cr.modify(|_, w| w.ge().clear_bit()),
+        ReqGen::G1 => mux.rg1cr.modify(|_, w| w.ge().clear_bit()),
+        ReqGen::G2 => mux.rg2cr.modify(|_, w| w.ge().clear_bit()),
+        ReqGen::G3 => mux.rg3cr.modify(|_, w| w.ge().clear_bit()),
+    }
+}
+
 // todo: Enable this for other MCUs as requried
 /// Enable the DMA mux RCC clock. Applicable to some variants, but no others. (H7 and G0 don't use it,
 /// for example)
@@ -1996,15 +3605,17 @@ pub fn enable_mux1() {
 }
 
 #[cfg(feature = "l4")] // Only required on L4
-/// Select which peripheral on a given channel we're using.
-/// See L44 RM, Table 41.
-pub(crate) fn channel_select<D>(regs: &mut D, input: DmaInput)
-where
-    D: Deref<Target = dma1::RegisterBlock>,
-{
+/// Select which peripheral on a given channel we're using, by setting the CSELR register of
+/// the DMA controller used. The channel is the one hard-set for `input` on that controller; see
+/// `DmaInput::fixed_channel`. See L44 RM, Tables 41 and 42.
+pub fn channel_select(periph: DmaPeriph, input: DmaInput) {
+    let (regs, val) = match periph {
+        DmaPeriph::Dma1 => (unsafe { &(*DMA1::ptr()) }, input.dma1_channel_select()),
+        DmaPeriph::Dma2 => (unsafe { &(*DMA2::ptr()) }, input.dma2_channel_select()),
+    };
+
     // todo: Allow selecting channels in pairs to save a write.
-    let val = input.dma1_channel_select();
-    regs.cselr.modify(|_, w| match input.dma1_channel() {
+    regs.cselr.modify(|_, w| match input.fixed_channel(periph) {
         DmaChannel::C1 => w.c1s().bits(val),
         DmaChannel::C2 => w.c2s().bits(val),
         DmaChannel::C3 => w.c3s().bits(val),
@@ -2015,15 +3626,51 @@ where
     });
 }
 
-// todo: Code below is for experimental struct-per-channel API
+#[cfg(feature = "f4")]
+/// Select which peripheral a stream (`channel`) serves, by setting its CR.CHSEL field. Run this
+/// before starting a transfer, eg using a peripheral's `write_dma` or `read_dma` method. Not all
+/// inputs are available on all streams; see `DmaInput::chsel`. This is the F4 equivalent of `mux()`.
+/// Returns an error, without changing the stream, if `input` has no F4 mapping.
+pub fn channel_select(
+    periph: DmaPeriph,
+    channel: DmaChannel,
+    input: DmaInput,
+) -> Result<(), DmaError> {
+    let chsel = input.chsel().ok_or(DmaError::UnsupportedInput)?;
+
+    let regs = match periph {
+        DmaPeriph::Dma1 => unsafe { &(*DMA1::ptr()) },
+        DmaPeriph::Dma2 => unsafe { &(*DMA2::ptr()) },
+    };
+
+    // CHSEL can only be written when the stream is disabled.
+    let cr = &regs.st[channel as usize].cr;
+    cr.modify(|_, w| w.en().clear_bit());
+    while cr.read().en().bit_is_set() {}
+
+    cr.modify(|_, w| w.chsel().bits(chsel));
+
+    Ok(())
+}
+
+// Code below is for the struct-per-channel API. Each struct represents a single channel, so
+// channels on the same DMA controller can be owned by, and moved into, different RTIC tasks.
 macro_rules! make_chan_struct {
     // ($Periph:ident, $PERIPH:ident, $periph:ident, $ch:expr) => {
     ($periph: expr, $ch:expr) => {
         paste! {
-            /// Experimental/WIP channel-based DMA struct.
+            /// Represents a single DMA channel. Stores its transfer settings from `cfg_channel`,
+            /// which are used to pause and resume transfers, and to find the position in circular
+            /// buffers.
             pub struct [<Dma $periph Ch $ch>] {
-                // #[cfg(feature = "h7")]
-                // regs: dma1::st // todo?
+                periph_addr: u32,
+                mem_addr: u32,
+                num_data: u32,
+                /// Number of bytes the peripheral address advances per data item; 0 if not incremented.
+                periph_step: u32,
+                /// Number of bytes the memory address advances per data item; 0 if not incremented.
+                mem_step: u32,
+                circular: bool,
             }
 
             impl [<Dma $periph Ch $ch>] {
@@ -2044,28 +3691,50 @@ macro_rules! make_chan_struct {
                         }
                     }
 
-                    Self { }
+                    Self {
+                        periph_addr: 0,
+                        mem_addr: 0,
+                        num_data: 0,
+                        periph_step: 0,
+                        mem_step: 0,
+                        circular: false,
+                    }
                 }
 
                 fn regs(&self) -> &[<dma $periph>]::RegisterBlock {
                     unsafe { &(*[<DMA $periph>]::ptr())}
                 }
 
-                #[cfg(feature = "h7")]
-                fn ccr(&self) -> &[<dma $periph>]::st::CR {
-                // fn ccr(&self) -> &u8 {
-                    &self.regs().st[$ch].cr
-                }
-
-                #[cfg(not(any(feature = "h7", feature = "f3", feature = "g0")))]
-                fn ccr(&self) -> &[<dma $periph>]::[<CCR $ch>] {
-                    &self.regs().[<ccr $ch>]
-                }
-
-                #[cfg(any(feature = "f3", feature = "g0"))]
-                // fn ccr(&self) -> &[<dma $periph>]::ch::cr {
-                 fn ccr(&self) -> i8 {
-                    &self.regs().[<ch $ch>].cr
+                /// Store the settings needed to pause, resume, and find positions in a transfer.
+                fn store_cfg(
+                    &mut self,
+                    periph_addr: u32,
+                    mem_addr: u32,
+                    num_data: u32,
+                    periph_size: DataSize,
+                    mem_size: DataSize,
+                    cfg: &ChannelCfg,
+                ) {
+                    // On F4 and H7, NDTR counts peripheral-sized items, even when the FIFO packs
+                    // them into different-sized memory items.
+                    let mem_size = if cfg!(any(feature = "f4", feature = "h7")) {
+                        periph_size
+                    } else {
+                        mem_size
+                    };
+
+                    self.periph_addr = periph_addr;
+                    self.mem_addr = mem_addr;
+                    self.num_data = num_data;
+                    self.periph_step = match cfg.periph_incr {
+                        IncrMode::Enabled => 1 << periph_size as u32,
+                        IncrMode::Disabled => 0,
+                    };
+                    self.mem_step = match cfg.mem_incr {
+                        IncrMode::Enabled => 1 << mem_size as u32,
+                        IncrMode::Disabled => 0,
+                    };
+                    self.circular = cfg.circular == Circular::Enabled;
                 }
 
                 #[cfg(not(feature = "h7"))] // due to num_data size diff
@@ -2081,6 +3750,8 @@ macro_rules! make_chan_struct {
                     mem_size: DataSize,
                     cfg: ChannelCfg,
                 ) {
+                    self.store_cfg(periph_addr, mem_addr, num_data as u32, periph_size, mem_size, &cfg);
+
                     cfg_channel(
                         &mut self.regs(),
                         DmaChannel::[<C $ch>],
@@ -2107,6 +3778,8 @@ macro_rules! make_chan_struct {
                     mem_size: DataSize,
                     cfg: ChannelCfg,
                 ) {
+                    self.store_cfg(periph_addr, mem_addr, num_data, periph_size, mem_size, &cfg);
+
                     cfg_channel(
                         &mut self.regs(),
                         DmaChannel::[<C $ch>],
@@ -2122,10 +3795,74 @@ macro_rules! make_chan_struct {
 
                 /// Stop a DMA transfer, if in progress.
                 pub fn stop(&mut self) {
-                    let ccr = self.ccr();
+                    stop_internal(&mut self.regs(), DmaChannel::[<C $ch>]);
+                }
 
-                    ccr.modify(|_, w| w.en().clear_bit());
-                    while ccr.read().en().bit_is_set() {}
+                /// Pause a transfer. Use `resume` to continue it. The remaining count is kept in
+                /// NDTR. Note that on F4 and H7, disabling a stream sets its Transfer Complete flag,
+                /// so the TC interrupt fires, if enabled.
+                pub fn pause(&mut self) {
+                    self.stop();
+                }
+
+                /// Resume a transfer stopped with `pause`. The DMA controller can't resume
+                /// an aborted transfer directly, so we restart it with its addresses advanced past
+                /// the data already transferred, and the remaining count. Circular transfers restart
+                /// from the beginning of the buffer, since the wrap uses the restarted addresses and count.
+                /// Not supported in double-buffer mode.
+                pub fn resume(&mut self) {
+                    let remaining = self.remaining() as u32;
+                    if remaining == 0 && !self.circular {
+                        return;
+                    }
+
+                    // "Before setting EN bit to '1' to start a new transfer, the event flags
+                    // corresponding to the stream in DMA_LISR or DMA_HISR register must be cleared."
+                    self.clear_interrupt(DmaInterrupt::TransferComplete);
+                    self.clear_interrupt(DmaInterrupt::HalfTransfer);
+
+                    let (done, remaining) = if self.circular {
+                        (0, self.num_data)
+                    } else {
+                        // NDTR can exceed `num_data` if the channel was reconfigured elsewhere
+                        // since this was set up; don't underflow in that case.
+                        (self.num_data.saturating_sub(remaining), remaining)
+                    };
+
+                    restart_internal(
+                        &self.regs(),
+                        DmaChannel::[<C $ch>],
+                        self.periph_addr + done * self.periph_step,
+                        self.mem_addr + done * self.mem_step,
+                        remaining as u16,
+                    );
+                }
+
+                /// Returns true if the Transfer Complete flag is set. For circular transfers, this
+                /// is set each time the end of the buffer is reached.
+                pub fn transfer_is_complete(&self) -> bool {
+                    cfg_if! {
+                        if #[cfg(any(feature = "f4", feature = "h7"))] {
+                            isr_flags_internal(&self.regs(), DmaChannel::[<C $ch>]) & 0b10_0000 != 0
+                        } else {
+                            isr_flags_internal(&self.regs(), DmaChannel::[<C $ch>]) & 0b0010 != 0
+                        }
+                    }
+                }
+
+                /// Returns the number of data items left to transfer, from the NDTR register.
+                pub fn remaining(&self) -> u16 {
+                    remaining_internal(&self.regs(), DmaChannel::[<C $ch>])
+                }
+
+                /// For circular transfers, returns the index in the buffer of the next item to be
+                /// transferred. Data before this index (wrapping around the end of the buffer) has
+                /// been written, eg by a peripheral-to-memory transfer.
+                pub fn circular_position(&self) -> usize {
+                    if self.num_data == 0 {
+                        return 0;
+                    }
+                    ((self.num_data - self.remaining() as u32) % self.num_data) as usize
                 }
 
                 /// Enable a specific type of interrupt.
@@ -2133,11 +3870,15 @@ macro_rules! make_chan_struct {
                     enable_interrupt_internal(&mut self.regs(), DmaChannel::[<C $ch>], interrupt);
                 }
 
+                /// Disable a specific type of interrupt.
+                pub fn disable_interrupt(&mut self, interrupt: DmaInterrupt) {
+                    disable_interrupt_internal(&mut self.regs(), DmaChannel::[<C $ch>], interrupt);
+                }
+
                 /// Clear an interrupt flag.
                 pub fn clear_interrupt(&mut self, interrupt: DmaInterrupt) {
                     clear_interrupt_internal(&mut self.regs(), DmaChannel::[<C $ch>], interrupt);
                 }
-                // todo: Other methods.
             }
         }
     };
@@ -2146,39 +3887,35 @@ macro_rules! make_chan_struct {
 // todo: As above, you may need more feature-gating, esp on
 // todo DMA2.
 // Note: G0 is limited, eg for some variants only up to DMA1, ch5.
-cfg_if! {
-    if #[cfg(not(any(feature = "f3", feature = "g0")))] {
-        #[cfg(feature = "h7")]
-        make_chan_struct!(1, 0);
-        make_chan_struct!(1, 1);
-        make_chan_struct!(1, 2);
-        make_chan_struct!(1, 3);
-        make_chan_struct!(1, 4);
-        make_chan_struct!(1, 5);
-        #[cfg(not(feature = "g0"))]
-        make_chan_struct!(1, 6);
-        #[cfg(not(feature = "g0"))]
-        make_chan_struct!(1, 7);
-        #[cfg(any(feature = "l5", feature = "g4"))]
-        make_chan_struct!(1, 8);
-
-        #[cfg(feature = "h7")]
-        make_chan_struct!(2, 0);
-        #[cfg(not(any(feature = "g0", feature = "wb")))]
-        make_chan_struct!(2, 1);
-        #[cfg(not(any(feature = "g0", feature = "wb")))]
-        make_chan_struct!(2, 2);
-        #[cfg(not(any(feature = "g0", feature = "wb")))]
-        make_chan_struct!(2, 3);
-        #[cfg(not(any(feature = "g0", feature = "wb")))]
-        make_chan_struct!(2, 4);
-        #[cfg(not(any(feature = "g0", feature = "wb")))]
-        make_chan_struct!(2, 5);
-        #[cfg(not(any(feature = "g0", feature = "wb")))]
-        make_chan_struct!(2, 6);
-        #[cfg(not(any(feature = "g0", feature = "wb")))]
-        make_chan_struct!(2, 7);
-        #[cfg(any(feature = "l5", feature = "g4"))]
-        make_chan_struct!(2, 8);
-    }
-}
+#[cfg(any(feature = "f4", feature = "h7"))]
+make_chan_struct!(1, 0);
+make_chan_struct!(1, 1);
+make_chan_struct!(1, 2);
+make_chan_struct!(1, 3);
+make_chan_struct!(1, 4);
+make_chan_struct!(1, 5);
+#[cfg(not(feature = "g0"))]
+make_chan_struct!(1, 6);
+#[cfg(not(feature = "g0"))]
+make_chan_struct!(1, 7);
+#[cfg(any(feature = "l5", feature = "g4"))]
+make_chan_struct!(1, 8);
+
+#[cfg(any(feature = "f4", feature = "h7"))]
+make_chan_struct!(2, 0);
+#[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+make_chan_struct!(2, 1);
+#[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+make_chan_struct!(2, 2);
+#[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+make_chan_struct!(2, 3);
+#[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+make_chan_struct!(2, 4);
+#[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+make_chan_struct!(2, 5);
+#[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+make_chan_struct!(2, 6);
+#[cfg(not(any(feature = "f3x4", feature = "g0", feature = "wb")))]
+make_chan_struct!(2, 7);
+#[cfg(any(feature = "l5", feature = "g4"))]
+make_chan_struct!(2, 8);
@@ -36,8 +36,8 @@ cfg_if! {
 use cfg_if::cfg_if;
 use paste::paste;
 
-#[cfg(not(any(feature = "f4", feature = "l552", feature = "h5")))]
-use crate::dma::{self, ChannelCfg, DmaChannel};
+#[cfg(not(any(feature = "f4", feature = "h5")))]
+use crate::dma::{self, ChannelCfg, ChannelToken};
 
 #[derive(Copy, Clone)]
 #[repr(u8)]
@@ -1395,10 +1395,11 @@ const fn regs(port: Port) -> *const pac::gpioa::RegisterBlock {
 pub unsafe fn write_dma(
     buf: &[u32],
     port: Port,
-    dma_channel: DmaChannel,
+    dma_channel: &ChannelToken,
     channel_cfg: ChannelCfg,
-    dma_periph: dma::DmaPeriph,
 ) {
+    let (dma_channel, dma_periph) = (dma_channel.channel(), dma_channel.periph());
+
     let (ptr, len) = (buf.as_ptr(), buf.len());
 
     let periph_addr = &(*(regs(port))).bsrr as *const _ as u32;
@@ -1452,10 +1453,11 @@ pub unsafe fn write_dma(
 pub unsafe fn read_dma(
     buf: &[u32],
     port: Port,
-    dma_channel: DmaChannel,
+    dma_channel: &ChannelToken,
     channel_cfg: ChannelCfg,
-    dma_periph: dma::DmaPeriph,
 ) {
+    let (dma_channel, dma_periph) = (dma_channel.channel(), dma_channel.periph());
+
     let (ptr, len) = (buf.as_ptr(), buf.len());
 
     let periph_addr = &(*(regs(port))).idr as *const _ as u32;
@@ -1497,3 +1499,44 @@ pub unsafe fn read_dma(
         }
     }
 }
+
+#[cfg(not(any(
+    feature = "f4",
+    feature = "l5",
+    feature = "f3",
+    feature = "l4",
+    feature = "h5"
+)))]
+/// Write a series of words to the BSRR register using DMA, taking ownership of the buffer for the
+/// duration of the transfer. Use the returned `Transfer` to check for completion, and to get the
+/// buffer back. See `write_dma` for details.
+pub fn write_dma_owned(
+    buf: &'static [u32],
+    port: Port,
+    dma_channel: ChannelToken,
+    channel_cfg: ChannelCfg,
+) -> dma::Transfer<Port, &'static [u32]> {
+    unsafe { write_dma(buf, port, &dma_channel, channel_cfg.single_buf()) };
+
+    dma::Transfer::new(port, buf, dma_channel)
+}
+
+#[cfg(not(any(
+    feature = "f4",
+    feature = "l5",
+    feature = "f3",
+    feature = "l4",
+    feature = "h5"
+)))]
+/// Read a series of words from the IDR register using DMA, taking ownership of the buffer for
+/// the duration of the transfer. See `write_dma_owned`.
+pub fn read_dma_owned(
+    buf: &'static mut [u32],
+    port: Port,
+    dma_channel: ChannelToken,
+    channel_cfg: ChannelCfg,
+) -> dma::Transfer<Port, &'static mut [u32]> {
+    unsafe { read_dma(buf, port, &dma_channel, channel_cfg.single_buf()) };
+
+    dma::Transfer::new(port, buf, dma_channel)
+}
@@ -0,0 +1,856 @@
+//! Support for the High-resolution timer (HRTIM) peripheral, on G474, G484, and H7. Includes the
+//! master timer and timing units A - F (A - E on H7): period and compare programming, the output
+//! set/reset crossbar, dead-time, fault inputs, burst mode, and ADC triggers. See G4 RM0440,
+//! section 27: High-resolution timer (HRTIM).
+//!
+//! On G4, the counters are clocked at up to 32x the HRTIM clock, using a DLL that's calibrated
+//! when initializing the peripheral; this provides 184ps resolution at 170Mhz. H7 has no DLL, so
+//! its counters run at the HRTIM clock or below, and its prescaler and dead-time encodings differ.
+//! See H743 RM0433, section 37: High-Resolution Timer (HRTIM).
+
+// todo: Use PAC register fields. The PACs name each timing unit's registers differently (eg `perar`,
+// todo `perbr`), and some names differ between G4 and H7 (eg `timadier` vs `timadier5`), so for now,
+// todo we access them by offset from the RM's register map.
+
+// todo: Capture, external events, synchronization, interleaved and half modes, chopper, DMA.
+
+use crate::{
+    clocks::Clocks,
+    pac::{HRTIM_MASTER, RCC},
+    timer::ValueError,
+    util::rcc_en_reset,
+};
+
+/// Offsets from the start of the master timer's register block. The timing units' blocks each
+/// follow at `UNIT_STRIDE`, then the common registers at `COMMON_OFFSET`. RM0440, section 27.5.
+const UNIT_STRIDE: usize = 0x80;
+const COMMON_OFFSET: usize = 0x380;
+
+// Registers shared by the master timer and timing units.
+const CR_OFFSET: usize = 0x00;
+const ISR_OFFSET: usize = 0x04;
+const ICR_OFFSET: usize = 0x08;
+const DIER_OFFSET: usize = 0x0c;
+const CNT_OFFSET: usize = 0x10;
+const PER_OFFSET: usize = 0x14;
+const REP_OFFSET: usize = 0x18;
+const CMP1_OFFSET: usize = 0x1c;
+const CMP2_OFFSET: usize = 0x24;
+const CMP3_OFFSET: usize = 0x28;
+const CMP4_OFFSET: usize = 0x2c;
+// Timing unit registers.
+const DT_OFFSET: usize = 0x38;
+const SET1_OFFSET: usize = 0x3c;
+const RST1_OFFSET: usize = 0x40;
+const SET2_OFFSET: usize = 0x44;
+const RST2_OFFSET: usize = 0x48;
+const OUT_OFFSET: usize = 0x64;
+const FLT_OFFSET: usize = 0x68;
+// Common registers.
+const CR1_OFFSET: usize = 0x00;
+const CR2_OFFSET: usize = 0x04;
+const COMMON_ISR_OFFSET: usize = 0x08;
+const COMMON_ICR_OFFSET: usize = 0x0c;
+const COMMON_IER_OFFSET: usize = 0x10;
+const OENR_OFFSET: usize = 0x14;
+const ODISR_OFFSET: usize = 0x18;
+const ODSR_OFFSET: usize = 0x1c;
+const BMCR_OFFSET: usize = 0x20;
+const BMTRGR_OFFSET: usize = 0x24;
+const BMCMPR_OFFSET: usize = 0x28;
+const BMPER_OFFSET: usize = 0x2c;
+const ADC1R_OFFSET: usize = 0x3c;
+#[cfg(feature = "g4")]
+const DLLCR_OFFSET: usize = 0x4c;
+const FLTINR1_OFFSET: usize = 0x50;
+const FLTINR2_OFFSET: usize = 0x54;
+
+// CR bits. (MCR for the master timer, TIMxCR for timing units)
+const CR_CKPSC_MASK: u32 = 0b111;
+const CR_CONT: u32 = 1 << 3;
+const CR_RETRIG: u32 = 1 << 4;
+const CR_PSHPLL: u32 = 1 << 6;
+const CR_TXREPU: u32 = 1 << 17;
+const CR_PREEN: u32 = 1 << 27;
+const CR_MREPU: u32 = 1 << 29;
+// OUTxR bits, for output 1. Output 2's are 16 bits higher.
+const OUT_POL: u32 = 1 << 1;
+const OUT_IDLEM: u32 = 1 << 2;
+const OUT_IDLES: u32 = 1 << 3;
+const OUT_FAULT_SHIFT: u32 = 4;
+const OUT_DTEN: u32 = 1 << 8;
+// BMCR bits.
+const BMCR_BME: u32 = 1 << 0;
+const BMCR_BMOM: u32 = 1 << 1;
+const BMCR_BMPREN: u32 = 1 << 10;
+// BMTRGR bits.
+const BMTRGR_SW: u32 = 1 << 0;
+// Common ISR bits.
+#[cfg(feature = "g4")]
+const ISR_DLLRDY: u32 = 1 << 16;
+
+/// Minimum and maximum period and compare values. On G4, the minimum is for a prescaler of 1 or
+/// lower; it scales with higher-resolution prescalers. RM0440, section 27.3.4, and RM0433, section
+/// 37.3.3.
+const MIN_PERIOD: u32 = 3;
+#[cfg(feature = "g4")]
+const MAX_PERIOD: u32 = 0xffdf;
+#[cfg(feature = "h7")]
+const MAX_PERIOD: u32 = 0xfffd;
+
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+/// The master timer, or a timing unit. The integer values are used to index registers and bit
+/// fields, eg the MCR counter enable bits, and CR1's ADC update source.
+pub enum HrTimUnit {
+    Master = 0,
+    A = 1,
+    B = 2,
+    C = 3,
+    D = 4,
+    E = 5,
+    #[cfg(feature = "g4")]
+    F = 6,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// An HRTIM output. The integer values are the bit positions in the OENR, ODISR and ODSR registers.
+pub enum HrTimOutput {
+    A1 = 0,
+    A2 = 1,
+    B1 = 2,
+    B2 = 3,
+    C1 = 4,
+    C2 = 5,
+    D1 = 6,
+    D2 = 7,
+    E1 = 8,
+    E2 = 9,
+    #[cfg(feature = "g4")]
+    F1 = 10,
+    #[cfg(feature = "g4")]
+    F2 = 11,
+}
+
+impl HrTimOutput {
+    /// The timing unit this output belongs to.
+    fn unit(&self) -> HrTimUnit {
+        match *self as u8 / 2 {
+            0 => HrTimUnit::A,
+            1 => HrTimUnit::B,
+            2 => HrTimUnit::C,
+            3 => HrTimUnit::D,
+            4 => HrTimUnit::E,
+            #[cfg(feature = "g4")]
+            _ => HrTimUnit::F,
+            #[cfg(not(feature = "g4"))]
+            _ => unreachable!(),
+        }
+    }
+
+    /// True for a unit's output 2.
+    fn is_second(&self) -> bool {
+        *self as u8 % 2 == 1
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// Counter clock prescaler; multiplies or divides the HRTIM clock. Sets the MCR or TIMxCR register's
+/// `CKPSC` field. Multiplying requires the DLL, so is only available on G4. H7 has no DLL, so
+/// its encoding starts at 1, and goes to lower frequencies.
+pub enum HrPrescaler {
+    #[cfg(feature = "g4")]
+    Mul32 = 0b000,
+    #[cfg(feature = "g4")]
+    Mul16 = 0b001,
+    #[cfg(feature = "g4")]
+    Mul8 = 0b010,
+    #[cfg(feature = "g4")]
+    Mul4 = 0b011,
+    #[cfg(feature = "g4")]
+    Mul2 = 0b100,
+    #[cfg(feature = "g4")]
+    Div1 = 0b101,
+    #[cfg(feature = "g4")]
+    Div2 = 0b110,
+    #[cfg(feature = "g4")]
+    Div4 = 0b111,
+    #[cfg(feature = "h7")]
+    Div1 = 0b000,
+    #[cfg(feature = "h7")]
+    Div2 = 0b001,
+    #[cfg(feature = "h7")]
+    Div4 = 0b010,
+    #[cfg(feature = "h7")]
+    Div8 = 0b011,
+    #[cfg(feature = "h7")]
+    Div16 = 0b100,
+    #[cfg(feature = "h7")]
+    Div32 = 0b101,
+    #[cfg(feature = "h7")]
+    Div64 = 0b110,
+    #[cfg(feature = "h7")]
+    Div128 = 0b111,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// A compare unit, of the master timer or a timing unit.
+pub enum HrCompare {
+    One,
+    Two,
+    Three,
+    Four,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// An event that can set or reset a timing unit's output, via the crossbar. The integer values are
+/// bit positions in the SETxyR and RSTxyR registers.
+pub enum OutputEvent {
+    Software = 0,
+    Resync = 1,
+    Period = 2,
+    Cmp1 = 3,
+    Cmp2 = 4,
+    Cmp3 = 5,
+    Cmp4 = 6,
+    MasterPeriod = 7,
+    MasterCmp1 = 8,
+    MasterCmp2 = 9,
+    MasterCmp3 = 10,
+    MasterCmp4 = 11,
+    /// Timer events 1 - 9 are compare events from other timing units. Which ones depends on the
+    /// timing unit; see RM0440, table 222: Timer events.
+    TimerEvent1 = 12,
+    TimerEvent2 = 13,
+    TimerEvent3 = 14,
+    TimerEvent4 = 15,
+    TimerEvent5 = 16,
+    TimerEvent6 = 17,
+    TimerEvent7 = 18,
+    TimerEvent8 = 19,
+    TimerEvent9 = 20,
+    ExternalEvent1 = 21,
+    ExternalEvent2 = 22,
+    ExternalEvent3 = 23,
+    ExternalEvent4 = 24,
+    ExternalEvent5 = 25,
+    ExternalEvent6 = 26,
+    ExternalEvent7 = 27,
+    ExternalEvent8 = 28,
+    ExternalEvent9 = 29,
+    ExternalEvent10 = 30,
+    /// Registers update (transfer from preload to active).
+    Update = 31,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// Output polarity. Sets the OUTxR register's `POLx` field.
+pub enum HrPolarity {
+    ActiveHigh = 0,
+    ActiveLow = 1,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// The state an output goes to when a fault enabled for its timing unit occurs. Sets the OUTxR
+/// register's `FAULTx` field.
+pub enum FaultState {
+    /// No action: The output isn't affected by faults.
+    NoAction = 0b00,
+    Active = 0b01,
+    Inactive = 0b10,
+    HighZ = 0b11,
+}
+
+#[derive(Clone, Copy)]
+/// Output configuration. Must be set before enabling the output.
+pub struct OutputCfg {
+    pub polarity: HrPolarity,
+    /// The output's state when a fault occurs.
+    pub fault_state: FaultState,
+    /// If true, the output goes to its idle state while burst mode is active.
+    pub idle_in_burst: bool,
+    /// The output's idle level, in burst mode, or when disabled. true for active.
+    pub idle_active: bool,
+}
+
+impl Default for OutputCfg {
+    fn default() -> Self {
+        Self {
+            polarity: HrPolarity::ActiveHigh,
+            fault_state: FaultState::NoAction,
+            idle_in_burst: false,
+            idle_active: false,
+        }
+    }
+}
+
+#[derive(Clone)]
+/// Configuration for the master timer, or a timing unit.
+pub struct HrTimerCfg {
+    pub prescaler: HrPrescaler,
+    /// If false, the counter stops after one period. Defaults to true.
+    pub continuous: bool,
+    /// In single-shot mode, allows the counter to be reset by a reset event while running.
+    /// Defaults to false.
+    pub retriggerable: bool,
+    /// Buffer period and compare writes, transferring them on update events. Defaults to true.
+    pub preload: bool,
+    /// Trigger an update event when the repetition counter underflows. Defaults to true.
+    pub update_on_repetition: bool,
+    /// Alternate the timing unit's output between outputs 1 and 2 each period. Not available
+    /// on the master timer. Defaults to false.
+    pub push_pull: bool,
+}
+
+impl Default for HrTimerCfg {
+    fn default() -> Self {
+        Self {
+            prescaler: HrPrescaler::Div1,
+            continuous: true,
+            retriggerable: false,
+            preload: true,
+            update_on_repetition: true,
+            push_pull: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// A fault input. Configured in the FLTINR1 and FLTINR2 registers.
+pub enum Fault {
+    F1 = 0,
+    F2 = 1,
+    F3 = 2,
+    F4 = 3,
+    F5 = 4,
+    #[cfg(feature = "g4")]
+    F6 = 5,
+}
+
+impl Fault {
+    /// The fault's bit position in the common ISR, ICR, and IER registers.
+    fn isr_bit(&self) -> u32 {
+        match self {
+            #[cfg(feature = "g4")]
+            Self::F6 => 6,
+            _ => *self as u32,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// The fault input's active level. Sets FLTINRx register, `FLTxP` field.
+pub enum FaultPolarity {
+    ActiveLow = 0,
+    ActiveHigh = 1,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// Fault source. Sets FLTINRx register, `FLTxSRC` field.
+pub enum FaultSource {
+    /// The HRTIM_FLTx pin.
+    Pin = 0,
+    /// An internal comparator output.
+    Internal = 1,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// Burst mode clock. Sets BMCR register, `BMCLK` field.
+pub enum BurstClock {
+    /// Master timer counter reset or roll-over.
+    Master = 0b0000,
+    TimerA = 0b0001,
+    TimerB = 0b0010,
+    TimerC = 0b0011,
+    TimerD = 0b0100,
+    TimerE = 0b0101,
+    #[cfg(feature = "g4")]
+    TimerF = 0b0110,
+    /// The HRTIM clock, divided by the burst mode prescaler.
+    Prescaled = 0b1010,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// ADC trigger output.
+pub enum AdcTrigger {
+    One = 0,
+    Two = 1,
+    Three = 2,
+    Four = 3,
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// HRTIM interrupts for the master timer or a timing unit. The integer values are bit positions
+/// in the ISR, ICR, and DIER registers. `SyncInput` is only available on the master timer; the
+/// capture, set, reset, and delayed protection interrupts are only available on timing units.
+pub enum HrTimInterrupt {
+    Cmp1 = 0,
+    Cmp2 = 1,
+    Cmp3 = 2,
+    Cmp4 = 3,
+    Repetition = 4,
+    SyncInput = 5,
+    Update = 6,
+    Capture1 = 7,
+    Capture2 = 8,
+    Set1 = 9,
+    Reset1 = 10,
+    Set2 = 11,
+    Reset2 = 12,
+    CounterReset = 13,
+    DelayedProtection = 14,
+}
+
+/// Get a pointer to a master timer or timing unit register.
+fn unit_reg(unit: HrTimUnit, offset: usize) -> *mut u32 {
+    (HRTIM_MASTER::ptr() as usize + unit as usize * UNIT_STRIDE + offset) as *mut u32
+}
+
+/// Get a pointer to a common register.
+fn common_reg(offset: usize) -> *mut u32 {
+    (HRTIM_MASTER::ptr() as usize + COMMON_OFFSET + offset) as *mut u32
+}
+
+fn read(reg: *mut u32) -> u32 {
+    unsafe { reg.read_volatile() }
+}
+
+fn write(reg: *mut u32, val: u32) {
+    unsafe { reg.write_volatile(val) }
+}
+
+/// Clear the bits in `mask`, then set those in `val`.
+fn modify(reg: *mut u32, mask: u32, val: u32) {
+    write(reg, (read(reg) & !mask) | val);
+}
+
+/// Build a register value from a list of units' bit positions, offset by `shift`.
+fn unit_bits(units: &[HrTimUnit], shift: u32) -> u32 {
+    units
+        .iter()
+        .fold(0, |acc, u| acc | 1 << (*u as u32 + shift))
+}
+
+/// Represents the High-resolution timer peripheral. The timing units and common registers are
+/// accessed through the master timer's register block.
+pub struct Hrtim {
+    pub regs: HRTIM_MASTER,
+    /// The HRTIM clock speed, in Hz.
+    clock_speed: u32,
+}
+
+impl Hrtim {
+    /// Initialize the HRTIM, including enabling and resetting its RCC peripheral clock. On G4, this
+    /// also calibrates the DLL, and enables periodic recalibration.
+    pub fn new(regs: HRTIM_MASTER, clocks: &Clocks) -> Self {
+        let rcc = unsafe { &(*RCC::ptr()) };
+
+        #[cfg(feature = "g4")]
+        rcc_en_reset!(apb2, hrtim1, rcc);
+        #[cfg(feature = "h7")]
+        rcc_en_reset!(apb2, hrtim, rcc);
+
+        // RM0440, section 27.3.24: "The DLL calibration must be performed at least once before
+        // using the high-resolution features." We then enable periodic calibration, to compensate
+        // for voltage and temperature drift.
+        #[cfg(feature = "g4")]
+        {
+            write(common_reg(DLLCR_OFFSET), 1); // CAL
+            while read(common_reg(COMMON_ISR_OFFSET)) & ISR_DLLRDY == 0 {}
+            // CALEN, and CALRTE = 0b11: Recalibrate every 2048 HRTIM clock cycles.
+            write(common_reg(DLLCR_OFFSET), (0b11 << 2) | (1 << 1));
+        }
+
+        Self {
+            regs,
+            // On H7, this assumes RCC_CFGR's `HRTIMSEL` bit is left at its default of 0; ie
+            // the HRTIM is clocked by the APB2 timer clock.
+            clock_speed: clocks.apb2_timer(),
+        }
+    }
+
+    /// Configure the master timer, or a timing unit. Do this while the timer is stopped.
+    pub fn cfg_timer(&mut self, unit: HrTimUnit, cfg: &HrTimerCfg) {
+        let mut val = cfg.prescaler as u32;
+        if cfg.continuous {
+            val |= CR_CONT;
+        }
+        if cfg.retriggerable {
+            val |= CR_RETRIG;
+        }
+        if cfg.preload {
+            val |= CR_PREEN;
+        }
+
+        if unit == HrTimUnit::Master {
+            if cfg.update_on_repetition {
+                val |= CR_MREPU;
+            }
+            let mask = CR_CKPSC_MASK | CR_CONT | CR_RETRIG | CR_PREEN | CR_MREPU;
+            modify(unit_reg(unit, CR_OFFSET), mask, val);
+        } else {
+            if cfg.update_on_repetition {
+                val |= CR_TXREPU;
+            }
+            if cfg.push_pull {
+                val |= CR_PSHPLL;
+            }
+            let mask = CR_CKPSC_MASK | CR_CONT | CR_RETRIG | CR_PREEN | CR_TXREPU | CR_PSHPLL;
+            modify(unit_reg(unit, CR_OFFSET), mask, val);
+        }
+    }
+
+    /// The counter clock speed of a timer, in Hz, based on its prescaler.
+    fn counter_clock(&self, unit: HrTimUnit) -> u64 {
+        let ckpsc = read(unit_reg(unit, CR_OFFSET)) & CR_CKPSC_MASK;
+        // G4: fCOUNTER = fHRTIM * 32 / 2^CKPSC. H7: fCOUNTER = fHRTIM / 2^CKPSC.
+        #[cfg(feature = "g4")]
+        return (self.clock_speed as u64 * 32) >> ckpsc;
+        #[cfg(feature = "h7")]
+        return self.clock_speed as u64 >> ckpsc;
+    }
+
+    #[cfg(feature = "g4")]
+    /// The minimum period and compare value for a timer, based on its prescaler.
+    fn min_period(&self, unit: HrTimUnit) -> u32 {
+        let ckpsc = read(unit_reg(unit, CR_OFFSET)) & CR_CKPSC_MASK;
+        MIN_PERIOD << (5 - ckpsc.min(5))
+    }
+
+    #[cfg(feature = "h7")]
+    /// The minimum period and compare value for a timer. Without high-resolution prescalers, this
+    /// doesn't depend on the prescaler.
+    fn min_period(&self, _unit: HrTimUnit) -> u32 {
+        MIN_PERIOD
+    }
+
+    /// Set a timer's frequency, in Hz.
+    pub fn set_freq(&mut self, unit: HrTimUnit, freq: f32) -> Result<(), ValueError> {
+        assert!(freq > 0.);
+
+        let period = (self.counter_clock(unit) as f32 / freq) as u32;
+
+        if period < self.min_period(unit) || period > MAX_PERIOD {
+            return Err(ValueError {});
+        }
+
+        self.set_period(unit, period as u16);
+        Ok(())
+    }
+
+    /// Set a timer's period register directly, in counter clock ticks. Must be between 3 and
+    /// 0xffdf (0xfffd on H7); on G4, the minimum is higher with prescalers below 1.
+    pub fn set_period(&mut self, unit: HrTimUnit, period: u16) {
+        write(unit_reg(unit, PER_OFFSET), period as u32);
+    }
+
+    /// Get a timer's period register value. Useful for setting compare values as a portion of the period.
+    pub fn get_period(&self, unit: HrTimUnit) -> u16 {
+        read(unit_reg(unit, PER_OFFSET)) as u16
+    }
+
+    /// Set a compare value, in counter clock ticks. Compare values have the same limits as the period.
+    pub fn set_compare(&mut self, unit: HrTimUnit, compare: HrCompare, value: u16) {
+        let offset = match compare {
+            HrCompare::One => CMP1_OFFSET,
+            HrCompare::Two => CMP2_OFFSET,
+            HrCompare::Three => CMP3_OFFSET,
+            HrCompare::Four => CMP4_OFFSET,
+        };
+        write(unit_reg(unit, offset), value as u32);
+    }
+
+    /// Set the repetition counter; the number of periods between repetition events.
+    pub fn set_repetition(&mut self, unit: HrTimUnit, repetition: u8) {
+        write(unit_reg(unit, REP_OFFSET), repetition as u32);
+    }
+
+    /// Read a timer's counter value.
+    pub fn read_count(&self, unit: HrTimUnit) -> u16 {
+        read(unit_reg(unit, CNT_OFFSET)) as u16
+    }
+
+    /// Start one or more timers' counters. Timers started together are synchronized.
+    /// Sets MCR register, `MCEN` and `TxCEN` fields.
+    pub fn start(&mut self, units: &[HrTimUnit]) {
+        modify(
+            unit_reg(HrTimUnit::Master, CR_OFFSET),
+            0,
+            unit_bits(units, 16),
+        );
+    }
+
+    /// Stop one or more timers' counters.
+    pub fn stop(&mut self, units: &[HrTimUnit]) {
+        modify(
+            unit_reg(HrTimUnit::Master, CR_OFFSET),
+            unit_bits(units, 16),
+            0,
+        );
+    }
+
+    /// Force an update event on one or more timers, transferring preloaded values to the active
+    /// registers. Sets CR2 register, `xSWU` fields.
+    pub fn software_update(&mut self, units: &[HrTimUnit]) {
+        write(common_reg(CR2_OFFSET), unit_bits(units, 0));
+    }
+
+    /// Reset one or more timers' counters. Sets CR2 register, `xRST` fields.
+    pub fn software_reset(&mut self, units: &[HrTimUnit]) {
+        write(common_reg(CR2_OFFSET), unit_bits(units, 8));
+    }
+
+    /// Configure an output. Do this before enabling it.
+    pub fn cfg_output(&mut self, output: HrTimOutput, cfg: &OutputCfg) {
+        let shift = if output.is_second() { 16 } else { 0 };
+
+        let mut val = (cfg.polarity as u32) << 1 | (cfg.fault_state as u32) << OUT_FAULT_SHIFT;
+        if cfg.idle_in_burst {
+            val |= OUT_IDLEM;
+        }
+        if cfg.idle_active {
+            val |= OUT_IDLES;
+        }
+
+        let mask = OUT_POL | OUT_IDLEM | OUT_IDLES | 0b11 << OUT_FAULT_SHIFT;
+        modify(
+            unit_reg(output.unit(), OUT_OFFSET),
+            mask << shift,
+            val << shift,
+        );
+    }
+
+    /// Select which events set an output active, and which set it inactive. This replaces any
+    /// existing events for the output. For example, for a PWM output, set on `Period`, and reset
+    /// on `Cmp1`.
+    pub fn set_output_events(
+        &mut self,
+        output: HrTimOutput,
+        set_events: &[OutputEvent],
+        reset_events: &[OutputEvent],
+    ) {
+        let (set_offset, reset_offset) = if output.is_second() {
+            (SET2_OFFSET, RST2_OFFSET)
+        } else {
+            (SET1_OFFSET, RST1_OFFSET)
+        };
+
+        let bits = |events: &[OutputEvent]| events.iter().fold(0, |acc, e| acc | 1 << *e as u32);
+
+        write(unit_reg(output.unit(), set_offset), bits(set_events));
+        write(unit_reg(output.unit(), reset_offset), bits(reset_events));
+    }
+
+    /// Enable one or more outputs. Writes to the OENR register. Note that outputs are disabled by
+    /// hardware when a fault occurs; run this again to re-enable them.
+    pub fn enable_outputs(&mut self, outputs: &[HrTimOutput]) {
+        let bits = outputs.iter().fold(0, |acc, o| acc | 1 << *o as u32);
+        write(common_reg(OENR_OFFSET), bits);
+    }
+
+    /// Disable one or more outputs. They go to their idle state. Writes to the ODISR register.
+    pub fn disable_outputs(&mut self, outputs: &[HrTimOutput]) {
+        let bits = outputs.iter().fold(0, |acc, o| acc | 1 << *o as u32);
+        write(common_reg(ODISR_OFFSET), bits);
+    }
+
+    /// Check if an output is enabled. Reads the OENR register.
+    pub fn output_is_enabled(&self, output: HrTimOutput) -> bool {
+        read(common_reg(OENR_OFFSET)) & (1 << output as u32) != 0
+    }
+
+    /// Check if an output was disabled by a fault, vice being disabled by software or idle.
+    /// Reads the ODSR register.
+    pub fn output_is_faulted(&self, output: HrTimOutput) -> bool {
+        read(common_reg(ODSR_OFFSET)) & (1 << output as u32) != 0
+    }
+
+    /// Set up and enable dead-time insertion between a timing unit's outputs 1 and 2, which
+    /// become complementary. `rising` delays output 1's rising edge, and `falling` delays output
+    /// 2's rising edge. Times are in seconds. Do this while the timing unit's outputs are disabled.
+    pub fn set_deadtime(
+        &mut self,
+        unit: HrTimUnit,
+        rising: f32,
+        falling: f32,
+    ) -> Result<(), ValueError> {
+        assert!(unit != HrTimUnit::Master);
+
+        // RM0440, section 27.5.37: tDTG = 2^DTPRSC * tHRTIM / 8. H7 has no DLL, so its
+        // dead-time generator runs from the HRTIM clock directly: tDTG = 2^DTPRSC * tHRTIM.
+        // (RM0433, section 37.5.36)
+        #[cfg(feature = "g4")]
+        let dtg_base = self.clock_speed as f32 * 8.;
+        #[cfg(feature = "h7")]
+        let dtg_base = self.clock_speed as f32;
+
+        for prsc in 0..8 {
+            let dtg_freq = dtg_base / (1 << prsc) as f32;
+            let dtr = (rising * dtg_freq) as u32;
+            let dtf = (falling * dtg_freq) as u32;
+
+            if dtr <= 0x1ff && dtf <= 0x1ff {
+                write(unit_reg(unit, DT_OFFSET), dtf << 16 | prsc << 10 | dtr);
+                modify(unit_reg(unit, OUT_OFFSET), 0, OUT_DTEN);
+                return Ok(());
+            }
+        }
+
+        Err(ValueError {})
+    }
+
+    /// Disable dead-time insertion for a timing unit.
+    pub fn disable_deadtime(&mut self, unit: HrTimUnit) {
+        assert!(unit != HrTimUnit::Master);
+        modify(unit_reg(unit, OUT_OFFSET), OUT_DTEN, 0);
+    }
+
+    /// Configure and enable a fault input. `filter` is the FLTxF field, 0 - 15; 0 disables
+    /// filtering. See RM0440, section 27.5.56: HRTIM Fault Input Register 1 (HRTIM_FLTINR1).
+    pub fn cfg_fault(
+        &mut self,
+        fault: Fault,
+        polarity: FaultPolarity,
+        source: FaultSource,
+        filter: u8,
+    ) {
+        assert!(filter <= 0b1111);
+
+        let (offset, shift) = match fault as u8 {
+            n @ 0..=3 => (FLTINR1_OFFSET, n as u32 * 8),
+            n => (FLTINR2_OFFSET, (n as u32 - 4) * 8),
+        };
+
+        // The enable bit must be set after configuring the other fields.
+        let val = (filter as u32) << 3 | (source as u32) << 2 | (polarity as u32) << 1;
+        modify(common_reg(offset), 0b111_1111 << shift, val << shift);
+        modify(common_reg(offset), 0, 1 << shift);
+    }
+
+    /// Select which faults act on a timing unit's outputs. Each output's response is set by
+    /// `OutputCfg::fault_state`. Sets FLTxR register, `FLTyEN` fields.
+    pub fn enable_faults(&mut self, unit: HrTimUnit, faults: &[Fault]) {
+        assert!(unit != HrTimUnit::Master);
+
+        let bits = faults.iter().fold(0, |acc, f| acc | 1 << *f as u32);
+        modify(unit_reg(unit, FLT_OFFSET), 0b11_1111, bits);
+    }
+
+    /// Check if a fault has occurred. Reads the common ISR register.
+    pub fn fault_occurred(&self, fault: Fault) -> bool {
+        read(common_reg(COMMON_ISR_OFFSET)) & (1 << fault.isr_bit()) != 0
+    }
+
+    /// Clear a fault's flag. Run this in the fault interrupt handler.
+    pub fn clear_fault(&mut self, fault: Fault) {
+        write(common_reg(COMMON_ICR_OFFSET), 1 << fault.isr_bit());
+    }
+
+    /// Enable the interrupt for a fault input. This is the `HRTIM_FLT` interrupt.
+    pub fn enable_fault_interrupt(&mut self, fault: Fault) {
+        modify(common_reg(COMMON_IER_OFFSET), 0, 1 << fault.isr_bit());
+    }
+
+    /// Disable the interrupt for a fault input.
+    pub fn disable_fault_interrupt(&mut self, fault: Fault) {
+        modify(common_reg(COMMON_IER_OFFSET), 1 << fault.isr_bit(), 0);
+    }
+
+    /// Configure burst mode. During a burst period of `period` burst clock ticks, the selected
+    /// timers' outputs are idle for the first `idle_duration` ticks. `prescaler` divides the HRTIM
+    /// clock by 2^`prescaler`, when `clock` is `BurstClock::Prescaled`. If `continuous` is false,
+    /// burst mode runs for one period per trigger. Disables burst mode if it's running.
+    pub fn cfg_burst_mode(
+        &mut self,
+        clock: BurstClock,
+        prescaler: u8,
+        period: u16,
+        idle_duration: u16,
+        continuous: bool,
+        units: &[HrTimUnit],
+    ) {
+        assert!(prescaler <= 0b1111);
+
+        self.disable_burst_mode();
+
+        let mut val =
+            (clock as u32) << 2 | (prescaler as u32) << 6 | BMCR_BMPREN | unit_bits(units, 16);
+        if continuous {
+            val |= BMCR_BMOM;
+        }
+        write(common_reg(BMCR_OFFSET), val);
+
+        write(common_reg(BMPER_OFFSET), period as u32);
+        write(common_reg(BMCMPR_OFFSET), idle_duration as u32);
+    }
+
+    /// Set the events that trigger burst mode. `triggers` is the BMTRGR register's value; see
+    /// RM0440, section 27.5.50: HRTIM Burst Mode Trigger Register (HRTIM_BMTRGR).
+    pub fn set_burst_triggers(&mut self, triggers: u32) {
+        write(common_reg(BMTRGR_OFFSET), triggers);
+    }
+
+    /// Enable burst mode. It starts on the next trigger.
+    pub fn enable_burst_mode(&mut self) {
+        modify(common_reg(BMCR_OFFSET), 0, BMCR_BME);
+    }
+
+    /// Disable burst mode.
+    pub fn disable_burst_mode(&mut self) {
+        modify(common_reg(BMCR_OFFSET), BMCR_BME, 0);
+    }
+
+    /// Trigger burst mode from software. Sets BMTRGR register, `SW` field.
+    pub fn trigger_burst_mode(&mut self) {
+        modify(common_reg(BMTRGR_OFFSET), 0, BMTRGR_SW);
+    }
+
+    /// Set the events that generate an ADC trigger, and the timer whose update events transfer
+    /// preloaded trigger selections. `sources` is the ADCxR register's value; the available events
+    /// differ between triggers. See RM0440, section 27.5.53: HRTIM ADC Trigger 1 Register
+    /// (HRTIM_ADC1R). Select the HRTIM trigger as the ADC's external trigger in the ADC config.
+    pub fn set_adc_trigger(&mut self, trigger: AdcTrigger, sources: u32, update_src: HrTimUnit) {
+        let shift = 16 + trigger as u32 * 3;
+        modify(
+            common_reg(CR1_OFFSET),
+            0b111 << shift,
+            (update_src as u32) << shift,
+        );
+
+        write(common_reg(ADC1R_OFFSET + trigger as usize * 4), sources);
+    }
+
+    /// Enable a specific type of interrupt for the master timer or a timing unit. The master timer
+    /// and each timing unit have their own interrupt vector.
+    pub fn enable_interrupt(&mut self, unit: HrTimUnit, interrupt: HrTimInterrupt) {
+        modify(unit_reg(unit, DIER_OFFSET), 0, 1 << interrupt as u32);
+    }
+
+    /// Disable a specific type of interrupt for the master timer or a timing unit.
+    pub fn disable_interrupt(&mut self, unit: HrTimUnit, interrupt: HrTimInterrupt) {
+        modify(unit_reg(unit, DIER_OFFSET), 1 << interrupt as u32, 0);
+    }
+
+    /// Clear an interrupt flag - run this in the interrupt's handler to prevent
+    /// repeat firings.
+    pub fn clear_interrupt(&mut self, unit: HrTimUnit, interrupt: HrTimInterrupt) {
+        write(unit_reg(unit, ICR_OFFSET), 1 << interrupt as u32);
+    }
+
+    /// Check if an interrupt flag is set.
+    pub fn interrupt_is_set(&self, unit: HrTimUnit, interrupt: HrTimInterrupt) -> bool {
+        read(unit_reg(unit, ISR_OFFSET)) & (1 << interrupt as u32) != 0
+    }
+}
@@ -9,8 +9,8 @@ use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
 
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "l552", feature = "h5")))]
-use crate::dma::{self, ChannelCfg, DmaChannel};
+#[cfg(not(feature = "h5"))]
+use crate::dma::{self, ChannelCfg, ChannelToken};
 #[cfg(feature = "g0")]
 use crate::pac::DMA as DMA1;
 #[cfg(not(any(feature = "g0", feature = "h5")))]
@@ -561,28 +561,30 @@ where
 
     #[cfg(not(feature = "g0"))]
     /// Read data, using DMA. See L44 RM, 37.4.16: "Transmission using DMA"
-    /// Note that the `channel` argument is unused on F3 and L4, since it is hard-coded,
-    /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
+    /// On F3 and L4, the `channel` token must be for the channel hard-wired to this peripheral on
+    /// its DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise. `dma::claim_channel_for()` claims the right one.
     /// For a single write, set `autoend` to `true`. For a write_read and other use cases,
     /// set it to `false`.
-    #[cfg(not(any(feature = "l552", feature = "h5")))]
+    #[cfg(not(feature = "h5"))]
     pub unsafe fn write_dma(
         &mut self,
         addr: u8,
         buf: &[u8],
         autoend: bool,
-        channel: DmaChannel,
+        channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
     ) {
+        let dma_periph = channel.periph();
+
         let (ptr, len) = (buf.as_ptr(), buf.len());
 
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let channel = channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel = R::write_chan();
-        #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
+        let channel = dma::check_fixed_channel(channel, dma_periph, R::write_chan(dma_periph));
         #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::write_sel(dma_periph);
 
         // DMA (Direct Memory Access) can be enabled for transmission by setting the TXDMAEN bit
         // in the I2C_CR1 register. Data is loaded from an SRAM area configured using the DMA
@@ -651,25 +653,27 @@ where
     }
 
     /// Read data, using DMA. See L44 RM, 37.4.16: "Reception using DMA"
-    /// Note that the `channel` argument is unused on F3 and L4, since it is hard-coded,
-    /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
-    #[cfg(not(any(feature = "l552", feature = "h5")))]
+    /// On F3 and L4, the `channel` token must be for the channel hard-wired to this peripheral on
+    /// its DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise. `dma::claim_channel_for()` claims the right one.
+    #[cfg(not(feature = "h5"))]
     pub unsafe fn read_dma(
         &mut self,
         addr: u8,
         buf: &mut [u8],
-        channel: DmaChannel,
+        channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
     ) {
+        let dma_periph = channel.periph();
+
         let (ptr, len) = (buf.as_mut_ptr(), buf.len());
 
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let channel = channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel = R::read_chan();
+        let channel = dma::check_fixed_channel(channel, dma_periph, R::read_chan(dma_periph));
         #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
-        #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::read_sel(dma_periph);
 
         // DMA (Direct Memory Access) can be enabled for reception by setting the RXDMAEN bit in
         // the I2C_CR1 register. Data is loaded from the I2C_RXDR register to an SRAM area
@@ -731,6 +735,47 @@ where
         }
     }
 
+    #[cfg(not(any(feature = "g0", feature = "h5")))]
+    /// Write data using DMA, taking ownership of the buffer and the I2C for the duration
+    /// of the transfer. Use the returned `Transfer` to check for completion, and to get them back.
+    /// See `write_dma` for details.
+    pub fn write_dma_owned(
+        mut self,
+        addr: u8,
+        buf: &'static [u8],
+        autoend: bool,
+        channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static [u8]> {
+        unsafe { self.write_dma(addr, buf, autoend, &channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, channel).with_cleanup(Self::end_dma)
+    }
+
+    #[cfg(not(feature = "h5"))]
+    /// Read data using DMA, taking ownership of the buffer and the I2C for the duration
+    /// of the transfer. See `write_dma_owned`, and `read_dma`.
+    pub fn read_dma_owned(
+        mut self,
+        addr: u8,
+        buf: &'static mut [u8],
+        channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static mut [u8]> {
+        unsafe { self.read_dma(addr, buf, &channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, channel).with_cleanup(Self::end_dma)
+    }
+
+    #[cfg(not(feature = "h5"))]
+    /// Disable DMA requests, once an owned transfer has ended.
+    fn end_dma(&mut self) {
+        self.regs.cr1.modify(|_, w| {
+            w.txdmaen().clear_bit();
+            w.rxdmaen().clear_bit()
+        });
+    }
+
     /// Print the (raw) contents of the status register.
     pub fn read_status(&self) -> u32 {
         unsafe { self.regs.isr.read().bits() }
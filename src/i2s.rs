@@ -0,0 +1,515 @@
+//! Support for the Inter-IC Sound (I2S) audio bus, using the SPI peripherals in I2S mode. This is the
+//! audio interface on families that don't have a SAI peripheral, ie F3, F4, and G4. Provides
+//! master and slave transmitter and receiver modes, with blocking and DMA functionality.
+//! See F446 RM, section 26.6: I2S functional description, or G4 RM, section 39.7.
+
+use core::{ops::Deref, ptr};
+
+use crate::{
+    dma::{self, ChannelCfg, ChannelToken},
+    pac::{self, DMA1, RCC},
+    timer::ValueError,
+    util::RccPeriph,
+    MAX_ITERS,
+};
+
+/// I2S error
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug)]
+pub enum I2sError {
+    /// Overrun occurred (receiver)
+    Overrun,
+    /// Underrun occurred (slave transmitter)
+    Underrun,
+    /// Frame format error; the WS line toggled at an unexpected time (slave)
+    FrameFormat,
+    Hardware,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+/// Select the I2S operating mode. Sets `I2SCFGR` register, `I2SCFG` field.
+pub enum I2sMode {
+    SlaveTransmit = 0b00,
+    SlaveReceive = 0b01,
+    /// The SPI/I2S peripheral generates the CK and WS signals, and optionally MCK.
+    MasterTransmit = 0b10,
+    MasterReceive = 0b11,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+/// Select the I2S standard. Sets `I2SCFGR` register, `I2SSTD` and `PCMSYNC` fields.
+pub enum I2sStandard {
+    /// I2S Philips standard. Data is delayed by one clock cycle from the WS edge.
+    Philips,
+    /// MSB-justified (left-justified) standard.
+    Msb,
+    /// LSB-justified (right-justified) standard.
+    Lsb,
+    /// PCM standard, with a short frame synchronization pulse.
+    PcmShort,
+    /// PCM standard, with a long (13 clock cycles) frame synchronization pulse.
+    PcmLong,
+}
+
+impl I2sStandard {
+    /// The value to write to the `I2SSTD` field.
+    fn bits(&self) -> u8 {
+        match self {
+            Self::Philips => 0b00,
+            Self::Msb => 0b01,
+            Self::Lsb => 0b10,
+            Self::PcmShort | Self::PcmLong => 0b11,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+/// Data length, and channel length the data is transferred in. Sets `I2SCFGR` register, `DATLEN`
+/// and `CHLEN` fields. Data lengths above 16 bits are always sent in 32-bit channels.
+pub enum DataFormat {
+    /// 16-bit data in a 16-bit channel
+    D16C16,
+    /// 16-bit data in a 32-bit channel
+    D16C32,
+    /// 24-bit data in a 32-bit channel
+    D24C32,
+    /// 32-bit data in a 32-bit channel
+    D32C32,
+}
+
+impl DataFormat {
+    /// Returns the (`DATLEN`, `CHLEN`) field values.
+    fn bits(&self) -> (u8, bool) {
+        match self {
+            Self::D16C16 => (0b00, false),
+            Self::D16C32 => (0b00, true),
+            Self::D24C32 => (0b01, true),
+            Self::D32C32 => (0b10, true),
+        }
+    }
+
+    /// Number of bits in a channel.
+    fn channel_len(&self) -> u32 {
+        match self {
+            Self::D16C16 => 16,
+            _ => 32,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(u8)]
+/// Steady state clock polarity. Sets `I2SCFGR` register, `CKPOL` field.
+pub enum I2sClockPolarity {
+    /// I2S clock steady state is low level
+    IdleLow = 0,
+    /// I2S clock steady state is high level
+    IdleHigh = 1,
+}
+
+/// Configuration data for I2S.
+pub struct I2sConfig {
+    /// Master or slave, and transmitter or receiver. Defaults to master transmitter.
+    pub mode: I2sMode,
+    /// I2S standard (framing). Defaults to Philips.
+    pub standard: I2sStandard,
+    /// Data and channel length. Defaults to 16-bit data in a 16-bit channel.
+    pub data_format: DataFormat,
+    /// Clock polarity. Defaults to idle low.
+    pub clock_polarity: I2sClockPolarity,
+    /// Output the master clock (MCK) on its pin, at 256 x the sample rate. Only applies to master
+    /// modes. Defaults to disabled.
+    pub mclk_output: bool,
+    /// Audio sample rate (frame frequency), in Hz. Used to calculate the prescaler in master
+    /// modes. Defaults to 48kHz.
+    pub sample_rate: u32,
+}
+
+impl Default for I2sConfig {
+    fn default() -> Self {
+        Self {
+            mode: I2sMode::MasterTransmit,
+            standard: I2sStandard::Philips,
+            data_format: DataFormat::D16C16,
+            clock_polarity: I2sClockPolarity::IdleLow,
+            mclk_output: false,
+            sample_rate: 48_000,
+        }
+    }
+}
+
+/// Represents an SPI peripheral, configured in I2S mode.
+pub struct I2s<R> {
+    pub regs: R,
+    pub cfg: I2sConfig,
+}
+
+impl<R> I2s<R>
+where
+    R: Deref<Target = pac::spi1::RegisterBlock> + RccPeriph,
+{
+    /// Initialize an SPI peripheral in I2S mode, including configuration register writes, and enabling
+    /// and resetting its RCC peripheral clock. `i2s_clock` is the frequency of the I2S kernel clock, in
+    /// Hz: On F3 and G4, this defaults to the system clock. (RCC `I2SSRC` and `I2S23SEL` fields). On F4,
+    /// this is the PLLI2S output. It's unused in slave modes. Returns an error if the I2S clock can't be
+    /// divided to reach the sample rate.
+    pub fn new(regs: R, cfg: I2sConfig, i2s_clock: u32) -> Result<Self, ValueError> {
+        let rcc = unsafe { &(*RCC::ptr()) };
+        R::en_reset(rcc);
+
+        // F446 RM, section 26.6.7: I2S master mode. Procedure:
+        // 1. Select the I2SDIV[7:0] bits in the SPI_I2SPR register to define the serial clock baud
+        // rate to reach the proper audio sample frequency. The ODD bit in the SPI_I2SPR
+        // register also has to be defined.
+        // 2. Select the CKPOL bit to define the steady level for the communication clock. Set the
+        // MCKOE bit in the SPI_I2SPR register if the master clock MCK needs to be provided to
+        // the external DAC/ADC audio component (the I2SDIV and ODD values should be
+        // computed depending on the state of the MCK output, for more details refer to
+        // Section 26.6.4: Clock generator).
+        let master = cfg.mode == I2sMode::MasterTransmit || cfg.mode == I2sMode::MasterReceive;
+
+        if master {
+            let (i2sdiv, odd) =
+                calc_prescaler(i2s_clock, cfg.sample_rate, cfg.data_format, cfg.mclk_output)?;
+
+            regs.i2spr.write(|w| unsafe {
+                w.i2sdiv().bits(i2sdiv);
+                w.odd().bit(odd);
+                w.mckoe().bit(cfg.mclk_output)
+            });
+        }
+
+        let (datlen, chlen) = cfg.data_format.bits();
+
+        // 3. Set the I2SMOD bit in the SPI_I2SCFGR register to activate the I2S functionalities and
+        // choose the I2S standard through the I2SSTD[1:0] and PCMSYNC bits, the data length
+        // through the DATLEN[1:0] bits and the number of bits per channel by configuring the
+        // CHLEN bit. Select also the I2S master mode and direction (Transmitter or Receiver)
+        // through the I2SCFG[1:0] bits in the SPI_I2SCFGR register.
+        regs.i2scfgr.write(|w| unsafe {
+            w.i2smod().set_bit();
+            w.i2scfg().bits(cfg.mode as u8);
+            w.i2sstd().bits(cfg.standard.bits());
+            w.pcmsync().bit(cfg.standard == I2sStandard::PcmLong);
+            w.ckpol().bit(cfg.clock_polarity as u8 != 0);
+            w.datlen().bits(datlen);
+            w.chlen().bit(chlen)
+        });
+
+        // 4. If needed, select all the potential interruption sources and the DMA capabilities by
+        // writing the SPI_CR2 register.
+        // 5. The I2SE bit in SPI_I2SCFGR register must be set.
+        // (Handled by `enable()`, after any DMA configuration.)
+
+        Ok(Self { regs, cfg })
+    }
+
+    /// Enable the I2S peripheral. In master mode, this starts generating the clocks. Sets the `I2SCFGR`
+    /// register, `I2SE` field.
+    pub fn enable(&mut self) {
+        self.regs.i2scfgr.modify(|_, w| w.i2se().set_bit());
+    }
+
+    /// Disable the I2S peripheral. See F446 RM, section 26.6.7: "To switch off the I2S". In transmit
+    /// modes, waits until the last data is sent.
+    pub fn disable(&mut self) {
+        if self.cfg.mode == I2sMode::MasterTransmit || self.cfg.mode == I2sMode::SlaveTransmit {
+            // Wait for TXE = 1 and BSY = 0.
+            let mut i = 0;
+            while self.regs.sr.read().txe().bit_is_clear() || self.regs.sr.read().bsy().bit_is_set()
+            {
+                i += 1;
+                if i >= MAX_ITERS {
+                    break;
+                }
+            }
+        }
+
+        self.regs.i2scfgr.modify(|_, w| w.i2se().clear_bit());
+    }
+
+    /// Check status flags for errors.
+    fn check_errors(&self) -> Result<(), I2sError> {
+        let sr = self.regs.sr.read();
+
+        if sr.ovr().bit_is_set() {
+            return Err(I2sError::Overrun);
+        }
+
+        // The G4 PAC doesn't have the UDR field.
+        #[cfg(not(feature = "g4"))]
+        if sr.udr().bit_is_set() {
+            return Err(I2sError::Underrun);
+        }
+
+        // FRE is bit 8; it's called TIFRFE on some PACs.
+        if sr.bits() & (1 << 8) != 0 {
+            return Err(I2sError::FrameFormat);
+        }
+
+        Ok(())
+    }
+
+    /// Write a 16-bit word, blocking until there's room in the transmit buffer. 24 and 32-bit
+    /// data is sent as 2 words: the most significant half first.
+    pub fn write(&mut self, word: u16) -> Result<(), I2sError> {
+        self.check_errors()?;
+
+        let mut i = 0;
+        while self.regs.sr.read().txe().bit_is_clear() {
+            i += 1;
+            if i >= MAX_ITERS {
+                return Err(I2sError::Hardware);
+            }
+        }
+
+        #[allow(invalid_reference_casting)]
+        unsafe {
+            ptr::write_volatile(&self.regs.dr as *const _ as *mut u16, word)
+        };
+
+        Ok(())
+    }
+
+    /// Read a 16-bit word, blocking until it's available. 24 and 32-bit data is received as
+    /// 2 words: the most significant half first.
+    pub fn read(&mut self) -> Result<u16, I2sError> {
+        self.check_errors()?;
+
+        let mut i = 0;
+        while self.regs.sr.read().rxne().bit_is_clear() {
+            i += 1;
+            if i >= MAX_ITERS {
+                return Err(I2sError::Hardware);
+            }
+        }
+
+        Ok(unsafe { ptr::read_volatile(&self.regs.dr as *const _ as *const u16) })
+    }
+
+    /// Write a left and right sample, blocking until complete. For 16-bit data, the sample is in
+    /// the lower 16 bits. For 24 and 32-bit data, the sample is left-aligned, ie its MSB is bit 31;
+    /// the upper half-word is sent first.
+    pub fn write_frame(&mut self, left: i32, right: i32) -> Result<(), I2sError> {
+        for sample in [left, right] {
+            if matches!(
+                self.cfg.data_format,
+                DataFormat::D24C32 | DataFormat::D32C32
+            ) {
+                self.write((sample >> 16) as u16)?;
+                self.write(sample as u16)?;
+            } else {
+                self.write(sample as u16)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Transmit data using DMA. The buffer contains raw 16-bit words, in transmission order.
+    /// See F446 RM, section 26.6.10: DMA features.
+    /// On F3, the `channel` token must be for the channel hard-wired to this peripheral on its
+    /// DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise.
+    pub unsafe fn write_dma(
+        &mut self,
+        buf: &[u16],
+        channel: &ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) {
+        let (ptr, len) = (buf.as_ptr(), buf.len());
+
+        self.start_dma(
+            ptr as u32,
+            len as u16,
+            channel,
+            channel_cfg,
+            dma::Direction::ReadFromMem,
+        );
+    }
+
+    /// Transmit 24 or 32-bit samples using DMA. Each sample must be stored with its half-words
+    /// swapped (see `to_i2s_order`), since the peripheral expects the most significant half first.
+    pub unsafe fn write_dma_i32(
+        &mut self,
+        buf: &[i32],
+        channel: &ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) {
+        let (ptr, len) = (buf.as_ptr(), buf.len());
+
+        self.start_dma(
+            ptr as u32,
+            (len * 2) as u16,
+            channel,
+            channel_cfg,
+            dma::Direction::ReadFromMem,
+        );
+    }
+
+    /// Receive data using DMA, as raw 16-bit words in reception order.
+    /// See F446 RM, section 26.6.10: DMA features.
+    /// On F3, the `channel` token must be for the channel hard-wired to this peripheral on its
+    /// DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise.
+    pub unsafe fn read_dma(
+        &mut self,
+        buf: &mut [u16],
+        channel: &ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) {
+        let (ptr, len) = (buf.as_mut_ptr(), buf.len());
+
+        self.start_dma(
+            ptr as u32,
+            len as u16,
+            channel,
+            channel_cfg,
+            dma::Direction::ReadFromPeriph,
+        );
+    }
+
+    /// Receive 24 or 32-bit samples using DMA. Each received sample has its half-words swapped;
+    /// use `from_i2s_order` to convert.
+    pub unsafe fn read_dma_i32(
+        &mut self,
+        buf: &mut [i32],
+        channel: &ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) {
+        let (ptr, len) = (buf.as_mut_ptr(), buf.len());
+
+        self.start_dma(
+            ptr as u32,
+            (len * 2) as u16,
+            channel,
+            channel_cfg,
+            dma::Direction::ReadFromPeriph,
+        );
+    }
+
+    /// Common DMA setup for reads and writes. Transfers are always in 16-bit words, since
+    /// that's the width of the data register.
+    unsafe fn start_dma(
+        &mut self,
+        mem_addr: u32,
+        num_data: u16,
+        channel: &ChannelToken,
+        channel_cfg: ChannelCfg,
+        direction: dma::Direction,
+    ) {
+        let dma_periph = channel.periph();
+
+        #[cfg(not(feature = "f3"))]
+        let channel = channel.channel();
+        #[cfg(feature = "f3")]
+        let channel = dma::check_fixed_channel(
+            channel,
+            dma_periph,
+            match direction {
+                dma::Direction::ReadFromPeriph => R::read_chan(dma_periph),
+                _ => R::write_chan(dma_periph),
+            },
+        );
+
+        let periph_addr = &self.regs.dr as *const _ as u32;
+
+        match dma_periph {
+            dma::DmaPeriph::Dma1 => {
+                let mut regs = unsafe { &(*DMA1::ptr()) };
+                dma::cfg_channel(
+                    &mut regs,
+                    channel,
+                    periph_addr,
+                    mem_addr,
+                    num_data,
+                    direction,
+                    dma::DataSize::S16,
+                    dma::DataSize::S16,
+                    channel_cfg,
+                );
+            }
+            #[cfg(not(feature = "f3x4"))]
+            dma::DmaPeriph::Dma2 => {
+                let mut regs = unsafe { &(*pac::DMA2::ptr()) };
+                dma::cfg_channel(
+                    &mut regs,
+                    channel,
+                    periph_addr,
+                    mem_addr,
+                    num_data,
+                    direction,
+                    dma::DataSize::S16,
+                    dma::DataSize::S16,
+                    channel_cfg,
+                );
+            }
+        }
+
+        match direction {
+            dma::Direction::ReadFromPeriph => self.regs.cr2.modify(|_, w| w.rxdmaen().set_bit()),
+            _ => self.regs.cr2.modify(|_, w| w.txdmaen().set_bit()),
+        }
+    }
+
+    /// Stop a DMA transfer. Stops the channel, and disables the `txdmaen` and `rxdmaen` bits.
+    pub fn stop_dma(&mut self, channel: &ChannelToken) {
+        dma::stop(channel.periph(), channel.channel());
+
+        self.regs.cr2.modify(|_, w| {
+            w.txdmaen().clear_bit();
+            w.rxdmaen().clear_bit()
+        });
+    }
+
+    /// Print the (raw) contents of the status register.
+    pub fn read_status(&self) -> u32 {
+        unsafe { self.regs.sr.read().bits() }
+    }
+}
+
+/// Calculate the `I2SDIV` and `ODD` prescaler values for a given I2S kernel clock and sample
+/// rate. See F446 RM, section 26.6.4: Clock generator:
+/// With MCK output: Fs = I2SxCLK / (256 * (2 * I2SDIV + ODD))
+/// Without, and a 16-bit channel: Fs = I2SxCLK / (32 * (2 * I2SDIV + ODD))
+/// Without, and a 32-bit channel: Fs = I2SxCLK / (64 * (2 * I2SDIV + ODD))
+fn calc_prescaler(
+    i2s_clock: u32,
+    sample_rate: u32,
+    data_format: DataFormat,
+    mclk_output: bool,
+) -> Result<(u8, bool), ValueError> {
+    let frame_factor = if mclk_output {
+        256
+    } else {
+        data_format.channel_len() * 2
+    };
+
+    // Round to the nearest divider.
+    let denom = frame_factor * sample_rate;
+    if denom == 0 {
+        return Err(ValueError {});
+    }
+    let div = (i2s_clock + denom / 2) / denom;
+
+    let i2sdiv = div / 2;
+
+    // I2SDIV = 0 or 1 are forbidden values.
+    if !(2..=255).contains(&i2sdiv) {
+        return Err(ValueError {});
+    }
+
+    Ok((i2sdiv as u8, div % 2 == 1))
+}
+
+/// Swap a sample's half-words, for use with 24 and 32-bit DMA transfers; these are sent most-significant
+/// half-word first, while samples are stored least-significant half-word first in memory.
+pub fn to_i2s_order(sample: i32) -> i32 {
+    (sample as u32).rotate_left(16) as i32
+}
+
+/// Convert a sample received with DMA in 24 or 32-bit mode to a normal `i32`. See `to_i2s_order`.
+pub fn from_i2s_order(word: i32) -> i32 {
+    (word as u32).rotate_left(16) as i32
+}
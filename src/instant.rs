@@ -1,4 +1,7 @@
 //! This module fits the requirement of `rtic-monotonic`, but has uses beyond that.
+//!
+//! Time is stored as an integer tick count, with an exact tick rate. Conversions to
+//! `core::time::Duration` use integer math, so precision doesn't degrade on long runs.
 
 use core::{
     self,
@@ -7,55 +10,131 @@ use core::{
     time::Duration,
 };
 
-/// A time instant, from the start of a timer, for use with `rtic-monotonic`. Currently only
-/// has microsecond precision.
-#[derive(Eq, PartialEq, PartialOrd, Copy, Clone, Default)]
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A tick rate, in ticks per second, as an exact fraction: `num / den`. For timers, this is the
+/// timer clock, divided by (PSC + 1). Defaults to 1Ghz; ie nanosecond ticks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TickRate {
+    pub num: u32,
+    pub den: u32,
+}
+
+impl Default for TickRate {
+    fn default() -> Self {
+        Self {
+            num: NANOS_PER_SEC as u32,
+            den: 1,
+        }
+    }
+}
+
+impl TickRate {
+    /// Convert a number of ticks to a duration. Rounds down to the nearest nanosecond.
+    pub fn ticks_to_duration(&self, ticks: u64) -> Duration {
+        // In units of 1/num seconds.
+        let total = ticks as u128 * self.den as u128;
+        let num = self.num as u128;
+
+        Duration::new(
+            (total / num) as u64,
+            (total % num * NANOS_PER_SEC / num) as u32,
+        )
+    }
+
+    /// Convert a duration to a number of ticks. Rounds up to the nearest tick, eg so that timeouts
+    /// don't expire early.
+    pub fn duration_to_ticks(&self, duration: Duration) -> u64 {
+        let divisor = self.den as u128 * NANOS_PER_SEC;
+        (duration.as_nanos() * self.num as u128).div_ceil(divisor) as u64
+    }
+}
+
+/// A time instant, from the start of a timer, for use with `rtic-monotonic`. Instants with
+/// different tick rates may be compared and subtracted.
+#[derive(Copy, Clone, Default, Debug)]
 pub struct Instant {
-    /// Total count, in microseconds.
-    /// todo: Do you need ns resolution?
-    // pub count_us: i64, // todo: u64 or i64? x128?
-    pub count_ns: i64, // todo: u64 or i64? x128?
+    /// Total count, in ticks.
+    pub ticks: u64,
+    pub rate: TickRate,
 }
 
 impl Instant {
+    pub fn new(ticks: u64, rate: TickRate) -> Self {
+        Self { ticks, rate }
+    }
+
+    /// The time since the start, as a `Duration`. Rounds down to the nearest nanosecond.
+    pub fn as_duration(&self) -> Duration {
+        self.rate.ticks_to_duration(self.ticks)
+    }
+
+    /// The tick count at a different tick rate. Rounds up to the nearest tick.
+    pub fn ticks_at(&self, rate: TickRate) -> u64 {
+        if rate == self.rate {
+            return self.ticks;
+        }
+
+        let dividend = self.ticks as u128 * self.rate.den as u128 * rate.num as u128;
+        let divisor = self.rate.num as u128 * rate.den as u128;
+        dividend.div_ceil(divisor) as u64
+    }
+
     /// The time, in seconds.
     pub fn as_secs(&self) -> f32 {
-        // self.count_us as f32 / 1_000_000.
-        self.count_ns as f32 / 1_000_000_000.
+        self.as_duration().as_secs_f32()
     }
 
     /// The time, in milliseconds.
     pub fn as_ms(&self) -> f32 {
-        // self.count_us as f32 / 1_000.
-        self.count_ns as f32 / 1_000_000.
+        self.as_duration().as_nanos() as f32 / 1_000_000.
     }
 
     /// The time, in microseconds
     pub fn as_us(&self) -> f32 {
-        // self.count_us as f32
-        self.count_ns as f32 / 1_000.
+        self.as_duration().as_nanos() as f32 / 1_000.
     }
 
     /// The time, in nanoseconds
     pub fn as_ns(&self) -> f32 {
-        self.count_ns as f32
+        self.as_duration().as_nanos() as f32
     }
 }
 
 impl Ord for Instant {
     fn cmp(&self, other: &Self) -> Ordering {
-        // self.count_us.cmp(&other.count_us)
-        self.count_ns.cmp(&other.count_ns)
+        if self.rate == other.rate {
+            return self.ticks.cmp(&other.ticks);
+        }
+
+        // Compare ticks * den / num, without dividing.
+        let lhs = self.ticks as u128 * self.rate.den as u128 * other.rate.num as u128;
+        let rhs = other.ticks as u128 * other.rate.den as u128 * self.rate.num as u128;
+        lhs.cmp(&rhs)
     }
 }
 
+impl PartialOrd for Instant {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Instant {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Instant {}
+
 impl Add<Duration> for Instant {
     type Output = Self;
 
     fn add(self, rhs: Duration) -> Self::Output {
         Self {
-            // count_us: self.count_us + rhs.as_micros() as i64,
-            count_ns: self.count_ns + rhs.as_nanos() as i64,
+            ticks: self.ticks + self.rate.duration_to_ticks(rhs),
+            rate: self.rate,
         }
     }
 }
@@ -65,8 +144,8 @@ impl Sub<Duration> for Instant {
 
     fn sub(self, rhs: Duration) -> Self::Output {
         Self {
-            // count_us: self.count_us - rhs.as_micros() as i64,
-            count_ns: self.count_ns - rhs.as_nanos() as i64,
+            ticks: self.ticks.saturating_sub(self.rate.duration_to_ticks(rhs)),
+            rate: self.rate,
         }
     }
 }
@@ -74,9 +153,13 @@ impl Sub<Duration> for Instant {
 impl Sub<Self> for Instant {
     type Output = Duration;
 
+    /// Saturates at 0, if `rhs` is later than `self`.
     fn sub(self, rhs: Self) -> Self::Output {
-        // todo: Handle negative overflow!
-        // Duration::from_micros((self.count_us - rhs.count_us) as u64)
-        Duration::from_nanos((self.count_ns - rhs.count_ns) as u64)
+        if self.rate == rhs.rate {
+            self.rate
+                .ticks_to_duration(self.ticks.saturating_sub(rhs.ticks))
+        } else {
+            self.as_duration().saturating_sub(rhs.as_duration())
+        }
     }
 }
@@ -94,6 +94,8 @@
 //!
 //! Supports the RTIC `Monotonic` trait. To enable, use the `monotonic` feature.
 //!
+//! For `rtic-monotonics` v2, with `fugit` time types, use the `monotonic_v2` feature, and `timer::TimerMonotonic`.
+//!
 //! [This article](https://www.anyleaf.org/blog/writing-embedded-firmware-using-rust) provides some information
 //! on using this library, as well as background information on Rust embedded in general.
 //!
@@ -334,6 +336,9 @@ pub use stm32wl::stm32wle5 as pac;
 #[cfg(not(any(feature = "f301", feature = "f302")))]
 pub mod adc;
 
+#[cfg(feature = "h7")]
+pub mod bdma;
+
 // bxCAN families: F3, F4, L4,
 // fdCAN families: L5, U5, G4, H7
 // H7 suppords fd and can_ccu. (What's that?)
@@ -387,7 +392,7 @@ pub mod dac;
 )))]
 pub mod dfsdm;
 
-#[cfg(not(any(feature = "f4", feature = "l552", feature = "h5")))]
+#[cfg(not(feature = "h5"))]
 pub mod dma;
 
 #[cfg(all(feature = "h7", feature = "net"))]
@@ -406,6 +411,13 @@ pub mod gpio;
 #[cfg(feature = "wb")]
 pub mod hsem;
 
+#[cfg(any(
+    feature = "g474",
+    feature = "g484",
+    all(feature = "h7", not(any(feature = "h735", feature = "h7b3")))
+))]
+pub mod hrtim;
+
 #[cfg(not(any(feature = "f4")))]
 pub mod i2c;
 #[cfg(feature = "f4")]
@@ -413,6 +425,10 @@ pub mod i2c_f4;
 #[cfg(feature = "f4")]
 pub use i2c_f4 as i2c;
 
+// SPI in I2S mode; used for audio on families without SAI.
+#[cfg(any(feature = "f3", feature = "f4", feature = "g4"))]
+pub mod i2s;
+
 #[cfg(feature = "wb")]
 pub mod ipcc;
 
@@ -420,6 +436,20 @@ pub mod iwdg;
 
 pub mod low_power;
 
+#[cfg(not(any(
+    feature = "f3",
+    feature = "f4",
+    feature = "g030",
+    feature = "g050",
+    feature = "g070",
+    feature = "g0b0",
+    feature = "h5",
+)))]
+pub mod lptimer;
+
+#[cfg(feature = "h7")]
+pub mod mdma;
+
 #[cfg(any(feature = "h747cm4", feature = "h747cm7"))]
 pub mod power;
 
@@ -0,0 +1,472 @@
+//! Support for the Low-power timer (LPTIM) peripheral. Includes initialization, one-shot and
+//! continuous counting, PWM output, counting of pulses on the external input, and interrupts.
+//!
+//! Clocked from LSE or LSI, LPTIM keeps running in Stop modes, and its interrupts can wake the
+//! MCU from `low_power::stop`. This makes it suitable as a low-power tick source, or for counting
+//! pulses while the core is stopped. Per L4 RM, section 35.3: LPTIM1 is functional down to Stop 2;
+//! LPTIM2 only down to Stop 1. (Other families have similar restrictions; check your RM)
+//!
+//! LPTIM interrupts are connected to direct EXTI lines, which are unmasked at reset, so unmasking the
+//! interrupt in the NVIC is all that's required for it to wake the MCU.
+
+// todo: Encoder mode, trigger inputs, timeout, repetition counter (WL and L5), and DMA.
+
+use cfg_if::cfg_if;
+use cortex_m::asm;
+use num_traits::float::FloatCore; // To round floats.
+use paste::paste;
+
+use crate::{
+    clocks::Clocks,
+    pac::{self, RCC},
+    MAX_ITERS,
+};
+
+/// LPTIM error
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LpTimError {
+    /// The requested period or frequency is out of range, for the kernel clock and prescaler.
+    Value,
+    /// A write to the auto-reload or compare register didn't complete; eg because the kernel
+    /// clock isn't running.
+    Hardware,
+}
+
+/// Clock source for the LPTIM kernel clock. Sets `RCC_CCIPR` register, `LPTIMxSEL` field.
+/// (`RCC_D2CCIP2R` and `RCC_D3CCIPR` on H7)
+#[derive(Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum LpTimClockSrc {
+    /// The APB clock. Stops in Stop modes.
+    #[cfg(not(feature = "h7"))]
+    Pclk = 0b00,
+    #[cfg(feature = "h7")]
+    Pclk = 0b000,
+    /// The LSI oscillator. This is enabled when initializing the timer, if not already running.
+    #[cfg(not(feature = "h7"))]
+    Lsi = 0b01,
+    #[cfg(feature = "h7")]
+    Lsi = 0b100,
+    /// HSI16. Stops in Stop modes.
+    #[cfg(not(feature = "h7"))]
+    Hsi16 = 0b10,
+    /// The LSE oscillator. This must already be running; eg set up by `Rtc::new`.
+    #[cfg(not(feature = "h7"))]
+    Lse = 0b11,
+    #[cfg(feature = "h7")]
+    Lse = 0b011,
+}
+
+/// Clock prescaler. Sets `LPTIM_CFGR` register, `PRESC` field.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum LpTimPrescaler {
+    Div1 = 0b000,
+    Div2 = 0b001,
+    Div4 = 0b010,
+    Div8 = 0b011,
+    Div16 = 0b100,
+    Div32 = 0b101,
+    Div64 = 0b110,
+    Div128 = 0b111,
+}
+
+impl LpTimPrescaler {
+    pub fn value(&self) -> u8 {
+        1 << (*self as u8)
+    }
+}
+
+/// What increments the counter. Sets `LPTIM_CFGR` register, `COUNTMODE` field.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum CountSource {
+    /// The counter is incremented following each internal clock pulse.
+    Internal = 0,
+    /// The counter is incremented following each valid clock pulse on the LPTIM external Input1.
+    /// The internal clock is still used to sample the input.
+    External = 1,
+}
+
+/// The active edge(s) of the external input, when counting external pulses.
+/// Sets `LPTIM_CFGR` register, `CKPOL` field.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum InputEdge {
+    Rising = 0b00,
+    Falling = 0b01,
+    /// Both edges are active. Requires an internal clock source 4 times faster than the
+    /// input pulses.
+    Both = 0b10,
+}
+
+/// Output waveform polarity. Sets `LPTIM_CFGR` register, `WAVPOL` field.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum OutputPolarity {
+    /// The output is set when the counter matches the compare value, and reset when it matches
+    /// the auto-reload value.
+    ActiveHigh = 0,
+    /// The inverse of `ActiveHigh`.
+    ActiveLow = 1,
+}
+
+/// Whether the counter runs continuously, or stops after reaching the auto-reload value once.
+#[derive(Clone, Copy)]
+pub enum LpTimMode {
+    /// Sets `LPTIM_CR` register, `CNTSTRT` bit.
+    Continuous,
+    /// Sets `LPTIM_CR` register, `SNGSTRT` bit.
+    OneShot,
+}
+
+/// LPTIM interrupts. Each corresponds to a bit in the `LPTIM_IER`, `LPTIM_ISR`, and `LPTIM_ICR` registers.
+#[derive(Clone, Copy)]
+pub enum LpTimInterrupt {
+    /// The counter matched the compare value.
+    CompareMatch,
+    /// The counter matched the auto-reload value.
+    AutoReloadMatch,
+    /// A valid edge on the selected external trigger input occurred.
+    ExternalTrigger,
+    /// A write to the compare register completed.
+    CompareOk,
+    /// A write to the auto-reload register completed.
+    AutoReloadOk,
+    /// The counter direction changed from down to up. (Encoder mode)
+    Up,
+    /// The counter direction changed from up to down. (Encoder mode)
+    Down,
+}
+
+/// Initial configuration data for Low-power Timer peripherals.
+#[derive(Clone)]
+pub struct LpTimerConfig {
+    /// Kernel clock source. Use LSE or LSI to keep the timer running in Stop modes. Defaults to LSI.
+    pub clock_source: LpTimClockSrc,
+    /// Kernel clock prescaler. Defaults to 1.
+    pub prescaler: LpTimPrescaler,
+    /// Count internal clock pulses, or pulses on the external input. Defaults to internal.
+    pub count_source: CountSource,
+    /// Active edge(s) when counting external pulses. Defaults to rising.
+    pub input_edge: InputEdge,
+    /// PWM output polarity. Defaults to active high.
+    pub output_polarity: OutputPolarity,
+    /// If true, writes to the auto-reload and compare registers take effect at the end of the current
+    /// period, vice immediately. Useful when changing period and duty while the timer is running.
+    /// Defaults to false. Sets `LPTIM_CFGR` register, `PRELOAD` field.
+    pub preload: bool,
+}
+
+impl Default for LpTimerConfig {
+    fn default() -> Self {
+        Self {
+            clock_source: LpTimClockSrc::Lsi,
+            prescaler: LpTimPrescaler::Div1,
+            count_source: CountSource::Internal,
+            input_edge: InputEdge::Rising,
+            output_polarity: OutputPolarity::ActiveHigh,
+            preload: false,
+        }
+    }
+}
+
+/// Represents a Low-power timer.
+pub struct LpTimer<R> {
+    /// Register block for the specific timer.
+    pub regs: R,
+    /// Our config stucture, for configuration that is written to the timer hardware on initialization
+    /// via the constructor.
+    pub cfg: LpTimerConfig,
+    /// Kernel clock speed in Hz, after the prescaler.
+    clock_speed: u32,
+    /// CPU cycles to wait after setting `ENABLE`, before the timer is actually enabled.
+    enable_delay: u32,
+    /// The mode the timer was last started in, if it's counting.
+    mode: Option<LpTimMode>,
+}
+
+/// Turn on the LSI, and wait for it to be ready.
+fn enable_lsi(rcc: &pac::rcc::RegisterBlock) {
+    cfg_if! {
+        if #[cfg(feature = "wb")] {
+            rcc.csr.modify(|_, w| w.lsi1on().set_bit());
+            while rcc.csr.read().lsi1rdy().bit_is_clear() {}
+        } else {
+            rcc.csr.modify(|_, w| w.lsion().set_bit());
+            while rcc.csr.read().lsirdy().bit_is_clear() {}
+        }
+    }
+}
+
+macro_rules! make_lptimer {
+    ($LPTIMX:ident, $lptim:ident, $enr:ident, $rstr:ident, $ccipr:ident, $sel:ident, $pclk:ident) => {
+        impl LpTimer<pac::$LPTIMX> {
+            paste! {
+                /// Initialize a Low-power timer, including enabling and resetting its RCC peripheral
+                /// clock, and selecting its kernel clock. The timer is left enabled, with a maximum
+                /// auto-reload value, but not counting; set a period or frequency, and run `start` to
+                /// start it.
+                pub fn [<new_ $lptim>](regs: pac::$LPTIMX, cfg: LpTimerConfig, clocks: &Clocks) -> Self {
+                    let rcc = unsafe { &(*RCC::ptr()) };
+
+                    rcc.$enr.modify(|_, w| w.[<$lptim en>]().set_bit());
+                    rcc.$rstr.modify(|_, w| w.[<$lptim rst>]().set_bit());
+                    rcc.$rstr.modify(|_, w| w.[<$lptim rst>]().clear_bit());
+
+                    if cfg.clock_source == LpTimClockSrc::Lsi {
+                        enable_lsi(rcc);
+                    }
+
+                    rcc.$ccipr.modify(|_, w| unsafe { w.$sel().bits(cfg.clock_source as u8) });
+
+                    let kernel_clock = match cfg.clock_source {
+                        LpTimClockSrc::Pclk => clocks.$pclk(),
+                        LpTimClockSrc::Lsi => 32_000,
+                        #[cfg(not(feature = "h7"))]
+                        LpTimClockSrc::Hsi16 => 16_000_000,
+                        LpTimClockSrc::Lse => 32_768,
+                    };
+
+                    // The CFGR register must only be modified when the LPTIM is disabled.
+                    regs.cfgr.modify(|_, w| unsafe {
+                        w.cksel().clear_bit();
+                        w.countmode().bit(cfg.count_source as u8 != 0);
+                        w.ckpol().bits(cfg.input_edge as u8);
+                        w.presc().bits(cfg.prescaler as u8);
+                        w.wavpol().bit(cfg.output_polarity as u8 != 0);
+                        w.wave().clear_bit();
+                        w.preload().bit(cfg.preload)
+                    });
+
+                    let clock_speed = kernel_clock / cfg.prescaler.value() as u32;
+
+                    // "After setting the ENABLE bit to '1', a delay of two counter clock is needed
+                    // before the LPTIM is actually enabled." (L4 RM, section 35.7.4). Round up, and
+                    // pad by x2.
+                    let cpu_per_kernel_cycles = clocks.sysclk() / kernel_clock + 1;
+                    let enable_delay = cpu_per_kernel_cycles * 2 * 2;
+
+                    let mut result = Self {
+                        regs,
+                        cfg,
+                        clock_speed,
+                        enable_delay,
+                        mode: None,
+                    };
+
+                    result.enable();
+                    // If this times out, the kernel clock isn't running; setting a period or frequency
+                    // will report it.
+                    result.set_auto_reload(u16::MAX).ok();
+
+                    result
+                }
+            }
+
+            /// Enable the timer. This doesn't start counting; use `start` for that. ARR and CMP can only be
+            /// written while the timer is enabled. Blocks for 2 kernel clock cycles, until the enable
+            /// takes effect.
+            pub fn enable(&mut self) {
+                self.regs.cr.modify(|_, w| w.enable().set_bit());
+                asm::delay(self.enable_delay);
+            }
+
+            /// Disable the timer. This stops and resets the counter.
+            pub fn disable(&mut self) {
+                self.regs.cr.modify(|_, w| w.enable().clear_bit());
+                self.mode = None;
+            }
+
+            /// Check if the timer is enabled.
+            pub fn is_enabled(&self) -> bool {
+                self.regs.cr.read().enable().bit_is_set()
+            }
+
+            /// Start counting, either continuously, or once up to the auto-reload value. Enables the
+            /// timer if it isn't already.
+            pub fn start(&mut self, mode: LpTimMode) {
+                if !self.is_enabled() {
+                    self.enable();
+                }
+
+                match mode {
+                    LpTimMode::Continuous => self.regs.cr.modify(|_, w| w.cntstrt().set_bit()),
+                    LpTimMode::OneShot => self.regs.cr.modify(|_, w| w.sngstrt().set_bit()),
+                }
+                self.mode = Some(mode);
+            }
+
+            /// Stop counting. This disables the timer, and resets the count. The auto-reload and compare
+            /// values are retained.
+            pub fn stop(&mut self) {
+                self.disable();
+            }
+
+            /// Set the timer period, in seconds. The timer must be enabled. Only valid when counting
+            /// the internal clock.
+            pub fn set_period(&mut self, period: f32) -> Result<(), LpTimError> {
+                assert!(period > 0.);
+                self.set_freq(1. / period)
+            }
+
+            /// Set the timer frequency, in Hz. The timer must be enabled. Only valid when counting
+            /// the internal clock. Since the prescaler can only be changed while the timer is disabled,
+            /// only the auto-reload value is changed; select a prescaler in the config that allows the
+            /// frequencies you need.
+            pub fn set_freq(&mut self, freq: f32) -> Result<(), LpTimError> {
+                assert!(freq > 0.);
+
+                let arr = (self.clock_speed as f32 / freq - 1.).round();
+
+                if arr < 1. || arr > u16::MAX as f32 {
+                    return Err(LpTimError::Value);
+                }
+
+                self.set_auto_reload(arr as u16)
+            }
+
+            /// Set the auto-reload register value. The timer must be enabled. Blocks until the write
+            /// completes, or returns an error if it times out. Note that the auto-reload value must be
+            /// greater than the compare value.
+            pub fn set_auto_reload(&mut self, arr: u16) -> Result<(), LpTimError> {
+                self.regs.arr.write(|w| unsafe { w.arr().bits(arr) });
+
+                let mut i = 0;
+                while self.regs.isr.read().arrok().bit_is_clear() {
+                    i += 1;
+                    if i >= MAX_ITERS {
+                        return Err(LpTimError::Hardware);
+                    }
+                }
+                self.regs.icr.write(|w| w.arrokcf().set_bit());
+                Ok(())
+            }
+
+            /// Set the compare register value. The timer must be enabled. Blocks until the write
+            /// completes, or returns an error if it times out.
+            pub fn set_compare(&mut self, cmp: u16) -> Result<(), LpTimError> {
+                self.regs.cmp.write(|w| unsafe { w.cmp().bits(cmp) });
+
+                let mut i = 0;
+                while self.regs.isr.read().cmpok().bit_is_clear() {
+                    i += 1;
+                    if i >= MAX_ITERS {
+                        return Err(LpTimError::Hardware);
+                    }
+                }
+                self.regs.icr.write(|w| w.cmpokcf().set_bit());
+                Ok(())
+            }
+
+            /// Return the integer associated with the maximum duty period; ie the auto-reload value.
+            pub fn get_max_duty(&self) -> u16 {
+                self.regs.arr.read().arr().bits()
+            }
+
+            /// Set the PWM duty, as a compare value between 0 and `get_max_duty()`. The PWM output is
+            /// active from the compare value until the auto-reload value, subject to the `output_polarity`
+            /// config setting.
+            pub fn set_duty(&mut self, duty: u16) -> Result<(), LpTimError> {
+                self.set_compare(duty)
+            }
+
+            /// Read the current counter value. When the timer is clocked asynchronously from the APB
+            /// clock (eg LSE or LSI), the value is read until 2 consecutive reads match, per RM
+            /// recommendation. When counting external pulses, this is the pulse count.
+            pub fn read_count(&self) -> u16 {
+                loop {
+                    let a = self.regs.cnt.read().cnt().bits();
+                    let b = self.regs.cnt.read().cnt().bits();
+                    if a == b {
+                        return a;
+                    }
+                }
+            }
+
+            /// Enable a specific type of LPTIM interrupt. The IER register can only be modified
+            /// while the timer is disabled, so the timer is briefly disabled if it's enabled. If
+            /// it was counting, it's restarted in the same mode, from 0.
+            pub fn enable_interrupt(&mut self, interrupt: LpTimInterrupt) {
+                self.modify_ier(interrupt, true);
+            }
+
+            /// Disable a specific type of LPTIM interrupt. The IER register can only be modified
+            /// while the timer is disabled, so the timer is briefly disabled if it's enabled. If
+            /// it was counting, it's restarted in the same mode, from 0.
+            pub fn disable_interrupt(&mut self, interrupt: LpTimInterrupt) {
+                self.modify_ier(interrupt, false);
+            }
+
+            fn modify_ier(&mut self, interrupt: LpTimInterrupt, value: bool) {
+                let was_enabled = self.is_enabled();
+                // `disable` clears this.
+                let mode = self.mode;
+                if was_enabled {
+                    self.disable();
+                }
+
+                self.regs.ier.modify(|_, w| match interrupt {
+                    LpTimInterrupt::CompareMatch => w.cmpmie().bit(value),
+                    LpTimInterrupt::AutoReloadMatch => w.arrmie().bit(value),
+                    LpTimInterrupt::ExternalTrigger => w.exttrigie().bit(value),
+                    LpTimInterrupt::CompareOk => w.cmpokie().bit(value),
+                    LpTimInterrupt::AutoReloadOk => w.arrokie().bit(value),
+                    LpTimInterrupt::Up => w.upie().bit(value),
+                    LpTimInterrupt::Down => w.downie().bit(value),
+                });
+
+                if was_enabled {
+                    match mode {
+                        Some(mode) => self.start(mode),
+                        None => self.enable(),
+                    }
+                }
+            }
+
+            /// Clear an interrupt flag - run this in the interrupt's handler to prevent
+            /// repeat firings.
+            pub fn clear_interrupt(&mut self, interrupt: LpTimInterrupt) {
+                self.regs.icr.write(|w| match interrupt {
+                    LpTimInterrupt::CompareMatch => w.cmpmcf().set_bit(),
+                    LpTimInterrupt::AutoReloadMatch => w.arrmcf().set_bit(),
+                    LpTimInterrupt::ExternalTrigger => w.exttrigcf().set_bit(),
+                    LpTimInterrupt::CompareOk => w.cmpokcf().set_bit(),
+                    LpTimInterrupt::AutoReloadOk => w.arrokcf().set_bit(),
+                    LpTimInterrupt::Up => w.upcf().set_bit(),
+                    LpTimInterrupt::Down => w.downcf().set_bit(),
+                });
+            }
+        }
+    };
+}
+
+cfg_if! {
+    if #[cfg(feature = "g0")] {
+        make_lptimer!(LPTIM1, lptim1, apbenr1, apbrstr1, ccipr, lptim1sel, apb1);
+        make_lptimer!(LPTIM2, lptim2, apbenr1, apbrstr1, ccipr, lptim2sel, apb1);
+    } else if #[cfg(feature = "g4")] {
+        make_lptimer!(LPTIMER1, lptim1, apb1enr1, apb1rstr1, ccipr, lptim1sel, apb1);
+    } else if #[cfg(feature = "l5")] {
+        make_lptimer!(LPTIM1, lptim1, apb1enr1, apb1rstr1, ccipr1, lptim1sel, apb1);
+        make_lptimer!(LPTIM2, lptim2, apb1enr2, apb1rstr2, ccipr1, lptim2sel, apb1);
+        make_lptimer!(LPTIM3, lptim3, apb1enr2, apb1rstr2, ccipr1, lptim3sel, apb1);
+    } else if #[cfg(feature = "h7b3")] {
+        // H7B3 names its kernel clock registers by CD and SRD domain, vice D2 and D3, and has no
+        // LPTIM4 or LPTIM5.
+        make_lptimer!(LPTIM1, lptim1, apb1lenr, apb1lrstr, cdccip2r, lptim1sel, apb1);
+        make_lptimer!(LPTIM2, lptim2, apb4enr, apb4rstr, srdccipr, lptim2sel, apb4);
+        make_lptimer!(LPTIM3, lptim3, apb4enr, apb4rstr, srdccipr, lptim3sel, apb4);
+    } else if #[cfg(feature = "h7")] {
+        make_lptimer!(LPTIM1, lptim1, apb1lenr, apb1lrstr, d2ccip2r, lptim1sel, apb1);
+        make_lptimer!(LPTIM2, lptim2, apb4enr, apb4rstr, d3ccipr, lptim2sel, apb4);
+        make_lptimer!(LPTIM3, lptim3, apb4enr, apb4rstr, d3ccipr, lptim345sel, apb4);
+        make_lptimer!(LPTIM4, lptim4, apb4enr, apb4rstr, d3ccipr, lptim345sel, apb4);
+        make_lptimer!(LPTIM5, lptim5, apb4enr, apb4rstr, d3ccipr, lptim345sel, apb4);
+    } else { // L4, WB, WL
+        make_lptimer!(LPTIM1, lptim1, apb1enr1, apb1rstr1, ccipr, lptim1sel, apb1);
+        make_lptimer!(LPTIM2, lptim2, apb1enr2, apb1rstr2, ccipr, lptim2sel, apb1);
+        #[cfg(feature = "wl")]
+        make_lptimer!(LPTIM3, lptim3, apb1enr2, apb1rstr2, ccipr, lptim3sel, apb1);
+    }
+}
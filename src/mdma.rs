@@ -0,0 +1,321 @@
+//! Support for the Master Direct Memory Access (MDMA) controller, on H7. The MDMA has 16 channels,
+//! and can access all memories, including the TCMs. It's used for memory-to-memory transfers,
+//! and for peripherals on the AXI/AHB3 matrix such as QUADSPI, SDMMC, JPEG and DMA2D. It can
+//! also be triggered by the transfer complete flags of DMA1 and DMA2 streams.
+//! See H743 RM, section 14: MDMA.
+
+use core::sync::atomic::{self, Ordering};
+
+use crate::{
+    dma::{DataSize, IncrMode, Priority},
+    pac::{mdma, MDMA, RCC},
+};
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+/// An MDMA channel.
+pub enum MdmaChannel {
+    C0 = 0,
+    C1 = 1,
+    C2 = 2,
+    C3 = 3,
+    C4 = 4,
+    C5 = 5,
+    C6 = 6,
+    C7 = 7,
+    C8 = 8,
+    C9 = 9,
+    C10 = 10,
+    C11 = 11,
+    C12 = 12,
+    C13 = 13,
+    C14 = 14,
+    C15 = 15,
+}
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+/// Hardware request sources for the MDMA. Sets CxTBR register, TSEL field. See H743 RM,
+/// Table 95: "MDMA hardware requests".
+pub enum MdmaInput {
+    Dma1Stream0Tc = 0,
+    Dma1Stream1Tc = 1,
+    Dma1Stream2Tc = 2,
+    Dma1Stream3Tc = 3,
+    Dma1Stream4Tc = 4,
+    Dma1Stream5Tc = 5,
+    Dma1Stream6Tc = 6,
+    Dma1Stream7Tc = 7,
+    Dma2Stream0Tc = 8,
+    Dma2Stream1Tc = 9,
+    Dma2Stream2Tc = 10,
+    Dma2Stream3Tc = 11,
+    Dma2Stream4Tc = 12,
+    Dma2Stream5Tc = 13,
+    Dma2Stream6Tc = 14,
+    Dma2Stream7Tc = 15,
+    LtdcLineInterrupt = 16,
+    JpegInFifoThresh = 17,
+    JpegInFifoNotFull = 18,
+    JpegOutFifoThresh = 19,
+    JpegOutFifoNotEmpty = 20,
+    JpegEndOfConversion = 21,
+    QuadspiFifoThresh = 22,
+    QuadspiTransferComplete = 23,
+    Dma2dClutTransferComplete = 24,
+    Dma2dTransferComplete = 25,
+    Dma2dTransferWatermark = 26,
+    Sdmmc1EndData = 27,
+    Sdmmc1EndBuffer = 28,
+    Sdmmc1CommandEnd = 29,
+}
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+/// Selects what a single request transfers. Sets CxTCR register, TRGM field.
+pub enum TriggerMode {
+    /// Each request transfers one buffer; its length is set by `buffer_len`.
+    Buffer = 0b00,
+    /// Each request transfers a whole block; `num_bytes` in `cfg_channel`.
+    Block = 0b01,
+    /// Each request transfers a repeated block.
+    RepeatedBlock = 0b10,
+    /// Each request transfers the whole linked list of transfers.
+    LinkedList = 0b11,
+}
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+/// Burst size, in beats. Sets CxTCR register, SBURST and DBURST fields.
+pub enum MdmaBurst {
+    Single = 0,
+    Beats2 = 1,
+    Beats4 = 2,
+    Beats8 = 3,
+    Beats16 = 4,
+    Beats32 = 5,
+    Beats64 = 6,
+    Beats128 = 7,
+}
+
+#[derive(Copy, Clone)]
+/// MDMA interrupt type. Set in CxCR, and cleared in CxIFCR.
+pub enum MdmaInterrupt {
+    TransferError,
+    /// The whole channel transfer is complete. (CTCIF)
+    ChannelTransferComplete,
+    BlockRepeatTransferComplete,
+    BlockTransferComplete,
+    /// A single buffer transfer is complete. (TCIF)
+    BufferTransferComplete,
+}
+
+/// Configuration for an MDMA channel, not including addresses and data sizes.
+#[derive(Clone)]
+pub struct MdmaChannelCfg {
+    /// Channel priority. Defaults to medium.
+    pub priority: Priority,
+    /// Defaults to `Block`.
+    pub trigger_mode: TriggerMode,
+    /// The hardware request that triggers transfers. If `None`, the transfer is triggered by
+    /// software, and starts as soon as the channel is configured. Defaults to `None`.
+    pub request: Option<MdmaInput>,
+    /// Source increment mode. Defaults to enabled.
+    pub src_incr: IncrMode,
+    /// Destination increment mode. Defaults to enabled.
+    pub dest_incr: IncrMode,
+    pub src_burst: MdmaBurst,
+    pub dest_burst: MdmaBurst,
+    /// Number of bytes transferred per buffer transfer, from 1 to 128. Must be a multiple of
+    /// the source and destination data sizes. Defaults to 128.
+    pub buffer_len: u8,
+}
+
+impl Default for MdmaChannelCfg {
+    fn default() -> Self {
+        Self {
+            priority: Priority::Medium,
+            trigger_mode: TriggerMode::Block,
+            request: None,
+            src_incr: IncrMode::Enabled,
+            dest_incr: IncrMode::Enabled,
+            src_burst: MdmaBurst::Single,
+            dest_burst: MdmaBurst::Single,
+            buffer_len: 128,
+        }
+    }
+}
+
+/// The TCMs must be accessed through the AHB bus; other memories and peripherals through AXI.
+/// Sets CxTBR register, SBUS and DBUS fields.
+fn is_tcm(addr: u32) -> bool {
+    // ITCM at 0x0000_0000 - 0x0000_FFFF; DTCM at 0x2000_0000 - 0x2001_FFFF.
+    addr < 0x0001_0000 || (0x2000_0000..0x2002_0000).contains(&addr)
+}
+
+/// Represents the Master Direct Memory Access (MDMA) peripheral.
+pub struct Mdma {
+    pub regs: MDMA,
+}
+
+impl Mdma {
+    /// Initialize the MDMA peripheral, including enabling and resetting
+    /// its RCC peripheral clock.
+    pub fn new(regs: MDMA) -> Self {
+        let rcc = unsafe { &(*RCC::ptr()) };
+        rcc.ahb3enr.modify(|_, w| w.mdmaen().set_bit());
+        rcc.ahb3rstr.modify(|_, w| w.mdmarst().set_bit());
+        rcc.ahb3rstr.modify(|_, w| w.mdmarst().clear_bit());
+
+        Self { regs }
+    }
+
+    fn ch(&self, channel: MdmaChannel) -> &mdma::CH {
+        match channel {
+            MdmaChannel::C0 => &self.regs.ch0,
+            MdmaChannel::C1 => &self.regs.ch1,
+            MdmaChannel::C2 => &self.regs.ch2,
+            MdmaChannel::C3 => &self.regs.ch3,
+            MdmaChannel::C4 => &self.regs.ch4,
+            MdmaChannel::C5 => &self.regs.ch5,
+            MdmaChannel::C6 => &self.regs.ch6,
+            MdmaChannel::C7 => &self.regs.ch7,
+            MdmaChannel::C8 => &self.regs.ch8,
+            MdmaChannel::C9 => &self.regs.ch9,
+            MdmaChannel::C10 => &self.regs.ch10,
+            MdmaChannel::C11 => &self.regs.ch11,
+            MdmaChannel::C12 => &self.regs.ch12,
+            MdmaChannel::C13 => &self.regs.ch13,
+            MdmaChannel::C14 => &self.regs.ch14,
+            MdmaChannel::C15 => &self.regs.ch15,
+        }
+    }
+
+    /// Configure an MDMA channel, and enable it. `num_bytes` is the block size, in bytes; up to
+    /// 65,536. Sets the Channel Transfer Complete interrupt. If no hardware request is selected
+    /// in `cfg`, starts the transfer with a software request. See H743 RM, section 14.3.
+    pub fn cfg_channel(
+        &mut self,
+        channel: MdmaChannel,
+        src_addr: u32,
+        dest_addr: u32,
+        num_bytes: u32,
+        src_size: DataSize,
+        dest_size: DataSize,
+        cfg: MdmaChannelCfg,
+    ) {
+        // BNDT is 17 bits.
+        assert!(
+            num_bytes <= 65_536,
+            "MDMA blocks are limited to 65,536 bytes."
+        );
+
+        let ch = self.ch(channel);
+
+        // Most of the channel's registers are read-only when EN = 1.
+        ch.cr.modify(|_, w| w.en().clear_bit());
+        while ch.cr.read().en().bit_is_set() {}
+
+        // Clear any flags left over from a previous transfer.
+        ch.ifcr.write(|w| unsafe { w.bits(0b1_1111) });
+
+        ch.sar.write(|w| unsafe { w.bits(src_addr) });
+        ch.dar.write(|w| unsafe { w.bits(dest_addr) });
+
+        atomic::compiler_fence(Ordering::SeqCst);
+
+        // Increments are of 0b10, with an offset matching the data size.
+        let src_incr = if let IncrMode::Enabled = cfg.src_incr {
+            0b10
+        } else {
+            0b00
+        };
+        let dest_incr = if let IncrMode::Enabled = cfg.dest_incr {
+            0b10
+        } else {
+            0b00
+        };
+
+        ch.tcr.write(|w| unsafe {
+            w.sinc().bits(src_incr);
+            w.dinc().bits(dest_incr);
+            w.ssize().bits(src_size as u8);
+            w.dsize().bits(dest_size as u8);
+            w.sincos().bits(src_size as u8);
+            w.dincos().bits(dest_size as u8);
+            w.sburst().bits(cfg.src_burst as u8);
+            w.dburst().bits(cfg.dest_burst as u8);
+            // TLEN is the number of bytes per buffer transfer, minus one.
+            w.tlen().bits(cfg.buffer_len.clamp(1, 128) - 1);
+            w.trgm().bits(cfg.trigger_mode as u8);
+            // Hardware requests are ignored when SWRM is set.
+            w.swrm().bit(cfg.request.is_none())
+        });
+
+        ch.bndtr.write(|w| unsafe { w.bndt().bits(num_bytes) });
+
+        ch.tbr.write(|w| unsafe {
+            w.tsel().bits(cfg.request.map(|r| r as u8).unwrap_or(0));
+            w.sbus().bit(is_tcm(src_addr));
+            w.dbus().bit(is_tcm(dest_addr))
+        });
+
+        // No linked list.
+        ch.lar.write(|w| unsafe { w.bits(0) });
+
+        ch.cr.modify(|_, w| unsafe {
+            w.pl().bits(cfg.priority as u8);
+            w.ctcie().set_bit();
+            w.en().set_bit()
+        });
+
+        if cfg.request.is_none() {
+            ch.cr.modify(|_, w| w.swrq().set_bit());
+        }
+    }
+
+    /// Stop an MDMA transfer, if in progress.
+    pub fn stop(&mut self, channel: MdmaChannel) {
+        let cr = &self.ch(channel).cr;
+        cr.modify(|_, w| w.en().clear_bit());
+        while cr.read().en().bit_is_set() {}
+    }
+
+    /// Clear an interrupt flag.
+    pub fn clear_interrupt(&mut self, channel: MdmaChannel, interrupt: MdmaInterrupt) {
+        self.ch(channel).ifcr.write(|w| match interrupt {
+            MdmaInterrupt::TransferError => w.cteif().set_bit(),
+            MdmaInterrupt::ChannelTransferComplete => w.cctcif().set_bit(),
+            MdmaInterrupt::BlockRepeatTransferComplete => w.cbrtif().set_bit(),
+            MdmaInterrupt::BlockTransferComplete => w.cbtif().set_bit(),
+            MdmaInterrupt::BufferTransferComplete => w.cltcif().set_bit(),
+        });
+    }
+
+    /// Returns true if the whole channel transfer is complete.
+    pub fn transfer_is_complete(&mut self, channel: MdmaChannel) -> bool {
+        self.ch(channel).isr.read().ctcif().bit_is_set()
+    }
+
+    /// Enable a specific type of interrupt.
+    pub fn enable_interrupt(&mut self, channel: MdmaChannel, interrupt: MdmaInterrupt) {
+        self.ch(channel).cr.modify(|_, w| match interrupt {
+            MdmaInterrupt::TransferError => w.teie().set_bit(),
+            MdmaInterrupt::ChannelTransferComplete => w.ctcie().set_bit(),
+            MdmaInterrupt::BlockRepeatTransferComplete => w.brtie().set_bit(),
+            MdmaInterrupt::BlockTransferComplete => w.btie().set_bit(),
+            MdmaInterrupt::BufferTransferComplete => w.tcie().set_bit(),
+        });
+    }
+
+    /// Disable a specific type of interrupt.
+    pub fn disable_interrupt(&mut self, channel: MdmaChannel, interrupt: MdmaInterrupt) {
+        self.ch(channel).cr.modify(|_, w| match interrupt {
+            MdmaInterrupt::TransferError => w.teie().clear_bit(),
+            MdmaInterrupt::ChannelTransferComplete => w.ctcie().clear_bit(),
+            MdmaInterrupt::BlockRepeatTransferComplete => w.brtie().clear_bit(),
+            MdmaInterrupt::BlockTransferComplete => w.btie().clear_bit(),
+            MdmaInterrupt::BufferTransferComplete => w.tcie().clear_bit(),
+        });
+    }
+}
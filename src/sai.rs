@@ -3,28 +3,23 @@
 
 use core::ops::Deref;
 
+#[cfg(feature = "h7")]
+use crate::bdma::Bdma;
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "f4", feature = "l552")))]
-use crate::dma::{self, ChannelCfg, Dma, DmaChannel};
-#[cfg(feature = "g0")]
-use crate::pac::dma as dma_p;
-#[cfg(any(
-    feature = "f3",
-    feature = "l4",
-    feature = "l5",
-    feature = "g4",
-    feature = "h7",
-    feature = "wb"
-))]
-use crate::pac::dma1 as dma_p;
+#[cfg(not(feature = "f4"))]
+use crate::dma::{self, ChannelCfg, ChannelToken, Dma};
 #[cfg(feature = "g4")]
 use crate::pac::sai;
 #[cfg(not(any(feature = "g4", feature = "h7")))]
 use crate::pac::sai1 as sai;
 #[cfg(feature = "h7")]
 use crate::pac::sai4 as sai;
-use crate::{clocks::Clocks, pac::RCC, util::RccPeriph};
+use crate::{
+    clocks::Clocks,
+    pac::{self, RCC},
+    util::RccPeriph,
+};
 
 #[derive(Clone, Copy)]
 #[repr(u8)]
@@ -970,32 +965,42 @@ It can generate an interrupt if WCKCFGIE bit is set in SAI_xIM register");
     /// There is one DMA channel per audio subblock supporting basic DMA request/acknowledge
     /// protocol.
     /// Before configuring the SAI block, the SAI DMA channel must be disabled.
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
-    pub unsafe fn write_dma<D>(
+    #[cfg(not(feature = "f4"))]
+    pub unsafe fn write_dma(
         &mut self,
         buf: &[i32], // todo size?
         sai_channel: SaiChannel,
-        dma_channel: DmaChannel,
+        dma_channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma: &mut Dma<D>,
-    ) where
-        D: Deref<Target = dma_p::RegisterBlock>,
-    {
-        let (ptr, len) = (buf.as_ptr(), buf.len());
+    ) {
+        let dma_periph = dma_channel.periph();
+
+        let regs = match dma_periph {
+            dma::DmaPeriph::Dma1 => unsafe { &(*pac::DMA1::ptr()) },
+            #[cfg(not(feature = "wb"))]
+            dma::DmaPeriph::Dma2 => unsafe { &(*pac::DMA2::ptr()) },
+        };
+        let mut dma = Dma { regs };
 
-        // todo: DMA2 support.
+        let (ptr, len) = (buf.as_ptr(), buf.len());
 
-        // L44 RM, Table 41. "DMA1 requests for each channel"
+        // L44 RM, Tables 41 and 42. "DMA1 (and DMA2) requests for each channel"
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let dma_channel = dma_channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let dma_channel = match sai_channel {
-            SaiChannel::A => DmaInput::Sai1A.dma1_channel(),
-            SaiChannel::B => DmaInput::Sai1B.dma1_channel(),
-        };
+        let dma_channel = dma::check_fixed_channel(
+            dma_channel,
+            dma_periph,
+            match sai_channel {
+                SaiChannel::A => DmaInput::Sai1A.fixed_channel(dma_periph),
+                SaiChannel::B => DmaInput::Sai1B.fixed_channel(dma_periph),
+            },
+        );
 
         #[cfg(feature = "l4")]
         match sai_channel {
-            SaiChannel::A => dma.channel_select(DmaInput::Sai1A),
-            SaiChannel::B => dma.channel_select(DmaInput::Sai1B),
+            SaiChannel::A => dma::channel_select(dma_periph, DmaInput::Sai1A),
+            SaiChannel::B => dma::channel_select(dma_periph, DmaInput::Sai1B),
         };
 
         // To configure the audio subblock for DMA transfer, set DMAEN bit in the SAI_xCR1 register.
@@ -1059,34 +1064,47 @@ It can generate an interrupt if WCKCFGIE bit is set in SAI_xIM register");
     /// DMA interface to read/write from/to the SAI_xDR register (to access the internal FIFO).
     /// There is one DMA channel per audio subblock supporting basic DMA request/acknowledge
     /// protocol.
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
-    pub unsafe fn read_dma<D>(
+    ///
+    /// On H7, set `channel_cfg.double_buf` for gapless, double-buffered audio; see
+    /// `dma::set_inactive_buf()`.
+    #[cfg(not(feature = "f4"))]
+    pub unsafe fn read_dma(
         &mut self,
         buf: &mut [i32], // todo size?
         sai_channel: SaiChannel,
-        dma_channel: DmaChannel,
+        dma_channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma: &mut Dma<D>,
-    ) where
-        D: Deref<Target = dma_p::RegisterBlock>,
-    {
+    ) {
+        let dma_periph = dma_channel.periph();
+
+        let regs = match dma_periph {
+            dma::DmaPeriph::Dma1 => unsafe { &(*pac::DMA1::ptr()) },
+            #[cfg(not(feature = "wb"))]
+            dma::DmaPeriph::Dma2 => unsafe { &(*pac::DMA2::ptr()) },
+        };
+        let mut dma = Dma { regs };
+
         let (ptr, len) = (buf.as_mut_ptr(), buf.len());
 
         // See commends on `write_dma`.
 
-        // L44 RM, Table 41. "DMA1 requests for each channel
-        // todo: DMA2 support.
-
+        // L44 RM, Tables 41 and 42. "DMA1 (and DMA2) requests for each channel"
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let dma_channel = dma_channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let dma_channel = match sai_channel {
-            SaiChannel::A => DmaInput::Sai1A.dma1_channel(),
-            SaiChannel::B => DmaInput::Sai1B.dma1_channel(),
-        };
+        let dma_channel = dma::check_fixed_channel(
+            dma_channel,
+            dma_periph,
+            match sai_channel {
+                SaiChannel::A => DmaInput::Sai1A.fixed_channel(dma_periph),
+                SaiChannel::B => DmaInput::Sai1B.fixed_channel(dma_periph),
+            },
+        );
 
         #[cfg(feature = "l4")]
         match sai_channel {
-            SaiChannel::A => dma.channel_select(DmaInput::Sai1A),
-            SaiChannel::B => dma.channel_select(DmaInput::Sai1B),
+            SaiChannel::A => dma::channel_select(dma_periph, DmaInput::Sai1A),
+            SaiChannel::B => dma::channel_select(dma_periph, DmaInput::Sai1B),
         };
 
         match sai_channel {
@@ -1130,6 +1148,44 @@ It can generate an interrupt if WCKCFGIE bit is set in SAI_xIM register");
         // 4. Enable the SAI interface. (handled by `Sai::enable() in user code`.)
     }
 
+    /// Send data over SAI with DMA, taking ownership of the buffer and the SAI for the duration
+    /// of the transfer. Use the returned `Transfer` to check for completion, and to get them back.
+    /// See `write_dma` for details.
+    #[cfg(not(feature = "f4"))]
+    pub fn write_dma_owned(
+        mut self,
+        buf: &'static [i32],
+        sai_channel: SaiChannel,
+        dma_channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static [i32]> {
+        unsafe { self.write_dma(buf, sai_channel, &dma_channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, dma_channel).with_cleanup(Self::end_dma)
+    }
+
+    /// Read data from SAI with DMA, taking ownership of the buffer and the SAI for the duration
+    /// of the transfer. See `write_dma_owned`, and `read_dma`.
+    #[cfg(not(feature = "f4"))]
+    pub fn read_dma_owned(
+        mut self,
+        buf: &'static mut [i32],
+        sai_channel: SaiChannel,
+        dma_channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static mut [i32]> {
+        unsafe { self.read_dma(buf, sai_channel, &dma_channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, dma_channel).with_cleanup(Self::end_dma)
+    }
+
+    /// Disable DMA requests on both subblocks, once an owned transfer has ended.
+    #[cfg(not(feature = "f4"))]
+    fn end_dma(&mut self) {
+        self.regs.cha().cr1.modify(|_, w| w.dmaen().clear_bit());
+        self.regs.chb().cr1.modify(|_, w| w.dmaen().clear_bit());
+    }
+
     /// Enable a specific type of interrupt. See L4 RM, Table 220: "SAI interrupt sources".
     pub fn enable_interrupt(&mut self, interrupt_type: SaiInterrupt, channel: SaiChannel) {
         // Disable the UART to allow writing the `add` and `addm7` bits
@@ -1208,3 +1264,86 @@ It can generate an interrupt if WCKCFGIE bit is set in SAI_xIM register");
         }
     }
 }
+
+#[cfg(feature = "h7")]
+impl Sai<pac::SAI4> {
+    /// Send data over SAI4 with the BDMA. SAI4 is in the D3 domain, so its DMA requests are only
+    /// available on DMAMUX2; connect them to the BDMA channel with `dma::mux2()`, using
+    /// `DmaInput2::Sai4A` or `DmaInput2::Sai4B`. `buf` must be in SRAM4, since the BDMA can't
+    /// access other memory. See `write_dma` for details.
+    pub unsafe fn write_dma_bdma(
+        &mut self,
+        buf: &[i32],
+        sai_channel: SaiChannel,
+        dma_channel: dma::DmaChannel,
+        channel_cfg: ChannelCfg,
+        bdma: &mut Bdma,
+    ) {
+        let (ptr, len) = (buf.as_ptr(), buf.len());
+        let (periph_addr, datasize) = self.setup_dma(sai_channel);
+
+        bdma.cfg_channel(
+            dma_channel,
+            periph_addr,
+            ptr as u32,
+            len as u16,
+            dma::Direction::ReadFromMem,
+            datasize,
+            datasize,
+            channel_cfg,
+        );
+    }
+
+    /// Read data from SAI4 with the BDMA. See `write_dma_bdma`.
+    pub unsafe fn read_dma_bdma(
+        &mut self,
+        buf: &mut [i32],
+        sai_channel: SaiChannel,
+        dma_channel: dma::DmaChannel,
+        channel_cfg: ChannelCfg,
+        bdma: &mut Bdma,
+    ) {
+        let (ptr, len) = (buf.as_mut_ptr(), buf.len());
+        let (periph_addr, datasize) = self.setup_dma(sai_channel);
+
+        bdma.cfg_channel(
+            dma_channel,
+            periph_addr,
+            ptr as u32,
+            len as u16,
+            dma::Direction::ReadFromPeriph,
+            datasize,
+            datasize,
+            channel_cfg,
+        );
+    }
+
+    /// Enable DMA requests on a subblock, and return its data register address, and the DMA
+    /// data size matching its configuration.
+    fn setup_dma(&mut self, sai_channel: SaiChannel) -> (u32, dma::DataSize) {
+        match sai_channel {
+            SaiChannel::A => self.regs.cha().cr1.modify(|_, w| w.dmaen().set_bit()),
+            SaiChannel::B => self.regs.chb().cr1.modify(|_, w| w.dmaen().set_bit()),
+        }
+
+        let (periph_addr, cfg_datasize) = match sai_channel {
+            SaiChannel::A => (
+                &self.regs.cha().dr as *const _ as u32,
+                self.config_a.datasize,
+            ),
+            SaiChannel::B => (
+                &self.regs.chb().dr as *const _ as u32,
+                self.config_b.datasize,
+            ),
+        };
+
+        let datasize = match cfg_datasize {
+            DataSize::S8 => dma::DataSize::S8,
+            DataSize::S10 => dma::DataSize::S16,
+            DataSize::S16 => dma::DataSize::S16,
+            _ => dma::DataSize::S32,
+        };
+
+        (periph_addr, datasize)
+    }
+}
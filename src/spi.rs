@@ -2,13 +2,15 @@
 //! Provides APIs to configure, read, and write from
 //! SPI, with blocking, nonblocking, and DMA functionality.
 
-use core::{ops::Deref, ptr};
+use core::{cell::RefCell, ops::Deref, ptr};
 
 use cfg_if::cfg_if;
+use cortex_m::interrupt::{self, Mutex};
 #[cfg(feature = "embedded_hal")]
 use embedded_hal::spi::FullDuplex;
 
 use crate::{
+    gpio::Pin,
     pac::{self, RCC},
     util::RccPeriph,
     MAX_ITERS,
@@ -26,8 +28,11 @@ cfg_if! {
 
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "f4", feature = "l552")))]
-use crate::dma::{self, ChannelCfg, Dma, DmaChannel}; // todo temp
+use crate::dma::{self, ChannelCfg, ChannelToken};
+
+/// The largest number of frames that can be programmed into CR2.TSIZE for a single transfer.
+#[cfg(any(feature = "h5", feature = "h7"))]
+const MAX_TSIZE: usize = 0xFFFF;
 
 /// SPI error
 #[non_exhaustive]
@@ -376,14 +381,13 @@ where
                 // todo: You may not need this master line separate. TSing SS config issues.
                 // regs.cfg2.modify(|_, w| w.master().set_bit());
 
-                // todo: CR2.tsize should be > 0.
-
+                // Keep control of the associated GPIOs while the peripheral is disabled between
+                // transfers, so SCK and MOSI don't float.
+                regs.cfg2.modify(|_, w| w.afcntr().set_bit());
 
                 // 3. Write to the SPI_CR2 register to select length of the transfer, if it is not known TSIZE
                 // has to be programmed to zero.
-                regs.cr2.modify(|_, w| {
-                    w.tsize().bits(0) // todo?
-                });
+                // (TSIZE is set for each transfer, in `write`, `transfer`, and the DMA functions.)
 
                 // 4. Write to SPI_CRCPOLY and into TCRCINI, RCRCINI and CRC33_17 bits at
                 // SPI2S_CR1 register to configure the CRC polynomial and CRC calculation if needed.
@@ -486,6 +490,30 @@ where
         self.regs.cr1.modify(|_, w| w.spe().set_bit());
     }
 
+    /// Change the SPI mode (clock polarity and phase). Sets `CR1` register, `CPOL` and `CPHA` fields;
+    /// on H7, sets these fields in `CFG2`.
+    pub fn set_mode(&mut self, mode: SpiModeType) {
+        self.regs.cr1.modify(|_, w| w.spe().clear_bit());
+
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
+        self.regs.cr1.modify(|_, w| {
+            w.cpol().bit(mode.polarity as u8 != 0);
+            w.cpha().bit(mode.phase as u8 != 0);
+            w.spe().set_bit()
+        });
+
+        #[cfg(any(feature = "h5", feature = "h7"))]
+        self.regs.cfg2.modify(|_, w| {
+            w.cpol().bit(mode.polarity as u8 != 0);
+            w.cpha().bit(mode.phase as u8 != 0)
+        });
+
+        #[cfg(any(feature = "h5", feature = "h7"))]
+        self.regs.cr1.modify(|_, w| w.spe().set_bit());
+
+        self.cfg.mode = mode;
+    }
+
     /// L44 RM, section 40.4.9: "Procedure for disabling the SPI"
     /// When SPI is disabled, it is mandatory to follow the disable procedures described in this
     /// paragraph. It is important to do this before the system enters a low-power mode when the
@@ -495,6 +523,10 @@ where
         // The correct disable procedure is (except when receive only mode is used):
         cfg_if! {
             if #[cfg(feature = "h7")] {
+                // Blocking transfers leave the peripheral disabled once complete.
+                if self.regs.cr1.read().spe().bit_is_clear() {
+                    return;
+                }
                 // 1. Wait until TXC=1 and/or EOT=1 (no more data to transmit and last data frame sent).
                 // When CRC is used, it is sent automatically after the last data in the block is processed.
                 // TXC/EOT is set when CRC frame is completed in this case. When a transmission is
@@ -641,169 +673,192 @@ where
         Ok(())
     }
 
+    /// Check the status register for errors, on H7.
     #[cfg(any(feature = "h5", feature = "h7"))]
-    fn read_one(&mut self) -> Result<u8, SpiError> {
-        // NOTE(read_volatile) read only 1 word
-        unsafe { Ok(ptr::read_volatile(&self.regs.rxdr as *const _ as *const u8)) }
+    fn check_errors(&self) -> Result<(), SpiError> {
+        let sr = self.regs.sr.read();
+
+        if sr.ovr().bit_is_set() {
+            Err(SpiError::Overrun)
+        } else if sr.modf().bit_is_set() {
+            Err(SpiError::ModeFault)
+        } else if sr.crce().bit_is_set() {
+            Err(SpiError::Crc)
+        } else {
+            Ok(())
+        }
     }
 
+    /// Start a transfer of a known length, on H7. See H743 RM, section 50.4.12: Data transmission
+    /// and reception procedures. TSIZE and FTHLV can only be changed when the peripheral is
+    /// disabled.
     #[cfg(any(feature = "h5", feature = "h7"))]
-    fn send(&mut self, word: u8) -> Result<(), SpiError> {
-        // NOTE(write_volatile) see note above
-        unsafe {
-            #[allow(invalid_reference_casting)]
-            ptr::write_volatile(&self.regs.txdr as *const _ as *mut u8, word);
-        }
-        // write CSTART to start a transaction in
-        // master mode
-        self.regs.cr1.modify(|_, w| w.cstart().set_bit());
+    fn start_transfer(&mut self, len: u16, fifo_thresh: u8) {
+        self.regs.cr1.modify(|_, w| w.spe().clear_bit());
 
-        Ok(())
+        self.regs
+            .cfg1
+            .modify(|_, w| unsafe { w.fthlv().bits(fifo_thresh - 1) });
+        self.regs.cr2.modify(|_, w| unsafe { w.tsize().bits(len) });
+
+        self.regs.cr1.modify(|_, w| w.spe().set_bit());
+        // "In master mode, the transfer starts when CSTART is set" (As soon as there's data in
+        // the TxFIFO)
+        self.regs.cr1.modify(|_, w| w.cstart().set_bit());
     }
 
+    /// End a transfer, on H7: Wait for EOT, clear the EOT and TXTF flags, and disable the
+    /// peripheral. RM: "The SPI must be disabled between transfers when TSIZE > 0."
     #[cfg(any(feature = "h5", feature = "h7"))]
-    fn exchange_duplex(&mut self, word: u8) -> Result<u8, SpiError> {
-        // todo DRY
-        let sr = self.regs.sr.read();
+    fn end_transfer(&mut self) -> Result<(), SpiError> {
+        let mut i = 0;
+        while self.regs.sr.read().eot().bit_is_clear() {
+            i += 1;
+            if i >= MAX_ITERS {
+                return Err(SpiError::Hardware);
+            }
+        }
 
-        let crce = sr.crce().bit_is_set();
+        let result = self.check_errors();
 
-        if sr.ovr().bit_is_set() {
-            return Err(SpiError::Overrun);
-        } else if sr.modf().bit_is_set() {
-            return Err(SpiError::ModeFault);
-        } else if crce {
-            return Err(SpiError::Crc);
-        }
+        self.regs.ifcr.write(|w| {
+            w.eotc().set_bit();
+            w.txtfc().set_bit();
+            w.ovrc().set_bit();
+            w.modfc().set_bit()
+        });
 
-        #[allow(invalid_reference_casting)]
-        unsafe {
-            ptr::write_volatile(&self.regs.txdr as *const _ as *mut _, word);
-            Ok(ptr::read_volatile(&self.regs.rxdr as *const _ as *const u8))
-        }
-        //
-        // { // else if sr.txc().is_completed() {
-        //     txc, is_completed,
-        //     {
-        //         let sr = self.regs.sr.read(); // Read SR again on a subsequent PCLK cycle
-        //
-        //         if sr.txc().is_completed() && !sr.rxp().is_not_empty() {
-        //             // The Tx FIFO completed, but no words were
-        //             // available in the Rx FIFO. This is a duplex failure
-        //             nb::Error::Other(Error::DuplexFailed)
-        //         } else {
-        //             nb::Error::WouldBlock
-        //         }
-        //     }
-        // }
+        self.regs.cr1.modify(|_, w| w.spe().clear_bit());
 
-        // Ok(())
+        result
     }
 
-    /// Internal implementation for reading a word
-    ///
-    /// * Assumes the transaction has started (CSTART handled externally)
-    /// * Assumes at least one word has already been written to the Tx FIFO
+    /// Exchange one block of up to `MAX_TSIZE` bytes, on H7. Programs TSIZE, and uses FTHLV
+    /// to pack 4 bytes per FIFO access where possible. `rx` may be null, in which case received
+    /// data is discarded. `tx` and `rx` may point to the same buffer, since data is always read
+    /// back into a position that's already been written.
     #[cfg(any(feature = "h5", feature = "h7"))]
-    fn read_duplex(&mut self) -> Result<u8, SpiError> {
-        // NOTE(read_volatile) read only 1 word
-        // todo DRY
-        let sr = self.regs.sr.read();
+    unsafe fn exchange_block(
+        &mut self,
+        tx: *const u8,
+        rx: *mut u8,
+        len: usize,
+    ) -> Result<(), SpiError> {
+        // Frames per FIFO packet; TXP and RXP are set when there's room for, or data available for
+        // a full packet. We access the data registers as 32-bit words to move a full packet at once.
+        const PACKET: usize = 4;
+        // Don't let more data than this be in flight, to prevent RxFIFO overruns. The smallest
+        // FIFO (SPI4-6) holds 8 bytes.
+        const MAX_IN_FLIGHT: usize = 8;
 
-        let crce = sr.crce().bit_is_set();
+        let txdr = self.regs.txdr.as_ptr();
+        let rxdr = self.regs.rxdr.as_ptr() as *const u32;
 
-        if sr.ovr().bit_is_set() {
-            return Err(SpiError::Overrun);
-        } else if sr.modf().bit_is_set() {
-            return Err(SpiError::ModeFault);
-        } else if crce {
-            return Err(SpiError::Crc);
-        }
+        self.start_transfer(len as u16, PACKET as u8);
 
-        unsafe { Ok(ptr::read_volatile(&self.regs.rxdr as *const _ as *const u8)) }
-        // , { // else if sr.txc().is_completed()
-        //         txc, is_completed,
-        //         {
-        //             let sr = self.regs.sr.read(); // Read SR again on a subsequent PCLK cycle
-        //
-        //             if sr.txc().is_completed() && !sr.rxp().is_not_empty() {
-        //                 // The Tx FIFO completed, but no words were
-        //                 // available in the Rx FIFO. This is a duplex failure
-        //                 nb::Error::Other(Error::DuplexFailed)
-        //             } else {
-        //                 nb::Error::WouldBlock
-        //             }
-        //         }
-    }
+        let mut i_tx = 0;
+        let mut i_rx = 0;
+        let mut i = 0;
 
-    #[cfg(any(feature = "h5", feature = "h7"))]
-    pub fn write<'w>(&mut self, write_words: &'w [u8]) -> Result<(), SpiError> {
-        // Depth of FIFO to use. All current SPI implementations
-        // have a FIFO depth of at least 8 (see RM0433 Rev 7
-        // Table 409.) but pick 4 as a conservative value.
-        const FIFO_WORDS: usize = 4; // todo: 8?
-
-        // Fill the first half of the write FIFO
-        let len = write_words.len();
-        let mut write = write_words.iter();
-        for _ in 0..core::cmp::min(FIFO_WORDS, len) {
-            self.send(*write.next().unwrap())?;
-        }
+        while i_rx < len {
+            let sr = self.regs.sr.read();
+            let mut progress = false;
+
+            if i_tx < len && sr.txp().bit_is_set() && i_tx - i_rx + PACKET <= MAX_IN_FLIGHT {
+                if len - i_tx >= PACKET {
+                    let word = u32::from_le_bytes([
+                        *tx.add(i_tx),
+                        *tx.add(i_tx + 1),
+                        *tx.add(i_tx + 2),
+                        *tx.add(i_tx + 3),
+                    ]);
+                    ptr::write_volatile(txdr, word);
+                    i_tx += PACKET;
+                } else {
+                    ptr::write_volatile(txdr as *mut u8, *tx.add(i_tx));
+                    i_tx += 1;
+                }
+                progress = true;
+            }
 
-        // Continue filling write FIFO and emptying read FIFO
-        for word in write {
-            let _ = self.exchange_duplex(*word)?;
-        }
+            if len - i_rx >= PACKET {
+                if sr.rxp().bit_is_set() {
+                    let word = ptr::read_volatile(rxdr).to_le_bytes();
+                    if !rx.is_null() {
+                        for (j, byte) in word.iter().enumerate() {
+                            *rx.add(i_rx + j) = *byte;
+                        }
+                    }
+                    i_rx += PACKET;
+                    progress = true;
+                }
+            } else if sr.rxwne().bit_is_set() || sr.rxplvl().bits() != 0 {
+                // The remaining data is less than a full packet, so RXP won't be set for it.
+                let byte = ptr::read_volatile(rxdr as *const u8);
+                if !rx.is_null() {
+                    *rx.add(i_rx) = byte;
+                }
+                i_rx += 1;
+                progress = true;
+            }
 
-        // Dummy read from the read FIFO
-        for _ in 0..core::cmp::min(FIFO_WORDS, len) {
-            let _ = self.read_duplex()?;
+            if progress {
+                i = 0;
+            } else {
+                if let Err(e) = self.check_errors() {
+                    self.regs.ifcr.write(|w| {
+                        w.ovrc().set_bit();
+                        w.modfc().set_bit()
+                    });
+                    self.regs.cr1.modify(|_, w| w.spe().clear_bit());
+                    return Err(e);
+                }
+                i += 1;
+                if i >= MAX_ITERS {
+                    self.regs.cr1.modify(|_, w| w.spe().clear_bit());
+                    return Err(SpiError::Hardware);
+                }
+            }
         }
 
-        Ok(())
+        self.end_transfer()
     }
 
+    /// Write multiple bytes on the SPI line, blocking until complete.
+    /// See H743 RM, section 50.4.12: Data transmission and reception procedures.
     #[cfg(any(feature = "h5", feature = "h7"))]
-    pub fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<(), SpiError> {
-        // Depth of FIFO to use. All current SPI implementations
-        // have a FIFO depth of at least 8 (see RM0433 Rev 7
-        // Table 409.) but pick 4 as a conservative value.
-        const FIFO_WORDS: usize = 4; // todo: 8?
-
-        // Fill the first half of the write FIFO
-        let len = words.len();
-        for i in 0..core::cmp::min(FIFO_WORDS, len) {
-            self.send(words[i])?;
+    pub fn write(&mut self, words: &[u8]) -> Result<(), SpiError> {
+        for block in words.chunks(MAX_TSIZE) {
+            unsafe { self.exchange_block(block.as_ptr(), ptr::null_mut(), block.len())? };
         }
 
-        for i in FIFO_WORDS..len + FIFO_WORDS {
-            if i < len {
-                // Continue filling write FIFO and emptying read FIFO
-                let read_value = self.exchange_duplex(words[i])?;
+        Ok(())
+    }
 
-                words[i - FIFO_WORDS] = read_value;
-            } else {
-                // Finish emptying the read FIFO
-                words[i - FIFO_WORDS] = self.read_duplex()?;
-            }
+    /// Write bytes on the SPI line, replacing them with the bytes read. Blocks until complete.
+    /// See H743 RM, section 50.4.12: Data transmission and reception procedures.
+    #[cfg(any(feature = "h5", feature = "h7"))]
+    pub fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<(), SpiError> {
+        for block in words.chunks_mut(MAX_TSIZE) {
+            let ptr = block.as_mut_ptr();
+            unsafe { self.exchange_block(ptr, ptr, block.len())? };
         }
 
         Ok(())
     }
 
-    // todo: End H7xx HAL C+Ps
-
     /// Transmit data using DMA. See L44 RM, section 40.4.9: Communication using DMA.
-    /// Note that the `channel` argument is unused on F3 and L4, since it is hard-coded,
-    /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
+    /// On F3 and L4, the `channel` token must be for the channel hard-wired to this peripheral on
+    /// its DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise. `dma::claim_channel_for()` claims the right one.
     pub unsafe fn write_dma(
         &mut self,
         buf: &[u8],
-        channel: DmaChannel,
+        channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
     ) {
+        let dma_periph = channel.periph();
+
         // Static write and read buffers?
         let (ptr, len) = (buf.as_ptr(), buf.len());
 
@@ -825,12 +880,12 @@ where
         // (N/A)
 
         // 2. Enable DMA streams for Tx and Rx in DMA registers, if the streams are used.
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let channel = channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel = R::write_chan();
+        let channel = dma::check_fixed_channel(channel, dma_periph, R::write_chan(dma_periph));
         #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
-        #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::write_sel(dma_periph);
 
         #[cfg(any(feature = "h5", feature = "h7"))]
         let periph_addr = &self.regs.txdr as *const _ as u32;
@@ -881,20 +936,24 @@ where
         self.regs.cfg1.modify(|_, w| w.txdmaen().set_bit());
 
         // 4. Enable the SPI by setting the SPE bit.
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
         self.regs.cr1.modify(|_, w| w.spe().set_bit());
+        #[cfg(any(feature = "h5", feature = "h7"))]
+        self.start_dma(len);
     }
 
     /// Receive data using DMA. See L44 RM, section 40.4.9: Communication using DMA.
-    /// Note that the `channel` argument is unused on F3 and L4, since it is hard-coded,
-    /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
+    /// On F3 and L4, the `channel` token must be for the channel hard-wired to this peripheral on
+    /// its DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise. `dma::claim_channel_for()` claims the right one.
     pub unsafe fn read_dma(
         &mut self,
         buf: &mut [u8],
-        channel: DmaChannel,
+        channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
     ) {
+        let dma_periph = channel.periph();
+
         // todo: Accept u16 words too.
         let (ptr, len) = (buf.as_mut_ptr(), buf.len());
 
@@ -905,12 +964,12 @@ where
         #[cfg(any(feature = "h5", feature = "h7"))]
         self.regs.cfg1.modify(|_, w| w.rxdmaen().set_bit());
 
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let channel = channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel = R::read_chan();
-        #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
+        let channel = dma::check_fixed_channel(channel, dma_periph, R::read_chan(dma_periph));
         #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::read_sel(dma_periph);
 
         #[cfg(any(feature = "h5", feature = "h7"))]
         let periph_addr = &self.regs.rxdr as *const _ as u32;
@@ -954,22 +1013,26 @@ where
             }
         }
 
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
         self.regs.cr1.modify(|_, w| w.spe().set_bit());
+        #[cfg(any(feature = "h5", feature = "h7"))]
+        self.start_dma(len);
     }
 
     /// Transfer data from DMA; this is the basic reading API, using both write and read transfers:
-    /// It performs a write with register data, and reads to a buffer.
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
+    /// It performs a write with register data, and reads to a buffer. Both channels must be on
+    /// the same DMA controller.
     pub unsafe fn transfer_dma(
         &mut self,
         buf_write: &[u8],
         buf_read: &mut [u8],
-        channel_write: DmaChannel,
-        channel_read: DmaChannel,
+        channel_write: &ChannelToken,
+        channel_read: &ChannelToken,
         channel_cfg_write: ChannelCfg,
         channel_cfg_read: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
     ) {
+        let dma_periph = channel_write.periph();
+
         // todo: Accept u16 words too.
         let (ptr_write, len_write) = (buf_write.as_ptr(), buf_write.len());
         let (ptr_read, len_read) = (buf_read.as_mut_ptr(), buf_read.len());
@@ -980,22 +1043,22 @@ where
 
         #[cfg(any(feature = "h5", feature = "h7"))]
         let periph_addr_write = &self.regs.txdr as *const _ as u32;
-        #[cfg(any(feature = "h5", not(feature = "h7")))]
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
         let periph_addr_write = &self.regs.dr as *const _ as u32;
 
         #[cfg(any(feature = "h5", feature = "h7"))]
         let periph_addr_read = &self.regs.rxdr as *const _ as u32;
-        #[cfg(any(feature = "h5", not(feature = "h7")))]
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
         let periph_addr_read = &self.regs.dr as *const _ as u32;
 
         #[cfg(any(feature = "h5", feature = "h7"))]
         let num_data_write = len_write as u32;
-        #[cfg(any(feature = "h5", not(feature = "h7")))]
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
         let num_data_write = len_write as u16;
 
         #[cfg(any(feature = "h5", feature = "h7"))]
         let num_data_read = len_read as u32;
-        #[cfg(any(feature = "h5", not(feature = "h7")))]
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
         let num_data_read = len_read as u16;
 
         // Be careful - order of enabling Rx and Tx may matter, along with other things like when we
@@ -1005,19 +1068,20 @@ where
         #[cfg(any(feature = "h5", feature = "h7"))]
         self.regs.cfg1.modify(|_, w| w.rxdmaen().set_bit());
 
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let (channel_write, channel_read) = (channel_write.channel(), channel_read.channel());
+
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel_write = R::write_chan();
+        let channel_write =
+            dma::check_fixed_channel(channel_write, dma_periph, R::write_chan(dma_periph));
         #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
-        #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::write_sel(dma_periph);
 
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel_read = R::read_chan();
-        #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
+        let channel_read =
+            dma::check_fixed_channel(channel_read, dma_periph, R::read_chan(dma_periph));
         #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::read_sel(dma_periph);
         match dma_periph {
             dma::DmaPeriph::Dma1 => {
                 let mut regs = unsafe { &(*DMA1::ptr()) };
@@ -1079,33 +1143,125 @@ where
         #[cfg(any(feature = "h5", feature = "h7"))]
         self.regs.cfg1.modify(|_, w| w.txdmaen().set_bit());
 
+        #[cfg(not(any(feature = "h5", feature = "h7")))]
         self.regs.cr1.modify(|_, w| w.spe().set_bit());
+        #[cfg(any(feature = "h5", feature = "h7"))]
+        self.start_dma(len_write.max(len_read));
+    }
+
+    /// Program the transfer size, enable the peripheral, and start a DMA transfer, on H7.
+    /// TSIZE is left at 0 (unknown length) for transfers longer than it can hold; in that case,
+    /// the transfer runs until the peripheral is disabled in `stop_dma`.
+    #[cfg(any(feature = "h5", feature = "h7"))]
+    fn start_dma(&mut self, len: usize) {
+        let tsize = if len <= MAX_TSIZE { len as u16 } else { 0 };
+
+        // One frame per DMA request.
+        self.regs.cfg1.modify(|_, w| unsafe { w.fthlv().bits(0) });
+        self.regs
+            .cr2
+            .modify(|_, w| unsafe { w.tsize().bits(tsize) });
+
+        self.regs.cr1.modify(|_, w| w.spe().set_bit());
+        self.regs.cr1.modify(|_, w| w.cstart().set_bit());
+    }
+
+    /// Transmit data using DMA, taking ownership of the buffer and the SPI for the duration
+    /// of the transfer. Use the returned `Transfer` to check for completion, and to get them back;
+    /// this also performs the steps of `stop_dma`. See `write_dma` for details.
+    pub fn write_dma_owned(
+        mut self,
+        buf: &'static [u8],
+        channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static [u8]> {
+        unsafe { self.write_dma(buf, &channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, channel).with_cleanup(Self::end_dma)
+    }
+
+    /// Receive data using DMA, taking ownership of the buffer and the SPI for the duration
+    /// of the transfer. See `write_dma_owned`, and `read_dma`.
+    pub fn read_dma_owned(
+        mut self,
+        buf: &'static mut [u8],
+        channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static mut [u8]> {
+        unsafe { self.read_dma(buf, &channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, channel).with_cleanup(Self::end_dma)
+    }
+
+    /// Write and read data using DMA, taking ownership of both buffers and the SPI for the
+    /// duration of the transfer. The transfer is done when both channels are complete. See
+    /// `write_dma_owned`, and `transfer_dma`.
+    pub fn transfer_dma_owned(
+        mut self,
+        buf_write: &'static [u8],
+        buf_read: &'static mut [u8],
+        channel_write: ChannelToken,
+        channel_read: ChannelToken,
+        channel_cfg_write: ChannelCfg,
+        channel_cfg_read: ChannelCfg,
+    ) -> dma::Transfer<Self, (&'static [u8], &'static mut [u8]), (ChannelToken, ChannelToken)> {
+        unsafe {
+            self.transfer_dma(
+                buf_write,
+                buf_read,
+                &channel_write,
+                &channel_read,
+                channel_cfg_write.single_buf(),
+                channel_cfg_read.single_buf(),
+            )
+        };
+
+        dma::Transfer::new_pair(self, (buf_write, buf_read), channel_write, channel_read)
+            .with_cleanup(Self::end_dma)
     }
 
     /// Stop a DMA transfer. Stops the channel, and disables the `txdmaen` and `rxdmaen` bits.
     /// Run this after each transfer completes - you may wish to do this in an interrupt
     /// (eg DMA transfer complete) instead of blocking. `channel2` is an optional second channel
     /// to stop; eg if you have both a tx and rx channel.
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
-    pub fn stop_dma(
-        &mut self,
-        channel: DmaChannel,
-        channel2: Option<DmaChannel>,
-        dma_periph: dma::DmaPeriph,
-    ) {
+    pub fn stop_dma(&mut self, channel: &ChannelToken, channel2: Option<&ChannelToken>) {
         // where
         // D: Deref<Target = dma_p::RegisterBlock>,
         // {
         // (RM:) To close communication it is mandatory to follow these steps in order:
         // 1. Disable DMA streams for Tx and Rx in the DMA registers, if the streams are used.
 
-        dma::stop(dma_periph, channel);
+        dma::stop(channel.periph(), channel.channel());
         if let Some(ch2) = channel2 {
-            dma::stop(dma_periph, ch2);
+            dma::stop(ch2.periph(), ch2.channel());
         };
 
+        self.end_dma();
+    }
+
+    /// Steps 2 and 3 of closing DMA communication, after the DMA channels are stopped.
+    fn end_dma(&mut self) {
         // 2. Disable the SPI by following the SPI disable procedure:
         // self.disable();
+        // On H7, the peripheral must be disabled between transfers when TSIZE > 0; this also
+        // ends transfers of unknown length.
+        #[cfg(any(feature = "h5", feature = "h7"))]
+        {
+            let mut i = 0;
+            while self.regs.sr.read().eot().bit_is_clear()
+                && self.regs.cr2.read().tsize().bits() != 0
+            {
+                i += 1;
+                if i >= MAX_ITERS {
+                    break;
+                }
+            }
+            self.regs.ifcr.write(|w| {
+                w.eotc().set_bit();
+                w.txtfc().set_bit()
+            });
+            self.regs.cr1.modify(|_, w| w.spe().clear_bit());
+        }
         // 3. Disable DMA Tx and Rx buffers by clearing the TXDMAEN and RXDMAEN bits in the
         // SPI_CR2 register, if DMA Tx and/or DMA Rx are used.
 
@@ -1124,21 +1280,23 @@ where
 
     /// Convenience function that clears the interrupt, and stops the transfer. For use with the TC
     /// interrupt only.
-    #[cfg(not(any(feature = "f4", feature = "l552")))]
-    pub fn cleanup_dma(
-        &mut self,
-        dma_periph: dma::DmaPeriph,
-        channel_tx: DmaChannel,
-        channel_rx: Option<DmaChannel>,
-    ) {
+    pub fn cleanup_dma(&mut self, channel_tx: &ChannelToken, channel_rx: Option<&ChannelToken>) {
         // The hardware seems to automatically enable Tx too; and we use it when transmitting.
-        dma::clear_interrupt(dma_periph, channel_tx, dma::DmaInterrupt::TransferComplete);
+        dma::clear_interrupt(
+            channel_tx.periph(),
+            channel_tx.channel(),
+            dma::DmaInterrupt::TransferComplete,
+        );
 
         if let Some(ch_rx) = channel_rx {
-            dma::clear_interrupt(dma_periph, ch_rx, dma::DmaInterrupt::TransferComplete);
+            dma::clear_interrupt(
+                ch_rx.periph(),
+                ch_rx.channel(),
+                dma::DmaInterrupt::TransferComplete,
+            );
         }
 
-        self.stop_dma(channel_tx, channel_rx, dma_periph);
+        self.stop_dma(channel_tx, channel_rx);
     }
 
     #[cfg(not(any(feature = "h5", feature = "h7")))]
@@ -1230,3 +1388,124 @@ impl<R> embedded_hal::blocking::spi::write::Default<u8> for Spi<R> where
     R: Deref<Target = pac::spi1::RegisterBlock> + RccPeriph
 {
 }
+
+/// Allows several devices, each with its own chip select pin, SPI mode, and baud rate, to share a
+/// single SPI peripheral. Create devices with `new_device`. Each transaction runs in a critical
+/// section, and reconfigures the peripheral for that device before asserting its CS pin.
+///
+/// Example:
+/// ```rust
+/// let spi = Spi::new(dp.SPI1, Default::default(), BaudRate::Div32);
+/// let bus = SpiBus::new(spi);
+///
+/// // With the `embedded_hal` feature, use `embedded_hal::spi::MODE_0` and `MODE_3` instead.
+/// let mut flash = bus.new_device(Pin::new(Port::A, 4, PinMode::Output), spi::SpiMode::mode0(), BaudRate::Div4);
+/// let mut imu = bus.new_device(Pin::new(Port::B, 0, PinMode::Output), spi::SpiMode::mode3(), BaudRate::Div32);
+///
+/// flash.write(&[0x06])?;
+/// imu.transfer(&mut buf)?;
+/// ```
+pub struct SpiBus<R> {
+    spi: Mutex<RefCell<Spi<R>>>,
+}
+
+impl<R> SpiBus<R>
+where
+    R: Deref<Target = pac::spi1::RegisterBlock> + RccPeriph,
+{
+    /// Take ownership of an initialized SPI peripheral, for sharing between devices.
+    pub fn new(spi: Spi<R>) -> Self {
+        Self {
+            spi: Mutex::new(RefCell::new(spi)),
+        }
+    }
+
+    /// Create a handle for a device on this bus. The CS pin must be configured as an output; it's
+    /// driven high (inactive) here.
+    pub fn new_device(
+        &self,
+        mut cs: Pin,
+        mode: SpiModeType,
+        baud_rate: BaudRate,
+    ) -> SpiDevice<'_, R> {
+        cs.set_high();
+
+        SpiDevice {
+            bus: self,
+            cs,
+            mode,
+            baud_rate,
+        }
+    }
+}
+
+/// A single device on a shared SPI bus. Owns its chip select pin, and stores the SPI mode and baud
+/// rate used for its transactions.
+pub struct SpiDevice<'a, R> {
+    bus: &'a SpiBus<R>,
+    pub cs: Pin,
+    pub mode: SpiModeType,
+    pub baud_rate: BaudRate,
+}
+
+impl<'a, R> SpiDevice<'a, R>
+where
+    R: Deref<Target = pac::spi1::RegisterBlock> + RccPeriph,
+{
+    /// Run a closure with exclusive access to the SPI peripheral, configured for this device, with
+    /// CS asserted (low). CS is de-asserted when the closure returns. Runs in a critical section.
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut Spi<R>) -> T) -> T {
+        interrupt::free(|cs| {
+            let mut spi = self.bus.spi.borrow(cs).borrow_mut();
+
+            spi.reclock(self.baud_rate);
+            spi.set_mode(self.mode);
+
+            self.cs.set_low();
+            let result = f(&mut spi);
+            self.cs.set_high();
+
+            result
+        })
+    }
+
+    /// Write multiple bytes to the device, blocking until complete.
+    pub fn write(&mut self, words: &[u8]) -> Result<(), SpiError> {
+        self.transaction(|spi| spi.write(words))
+    }
+
+    /// Write bytes to the device, replacing them with bytes read, blocking until complete.
+    pub fn transfer(&mut self, words: &mut [u8]) -> Result<(), SpiError> {
+        self.transaction(|spi| spi.transfer(words))
+    }
+
+    /// Free the CS pin.
+    pub fn free(self) -> Pin {
+        self.cs
+    }
+}
+
+#[cfg(feature = "embedded_hal")]
+impl<'a, R> embedded_hal::blocking::spi::Write<u8> for SpiDevice<'a, R>
+where
+    R: Deref<Target = pac::spi1::RegisterBlock> + RccPeriph,
+{
+    type Error = SpiError;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), SpiError> {
+        SpiDevice::write(self, words)
+    }
+}
+
+#[cfg(feature = "embedded_hal")]
+impl<'a, R> embedded_hal::blocking::spi::Transfer<u8> for SpiDevice<'a, R>
+where
+    R: Deref<Target = pac::spi1::RegisterBlock> + RccPeriph,
+{
+    type Error = SpiError;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], SpiError> {
+        SpiDevice::transfer(self, words)?;
+        Ok(words)
+    }
+}
@@ -1,21 +1,32 @@
 //! Provides support for timers. Includes initialization, interrupts,
-//! and PWM features.
+//! PWM, and quadrature encoder features.
 //!
-//! Low-power timers (LPTIM) are not yet supported.
+//! Low-power timers (LPTIM) are supported in the `lptimer` module, and high-resolution timers
+//! (HRTIM) in the `hrtim` module.
 
 // todo: WB and WL should support pwm features
 
 #[cfg(feature = "monotonic")]
 use core;
+#[cfg(feature = "monotonic_v2")]
+use core::marker::PhantomData;
+#[cfg(any(feature = "monotonic", feature = "monotonic_v2"))]
+use core::sync::atomic::compiler_fence;
 use core::{
     ops::Deref,
     sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
 };
 
 use cfg_if::cfg_if;
 use paste::paste;
 #[cfg(feature = "monotonic")]
 use rtic_monotonic::Monotonic;
+#[cfg(feature = "monotonic_v2")]
+use rtic_time::{
+    monotonic::TimerQueueBasedMonotonic,
+    timer_queue::{TimerQueue, TimerQueueBackend},
+};
 
 cfg_if! {
     if #[cfg(feature = "embedded_hal")] {
@@ -32,16 +43,14 @@ use num_traits::float::FloatCore; // To round floats.
 
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "f4", feature = "l552")))]
-use crate::dma::{self, ChannelCfg, DmaChannel};
+use crate::dma::{self, ChannelCfg, ChannelToken};
 #[cfg(feature = "g0")]
 use crate::pac::DMA as DMA1;
 #[cfg(not(feature = "g0"))]
 use crate::pac::DMA1;
-// todo: LPTIM (low-power timers) and HRTIM (high-resolution timers). And Advanced control functionality
 use crate::{
     clocks::Clocks,
-    instant::Instant,
+    instant::{Instant, TickRate},
     pac::{self, RCC},
     util::{rcc_en_reset, RccPeriph},
 };
@@ -49,8 +58,6 @@ use crate::{
 // This `TICK_OVERFLOW_COUNT` must be incremented in firmware in the timer's update interrupt.
 pub static TICK_OVERFLOW_COUNT: AtomicU32 = AtomicU32::new(0);
 
-// todo: Low power timer enabling etc. eg on L4, RCC_APB1ENR1.LPTIM1EN
-
 #[derive(Clone, Copy, Debug)]
 /// Used for when attempting to set a timer period that is out of range.
 pub struct ValueError {}
@@ -150,6 +157,196 @@ pub enum MasterModeSelection {
     Compare4 = 0b111,
 }
 
+impl InputTrigger {
+    /// The trigger selection for internal trigger `n` (ITRn).
+    fn internal(n: u8) -> Self {
+        match n {
+            0 => Self::Internal0,
+            1 => Self::Internal1,
+            2 => Self::Internal2,
+            3 => Self::Internal3,
+            4 => Self::Internal4,
+            5 => Self::Internal5,
+            6 => Self::Internal6,
+            7 => Self::Internal7,
+            8 => Self::Internal8,
+            9 => Self::Internal9,
+            10 => Self::Internal10,
+            11 => Self::Internal11,
+            12 => Self::Internal12,
+            13 => Self::Internal13,
+            _ => panic!("Invalid internal trigger."),
+        }
+    }
+}
+
+/// Identifies a timer, for looking up the internal trigger connections between timers.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TimerNum {
+    Tim1,
+    Tim2,
+    Tim3,
+    Tim4,
+    Tim5,
+    Tim8,
+    Tim15,
+    Tim16,
+    Tim17,
+    Tim20,
+}
+
+/// Find the internal trigger (ITRx) a slave timer uses to receive a master timer's trigger output
+/// (TRGO). Returns `None` if the timers aren't connected. See the RM's "TIMx internal trigger
+/// connection" table; eg L4 RM, section 31.4.3, or G4 RM, section 28.3.29.
+pub fn internal_trigger(master: TimerNum, slave: TimerNum) -> Option<InputTrigger> {
+    use TimerNum::*;
+
+    if master == slave {
+        return None;
+    }
+
+    cfg_if! {
+        if #[cfg(any(feature = "g4", feature = "l5", feature = "wb", feature = "wl", feature = "h5"))] {
+            // On these families, each ITRx connects to the same timer, regardless of the slave.
+            #[cfg(feature = "g4")]
+            let itrs = [Some(Tim1), Some(Tim2), Some(Tim3), Some(Tim4), Some(Tim5), Some(Tim8),
+                Some(Tim15), Some(Tim16), Some(Tim17), Some(Tim20)];
+            #[cfg(feature = "l5")]
+            let itrs = [Some(Tim1), Some(Tim2), Some(Tim3), Some(Tim4), Some(Tim5), Some(Tim8),
+                Some(Tim15), Some(Tim16), Some(Tim17)];
+            // todo: Confirm ITR6 and up on H5.
+            #[cfg(feature = "h5")]
+            let itrs = [Some(Tim1), Some(Tim2), Some(Tim3), Some(Tim4), Some(Tim5), Some(Tim8)];
+            #[cfg(any(feature = "wb", feature = "wl"))]
+            let itrs = [Some(Tim1), Some(Tim2)];
+        } else if #[cfg(feature = "g0")] {
+            // todo: ITR3 on G0.
+            let itrs = match slave {
+                Tim1 => [None, Some(Tim2), Some(Tim3), None],
+                Tim2 => [Some(Tim1), None, Some(Tim3), None],
+                Tim3 => [Some(Tim1), Some(Tim2), None, None],
+                _ => return None,
+            };
+        } else {
+            let itrs = match slave {
+                #[cfg(feature = "f4")]
+                Tim1 => [Some(Tim5), Some(Tim2), Some(Tim3), Some(Tim4)],
+                #[cfg(not(feature = "f4"))]
+                Tim1 => [Some(Tim15), Some(Tim2), Some(Tim3), Some(Tim4)],
+                Tim2 => [Some(Tim1), Some(Tim8), Some(Tim3), Some(Tim4)],
+                #[cfg(feature = "f4")]
+                Tim3 => [Some(Tim1), Some(Tim2), Some(Tim5), Some(Tim4)],
+                #[cfg(not(feature = "f4"))]
+                Tim3 => [Some(Tim1), Some(Tim2), Some(Tim15), Some(Tim4)],
+                Tim4 => [Some(Tim1), Some(Tim2), Some(Tim3), Some(Tim8)],
+                #[cfg(feature = "h7")]
+                Tim5 => [Some(Tim1), Some(Tim8), Some(Tim3), Some(Tim4)],
+                #[cfg(not(feature = "h7"))]
+                Tim5 => [Some(Tim2), Some(Tim3), Some(Tim4), Some(Tim8)],
+                // todo: ITR3 on F3 (TIM3?)
+                #[cfg(feature = "f3")]
+                Tim8 => [Some(Tim1), Some(Tim2), Some(Tim4), None],
+                #[cfg(not(feature = "f3"))]
+                Tim8 => [Some(Tim1), Some(Tim2), Some(Tim4), Some(Tim5)],
+                // todo: TIM15 and TIM20.
+                _ => return None,
+            };
+        }
+    }
+
+    itrs.iter()
+        .position(|t| *t == Some(master))
+        .map(|i| InputTrigger::internal(i as u8))
+}
+
+/// Timers that can be linked to each other, using one's trigger output (TRGO) as the other's
+/// trigger input (TRGI). See `link_timers`, `chain_counters`, and `sync_start`.
+pub trait TimerSync {
+    /// Used to look up the internal trigger connections between timers.
+    const NUM: TimerNum;
+
+    /// Select the information sent to slave timers as the trigger output (TRGO). Sets `TIMx_CR2`
+    /// register, `MMS` field.
+    fn set_master_mode(&mut self, mode: MasterModeSelection);
+
+    /// Delay the trigger input, for synchronization between this timer and its slaves. Sets
+    /// `TIMx_SMCR` register, `MSM` field.
+    fn set_master_slave_mode(&mut self, enabled: bool);
+
+    /// Select the trigger input, and the slave mode. Sets `TIMx_SMCR` register, `TS` and `SMS` fields.
+    fn set_slave_mode(&mut self, trigger: InputTrigger, mode: InputSlaveMode);
+
+    /// Read the raw counter value.
+    fn counter(&self) -> u32;
+
+    /// Read the raw auto-reload value.
+    fn auto_reload(&self) -> u32;
+}
+
+/// Link two timers: The master's trigger output (TRGO), selected by `master_mode`, drives the
+/// slave's slave mode controller. Returns an error if the timers aren't connected internally. For
+/// example, to gate the slave with the master's OC1REF: `MasterModeSelection::Compare1`, and
+/// `InputSlaveMode::Gated`.
+pub fn link_timers<M: TimerSync, S: TimerSync>(
+    master: &mut M,
+    slave: &mut S,
+    master_mode: MasterModeSelection,
+    slave_mode: InputSlaveMode,
+) -> Result<(), ValueError> {
+    let trigger = internal_trigger(M::NUM, S::NUM).ok_or(ValueError {})?;
+
+    master.set_master_mode(master_mode);
+    slave.set_slave_mode(trigger, slave_mode);
+
+    Ok(())
+}
+
+/// Chain two timers into a single counter, eg a 48-bit counter from a 32-bit and a 16-bit timer:
+/// The slave counts the master's update events. Set the master's auto-reload value to its
+/// maximum, and the slave's prescaler to 0, for a contiguous count. Read it with `read_chained_count`.
+pub fn chain_counters<M: TimerSync, S: TimerSync>(
+    master: &mut M,
+    slave: &mut S,
+) -> Result<(), ValueError> {
+    link_timers(
+        master,
+        slave,
+        MasterModeSelection::Update,
+        InputSlaveMode::ExternalClock1,
+    )
+}
+
+/// Read the combined count of timers linked with `chain_counters`.
+pub fn read_chained_count<M: TimerSync, S: TimerSync>(master: &M, slave: &S) -> u64 {
+    let range = master.auto_reload() as u64 + 1;
+
+    // Re-read if the master wrapped between reading the two counters.
+    loop {
+        let high = slave.counter();
+        let low = master.counter();
+        if slave.counter() == high {
+            return high as u64 * range + low as u64;
+        }
+    }
+}
+
+/// Configure a slave timer to start when its master is enabled. Run once for each slave, then
+/// enable the master. The master's trigger input is delayed so they start in phase.
+pub fn sync_start<M: TimerSync, S: TimerSync>(
+    master: &mut M,
+    slave: &mut S,
+) -> Result<(), ValueError> {
+    link_timers(
+        master,
+        slave,
+        MasterModeSelection::Enable,
+        InputSlaveMode::Trigger,
+    )?;
+    master.set_master_slave_mode(true);
+
+    Ok(())
+}
+
 /// Timer interrupt
 pub enum TimerInterrupt {
     /// Update interrupt can be used for a timeout. DIER UIE to set, ... to clear
@@ -368,6 +565,337 @@ impl Default for TimerConfig {
     }
 }
 
+/// Lock level for the break and dead-time configuration, as protection against software errors.
+/// Once set, the lock level can't be changed until the MCU is reset. Sets `TIMx_BDTR` register,
+/// `LOCK` field.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum LockLevel {
+    /// No bits are write protected.
+    Off = 0b00,
+    /// Dead-time, OSSI, OSSR, break and break 2 settings, and automatic output enable are
+    /// write protected.
+    Level1 = 0b01,
+    /// Level 1, plus output polarities and idle states.
+    Level2 = 0b10,
+    /// Level 2, plus output compare modes and preload.
+    Level3 = 0b11,
+}
+
+/// Break input active level. Sets `TIMx_BDTR` register, `BKP` and `BK2P` fields.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum BreakPolarity {
+    ActiveLow = 0,
+    ActiveHigh = 1,
+}
+
+/// Configuration for a break input. When a break occurs, the main output enable (MOE) is cleared,
+/// and outputs go to their idle states.
+#[derive(Clone, Copy)]
+pub struct BreakInput {
+    pub polarity: BreakPolarity,
+    /// Digital filter, 0 - 15; 0 disables filtering. See the RM's `TIMx_BDTR` register, `BKF` field.
+    /// Not available on F4.
+    pub filter: u8,
+}
+
+/// Break and dead-time configuration for advanced-control timers. Sets the `TIMx_BDTR` register.
+#[derive(Clone)]
+pub struct BreakDeadtimeCfg {
+    /// Dead time between a channel's output and its complementary output, in seconds. It's
+    /// quantized in ticks of the timer's clock (after `CKD` division); long dead times have a
+    /// coarser resolution.
+    pub dead_time: f32,
+    pub lock: LockLevel,
+    /// Off-state selection for idle mode: When outputs are disabled by MOE, drive them to their
+    /// idle level (true), or disable them (false). Sets the `OSSI` field.
+    pub off_state_idle: bool,
+    /// Off-state selection for run mode: When MOE is set, and a channel's output is disabled, drive
+    /// it to its inactive level (true), or disable it (false). Sets the `OSSR` field.
+    pub off_state_run: bool,
+    /// Set MOE automatically at the next update event, eg after a break clears. Sets the `AOE` field.
+    pub automatic_output: bool,
+    /// Break input. `None` disables it.
+    pub break1: Option<BreakInput>,
+    /// Break input 2. `None` disables it.
+    #[cfg(not(feature = "f4"))]
+    pub break2: Option<BreakInput>,
+}
+
+impl Default for BreakDeadtimeCfg {
+    fn default() -> Self {
+        Self {
+            dead_time: 0.,
+            lock: LockLevel::Off,
+            off_state_idle: false,
+            off_state_run: false,
+            automatic_output: false,
+            break1: None,
+            #[cfg(not(feature = "f4"))]
+            break2: None,
+        }
+    }
+}
+
+/// Quadrature encoder counting mode. Sets `TIMx_SMCR` register, `SMS` field. See G4 RM, section
+/// 28.3.24: Encoder interface mode.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum EncoderMode {
+    /// Count on TI1FP1 edges only, depending on the TI2FP2 level. (x2 resolution)
+    Ti1 = 0b001,
+    /// Count on TI2FP2 edges only, depending on the TI1FP1 level. (x2 resolution)
+    Ti2 = 0b010,
+    /// Count on both TI1FP1 and TI2FP2 edges. (x4 resolution)
+    Both = 0b011,
+}
+
+/// Configuration for quadrature encoder mode, using channels 1 and 2 as the A and B inputs.
+#[derive(Clone)]
+pub struct EncoderCfg {
+    pub mode: EncoderMode,
+    /// Digital filter for the CH1 input, 0 - 15; 0 disables filtering. Sets `TIMx_CCMR1`
+    /// register, `IC1F` field.
+    pub filter_a: u8,
+    /// Digital filter for the CH2 input, 0 - 15. Sets `TIMx_CCMR1` register, `IC2F` field.
+    pub filter_b: u8,
+    /// `ActiveLow` inverts the CH1 input, which reverses the count direction. Sets `TIMx_CCER`
+    /// register, `CC1P` field.
+    pub polarity_a: Polarity,
+    /// `ActiveLow` inverts the CH2 input. Sets `TIMx_CCER` register, `CC2P` field.
+    pub polarity_b: Polarity,
+}
+
+impl Default for EncoderCfg {
+    fn default() -> Self {
+        Self {
+            mode: EncoderMode::Both,
+            filter_a: 0,
+            filter_b: 0,
+            polarity_a: Polarity::ActiveHigh,
+            polarity_b: Polarity::ActiveHigh,
+        }
+    }
+}
+
+/// Configuration for PWM input mode. The signal is connected to CH1. IC1 captures the period,
+/// and IC2 captures the active (eg high) time. See G4 RM, section 28.3.8: PWM input mode.
+#[derive(Clone)]
+pub struct PwmInputCfg {
+    /// Digital filter for the CH1 input, 0 - 15; 0 disables filtering. Sets `TIMx_CCMR1`
+    /// register, `IC1F` and `IC2F` fields.
+    pub filter: u8,
+    /// Counter prescaler. The counter runs at the timer clock / (`psc` + 1); this sets the
+    /// longest period that can be measured, and the resolution. Sets the `TIMx_PSC` register.
+    pub psc: u16,
+    /// `ActiveHigh` measures the high time of the signal as its duty cycle, starting each period
+    /// on a rising edge. `ActiveLow` measures the low time, starting on a falling edge.
+    pub polarity: Polarity,
+}
+
+impl Default for PwmInputCfg {
+    fn default() -> Self {
+        Self {
+            filter: 0,
+            psc: 0,
+            polarity: Polarity::ActiveHigh,
+        }
+    }
+}
+
+/// What starts a pulse in one-pulse mode.
+#[derive(Clone, Copy)]
+pub enum PulseTrigger {
+    /// Start a pulse with `fire_pulse`.
+    Software,
+    /// An edge on the CH1 input. (TI1FP1)
+    Ti1,
+    /// An edge on the CH2 input. (TI2FP2)
+    Ti2,
+}
+
+/// Configuration for one-pulse mode. See G4 RM, section 28.3.19: One-pulse mode.
+#[derive(Clone)]
+pub struct OnePulseCfg {
+    /// The output channel. If using an input trigger, this can't be the trigger's channel.
+    pub channel: TimChannel,
+    pub trigger: PulseTrigger,
+    /// `ActiveHigh` triggers on a rising edge; `ActiveLow` on a falling one.
+    pub trigger_polarity: Polarity,
+    /// Digital filter for the trigger input, 0 - 15; 0 disables filtering.
+    pub trigger_filter: u8,
+    /// `ActiveHigh` for a high pulse, from a low idle level.
+    pub polarity: Polarity,
+}
+
+impl Default for OnePulseCfg {
+    fn default() -> Self {
+        Self {
+            channel: TimChannel::C1,
+            trigger: PulseTrigger::Software,
+            trigger_polarity: Polarity::ActiveHigh,
+            trigger_filter: 0,
+            polarity: Polarity::ActiveHigh,
+        }
+    }
+}
+
+/// A digital protocol that encodes each bit as a PWM period's duty cycle. Sent by using DMA
+/// bursts to write a CCR register on each update event. See `Timer::set_pwm_protocol`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PwmProtocol {
+    /// WS2812 (Neopixel) RGB LEDs: 800kbps, with 0.4us and 0.8us high times.
+    Ws2812,
+    /// SK6812 RGB and RGBW LEDs: 800kbps, with 0.3us and 0.6us high times.
+    Sk6812,
+    /// DShot ESC protocol, at 150kbps.
+    Dshot150,
+    /// DShot ESC protocol, at 300kbps.
+    Dshot300,
+    /// DShot ESC protocol, at 600kbps.
+    Dshot600,
+}
+
+impl PwmProtocol {
+    /// Bit rate, in Hz.
+    pub fn bit_freq(&self) -> u32 {
+        match self {
+            Self::Ws2812 | Self::Sk6812 => 800_000,
+            Self::Dshot150 => 150_000,
+            Self::Dshot300 => 300_000,
+            Self::Dshot600 => 600_000,
+        }
+    }
+
+    /// High time for 0 and 1 bits, as a portion of the bit period, in thousandths.
+    fn duty_thousandths(&self) -> (u32, u32) {
+        match self {
+            // 0.4us and 0.8us, of 1.25us.
+            Self::Ws2812 => (320, 640),
+            // 0.3us and 0.6us, of 1.25us.
+            Self::Sk6812 => (240, 480),
+            _ => (375, 750),
+        }
+    }
+}
+
+/// Number of bit periods to hold the line low after LED data, to latch it. The WS2812 and
+/// SK6812 require > 50us and > 80us respectively. (80us / 1.25us = 64)
+const LED_RESET_BITS: usize = 64;
+
+/// Number of bit periods to hold the line low after a DShot frame.
+const DSHOT_END_BITS: usize = 2;
+
+/// Converts LED pixels and DShot frames to CCR values, for use as a `write_dma_burst` buffer.
+/// Created by `Timer::set_pwm_protocol`.
+#[derive(Clone, Copy)]
+pub struct PwmEncoder {
+    pub protocol: PwmProtocol,
+    /// CCR value for a 0 bit.
+    pub zero: u16,
+    /// CCR value for a 1 bit.
+    pub one: u16,
+    /// The `base_address` argument for `write_dma_burst`: The channel's CCR register.
+    pub base_address: u8,
+}
+
+impl PwmEncoder {
+    /// Write one CCR value for each bit of `word`, MSB first, starting at `i`.
+    fn write_bits(&self, word: u32, num_bits: usize, buf: &mut [u16], i: usize) -> usize {
+        for bit in 0..num_bits {
+            buf[i + bit] = if word & (1 << (num_bits - 1 - bit)) != 0 {
+                self.one
+            } else {
+                self.zero
+            };
+        }
+        i + num_bits
+    }
+
+    /// Encode RGB pixels for WS2812 or SK6812 LEDs, as GRB, followed by the latch period.
+    /// Returns the number of values written; `buf` must be at least 24 per pixel, + 64 long.
+    pub fn encode_rgb(&self, pixels: &[[u8; 3]], buf: &mut [u16]) -> usize {
+        assert!(buf.len() >= pixels.len() * 24 + LED_RESET_BITS);
+
+        let mut i = 0;
+        for [r, g, b] in pixels {
+            let grb = (*g as u32) << 16 | (*r as u32) << 8 | *b as u32;
+            i = self.write_bits(grb, 24, buf, i);
+        }
+
+        self.write_reset(buf, i)
+    }
+
+    /// Encode RGBW pixels for SK6812 RGBW LEDs, as GRBW, followed by the latch period.
+    /// Returns the number of values written; `buf` must be at least 32 per pixel, + 64 long.
+    pub fn encode_rgbw(&self, pixels: &[[u8; 4]], buf: &mut [u16]) -> usize {
+        assert!(buf.len() >= pixels.len() * 32 + LED_RESET_BITS);
+
+        let mut i = 0;
+        for [r, g, b, w] in pixels {
+            let grbw = (*g as u32) << 24 | (*r as u32) << 16 | (*b as u32) << 8 | *w as u32;
+            i = self.write_bits(grbw, 32, buf, i);
+        }
+
+        self.write_reset(buf, i)
+    }
+
+    /// Hold the line low, to latch LED data.
+    fn write_reset(&self, buf: &mut [u16], i: usize) -> usize {
+        buf[i..i + LED_RESET_BITS].fill(0);
+        i + LED_RESET_BITS
+    }
+
+    /// Encode a DShot frame. `value` is 0 for disarmed, 1 - 47 for commands, or 48 - 2047 for
+    /// throttle. Returns the number of values written; `buf` must be at least 18 long.
+    pub fn encode_dshot(&self, value: u16, telemetry: bool, buf: &mut [u16]) -> usize {
+        assert!(buf.len() >= 16 + DSHOT_END_BITS);
+
+        let i = self.write_bits(dshot_frame(value, telemetry) as u32, 16, buf, 0);
+        buf[i..i + DSHOT_END_BITS].fill(0);
+        i + DSHOT_END_BITS
+    }
+}
+
+/// Create a DShot frame: An 11-bit value, the telemetry request bit, and a 4-bit checksum.
+pub fn dshot_frame(value: u16, telemetry: bool) -> u16 {
+    assert!(value < 2_048);
+
+    let packet = value << 1 | telemetry as u16;
+    let crc = (packet ^ (packet >> 4) ^ (packet >> 8)) & 0xf;
+    packet << 4 | crc
+}
+
+#[cfg(feature = "g4")]
+/// Directions an encoder index event resets the counter in. Sets `TIMx_ECR` register, `IDIR` field.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+pub enum IndexDir {
+    Both = 0b00,
+    Up = 0b01,
+    Down = 0b10,
+}
+
+#[cfg(feature = "g4")]
+/// Encoder index configuration. The index signal is connected to the ETR input. On an index event,
+/// the counter is reset to 0 (counting up), or ARR (counting down). See G4 RM, section 28.3.26:
+/// Encoder index.
+#[derive(Clone, Copy)]
+pub struct EncoderIndex {
+    pub direction: IndexDir,
+    /// Only reset on the first index event after enabling; later index events are ignored. Sets
+    /// the `FIDX` field.
+    pub first_only: bool,
+    /// Index position: The A/B input states (`0b00` - `0b11` for B:A) at which the reset occurs.
+    /// Sets the `IPOS` field.
+    pub position: u8,
+    /// ETR input filter, 0 - 15. Sets `TIMx_SMCR` register, `ETF` field.
+    pub filter: u8,
+    /// Invert the ETR input. Sets `TIMx_SMCR` register, `ETP` field.
+    pub inverted: bool,
+}
+
 /// Represents a General Purpose or Advanced Control timer.
 pub struct Timer<TIM> {
     /// Register block for the specific timer.
@@ -377,16 +905,9 @@ pub struct Timer<TIM> {
     pub cfg: TimerConfig,
     /// Associated timer clock speed in Hz.
     clock_speed: u32,
-    // #[cfg(feature = "monotonic")]
     /// Used to indicate the timer has expired, and running time counts (eg the `time_elapsed()` method) properly
     /// increment.
     pub wrap_count: u32,
-    // #[cfg(feature = "monotonic")]
-    /// Updated in the constructor and `set_freq` fns. Used for mapping timer ticks to time (eg in
-    /// seconds, us etc)
-    // pub us_per_tick: f32,
-    ns_per_tick: f32,
-    period: f32, // In seconds. Used for overflow tracking. Updated when `ns_per_tick` is.
 }
 
 macro_rules! make_timer {
@@ -425,10 +946,6 @@ macro_rules! make_timer {
                         regs,
                         // #[cfg(feature = "monotonic")]
                         wrap_count: 0,
-                        // #[cfg(feature = "monotonic")]
-                        // us_per_tick: 0., // set below
-                        ns_per_tick: 0., // set below
-                        period: 0., // set below.
                     };
 
                     result.set_freq(freq).ok();
@@ -545,16 +1062,6 @@ macro_rules! make_timer {
                 self.regs.arr.write(|w| unsafe { w.bits(arr.into()) });
                 self.regs.psc.write(|w| unsafe { w.bits(psc.into()) });
 
-                // (PSC+1)*(ARR+1) = TIMclk/Updatefrequency = TIMclk * period
-                // period = (PSC+1)*(ARR+1) / TIMclk
-                // Calculate this based on our actual ARR and PSC values; don't use
-                // the requested frequency or period.
-                let arr_f32 = arr as f32;
-                let period_secs = (psc as f32 + 1.) * ( arr_f32 + 1.) / self.clock_speed as f32;
-                // self.us_per_tick = period_secs  / (arr_f32) * 1_000_000.;
-                self.ns_per_tick = period_secs  / (arr_f32) * 1_000_000_000.;
-                self.period = period_secs;
-
                 Ok(())
             }
 
@@ -577,6 +1084,34 @@ macro_rules! make_timer {
                 self.regs.psc.write(|w| unsafe { w.bits(psc.into()) });
             }
 
+            /// Set the counter's tick rate, in Hz, by setting the prescaler. Doesn't change the
+            /// auto-reload value. Returns an error if the timer clock isn't an integer multiple of `freq`,
+            /// or if it's out of range.
+            pub fn set_tick_freq(&mut self, freq: u32) -> Result<(), ValueError> {
+                assert!(freq > 0);
+
+                if self.clock_speed % freq != 0 || self.clock_speed / freq > (1 << 16) {
+                    return Err(ValueError {});
+                }
+
+                self.set_prescaler((self.clock_speed / freq - 1) as u16);
+                Ok(())
+            }
+
+            /// Get the counter's tick rate, in Hz, from the timer clock and prescaler. This is rounded
+            /// down; see `tick_rate` for the exact value.
+            pub fn get_tick_freq(&self) -> u32 {
+                self.clock_speed / (self.regs.psc.read().bits() + 1)
+            }
+
+            /// Get the counter's exact tick rate: The timer clock / (PSC + 1).
+            pub fn tick_rate(&self) -> TickRate {
+                TickRate {
+                    num: self.clock_speed,
+                    den: self.regs.psc.read().bits() + 1,
+                }
+            }
+
             /// Reset the countdown; set the counter to 0.
             pub fn reset_count(&mut self) {
                 self.regs.cnt.write(|w| unsafe { w.bits(0) });
@@ -634,6 +1169,40 @@ macro_rules! make_timer {
                 self.enable_capture_compare(channel);
             }
 
+            /// Configure the timer frequency, and a channel's PWM output, to send a duty-cycle-encoded
+            /// protocol, like WS2812 or DShot. Use the returned encoder to fill a buffer, then send it
+            /// with `write_dma_burst`, using a burst length of 1, and the `UpdateDma` interrupt.
+            pub fn set_pwm_protocol(&mut self, protocol: PwmProtocol, channel: TimChannel) -> Result<PwmEncoder, ValueError> {
+                // Use the full timer clock for duty-cycle resolution.
+                let period = (self.clock_speed + protocol.bit_freq() / 2) / protocol.bit_freq();
+                if period < 10 || period > u16::MAX as u32 + 1 {
+                    return Err(ValueError {});
+                }
+
+                self.set_prescaler(0);
+                self.set_auto_reload(period - 1);
+
+                // The line is low between bits, frames and pixels.
+                self.enable_pwm_output(channel, OutputCompare::Pwm1, 0.);
+                self.reinitialize();
+
+                let (zero, one) = protocol.duty_thousandths();
+                let base_address = match channel {
+                    TimChannel::C1 => 13,
+                    TimChannel::C2 => 14,
+                    TimChannel::C3 => 15,
+                    #[cfg(not(feature = "wl"))]
+                    TimChannel::C4 => 16,
+                };
+
+                Ok(PwmEncoder {
+                    protocol,
+                    zero: ((period * zero + 500) / 1_000) as u16,
+                    one: ((period * one + 500) / 1_000) as u16,
+                    base_address,
+                })
+            }
+
             /// Return the integer associated with the maximum duty period.
             pub fn get_max_duty(&self) -> $res {
                 #[cfg(feature = "g0")]
@@ -648,17 +1217,18 @@ macro_rules! make_timer {
              /// software overhead, but it can also be used to read several registers in a row, at regular
              /// intervals." This may be used to create arbitrary waveforms by modifying the CCR register
              /// (base address = 13-16, for CCR1-4), or for implementing duty-cycle based digital protocols.
-            #[cfg(not(any(feature = "g0", feature = "f4", feature = "l552", feature = "f3", feature = "l4")))]
+            #[cfg(not(any(feature = "g0", feature = "f3", feature = "l4")))]
             pub unsafe fn write_dma_burst(
                 &mut self,
                 buf: &[u16],
                 base_address: u8,
                 burst_len: u8,
-                dma_channel: DmaChannel,
+                dma_channel: &ChannelToken,
                 channel_cfg: ChannelCfg,
                 ds_32_bits: bool,
-                dma_periph: dma::DmaPeriph,
             ) {
+                let (dma_channel, dma_periph) = (dma_channel.channel(), dma_channel.periph());
+
                 // Note: F3 and L4 are unsupported here, since I'm not sure how to select teh
                 // correct Timer channel.
 
@@ -767,18 +1337,19 @@ macro_rules! make_timer {
 
             }
 
-            #[cfg(not(any(feature = "g0", feature = "f4", feature = "l552", feature = "f3", feature = "l4")))]
+            #[cfg(not(any(feature = "g0", feature = "f3", feature = "l4")))]
             pub unsafe fn read_dma_burst(
                 // todo: Experimenting with input capture.
                 &mut self,
                 buf: &mut [u16],
                 base_address: u8,
                 burst_len: u8,
-                dma_channel: DmaChannel,
+                dma_channel: &ChannelToken,
                 channel_cfg: ChannelCfg,
                 ds_32_bits: bool,
-                dma_periph: dma::DmaPeriph,
             ) {
+                let (dma_channel, dma_periph) = (dma_channel.channel(), dma_channel.periph());
+
                 let (ptr, len) = (buf.as_mut_ptr(), buf.len());
 
                 let periph_addr = &self.regs.dmar as *const _ as u32;
@@ -832,91 +1403,59 @@ macro_rules! make_timer {
                 }
             }
 
-            /// Get the time elapsed since the start of the timer.
-            /// Used by `Monotonic` if enabled using the `monotonic` feature, but usable
-            /// on its own.
-            /// Important: the value returned here will only be correct if the ARR and PSC are set
-            /// only using the constructor, `set_freq`, or `set_period` methods; if the timer
-            /// doesn't expire prior to calling this, rel to the time being measured (or if it expires,
-            /// the ISR manually updates the wrap count), if system clock time is changed, if the timer
-            /// is stopped, started etc, or if low power modes are entered.
-            pub fn time_elapsed(&mut self) -> Instant {
-                // let count_us = ((self.read_count() as f32 + self.wrap_count as f32 *
-                //     self.get_max_duty() as f32) * self.us_per_tick) as i64;
-                //
-                // Instant { count_us }
+            /// Get the time elapsed since the start of the timer, in ticks, including wraps counted
+            /// in `wrap_count`. Accounts for a wrap that has occurred, but hasn't been counted yet.
+            /// Assumes edge-aligned, upcounting mode.
+            pub fn ticks_elapsed(&self) -> u64 {
+                let period = self.regs.arr.read().bits() as u64 + 1;
+
+                loop {
+                    let uif = self.get_uif();
+                    let count = self.read_count() as u64;
+                    // Make sure a wrap didn't occur between reading the flag and the count.
+                    if self.get_uif() != uif {
+                        continue;
+                    }
 
-                let count_ns = ((self.read_count() as f32 + self.wrap_count as f32 *
-                    self.get_max_duty() as f32) * self.ns_per_tick) as i64;
+                    return (self.wrap_count as u64 + uif as u64) * period + count;
+                }
+            }
 
-                Instant { count_ns }
+            /// Get the time elapsed since the start of the timer.
+            /// Important: the value returned here will only be correct if the timer doesn't expire
+            /// prior to calling this, rel to the time being measured (or if it expires, the ISR
+            /// manually updates the wrap count), if the timer is stopped, started etc, or if low power
+            /// modes are entered. ARR and PSC may be set by any method, but changing them while
+            /// measuring will make the result incorrect.
+            pub fn time_elapsed(&self) -> Instant {
+                Instant::new(self.ticks_elapsed(), self.tick_rate())
             }
 
             /// Get the current timestamp, while keeping track of overflows. You must increment `TICK_OVERFLOW_COUNT`
             /// in firmware using an ISR, and the timer must be kept running. This is useful for tracking
             /// longer periods of time, such as system uptime.
-            pub fn get_timestamp(&mut self) -> f32 {
-                let elapsed = self.time_elapsed().as_secs();
-
-                TICK_OVERFLOW_COUNT.load(Ordering::Acquire) as f32 * self.period + elapsed
-            }
-
-            /// An alternative to `get_timestamp` that returns the result in microseconds. It avoids
-            /// floating point problems on longer runs.
-            pub fn get_timestamp_ms(&mut self) -> u64 {
-                let elapsed = self.time_elapsed().count_ns as u64 * 1_000;
-
-                TICK_OVERFLOW_COUNT.load(Ordering::Acquire) as u64 * (self.period * 1_000_000.) as u64 + elapsed
-            }
-        }
-
-        #[cfg(feature = "monotonic")]
-        impl Monotonic for Timer<pac::$TIMX> {
-            type Instant = Instant;
-            type Duration = core::time::Duration;
-
-            const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
-
-            fn now(&mut self) -> Self::Instant {
-                self.time_elapsed()
-            }
-
-            /// We use the compare 1 channel for this.
-            /// todo: Support wrapping?
-            fn set_compare(&mut self, instant: Self::Instant) {
-                self.regs
-                    .ccr1()
-                    .write(|w| unsafe { w.ccr().bits(((instant.count_ns as f32 / self.ns_per_tick) as u16).into()) });
-            }
+            pub fn timestamp(&self) -> Duration {
+                let period = self.regs.arr.read().bits() as u64 + 1;
+                let overflows = TICK_OVERFLOW_COUNT.load(Ordering::Acquire) as u64;
 
-            /// We use the compare 1 channel for this.
-            /// todo: Support wrapping?
-            fn clear_compare_flag(&mut self) {
-                self.regs.sr.modify(|_, w| w.cc1if().clear_bit());
+                self.tick_rate()
+                    .ticks_to_duration(overflows * period + self.ticks_elapsed())
             }
 
-            fn zero() -> Self::Instant {
-                Instant::default()
-            }
-
-            unsafe fn reset(&mut self) {
-                self.reset_count();
-                self.wrap_count = 0;
+            /// Get the current timestamp in seconds. See `timestamp`.
+            pub fn get_timestamp(&self) -> f32 {
+                self.timestamp().as_secs_f32()
             }
 
-            fn on_interrupt(&mut self) {
-                self.wrap_count += 1;
-            }
-            fn enable_timer(&mut self) {
-                self.enable();
-            }
-            fn disable_timer(&mut self) {
-                self.disable();
+            /// An alternative to `get_timestamp` that returns the result in milliseconds. It avoids
+            /// floating point problems on longer runs.
+            pub fn get_timestamp_ms(&self) -> u64 {
+                self.timestamp().as_millis() as u64
             }
 
-            /// Print the (raw) contents of the status register.
-            pub fn read_status(&self) -> u32 {
-                unsafe { self.regs.isr.read().bits() }
+            /// An alternative to `get_timestamp` that returns the result in microseconds.
+            pub fn get_timestamp_us(&self) -> u64 {
+                self.timestamp().as_micros() as u64
             }
         }
 
@@ -1063,6 +1602,7 @@ macro_rules! cc_4_channels {
             /// L4 RM, section 26.3.8. H723 RM, section 43.3.7.
             /// Note: Does not handle TISEL (timer input selection register - you must do this manually
             /// using the PAC.
+            /// `filter` sets the `ICxF` field, 0 - 15; 0 disables filtering.
             #[cfg(not(any(feature = "f3", feature = "f4", feature = "l4x5", feature = "l5", feature = "g0", feature = "wb")))]
             pub fn set_input_capture(
                 &mut self,
@@ -1072,7 +1612,10 @@ macro_rules! cc_4_channels {
                 // slave_mode: InputSlaveMode,
                 ccp: Polarity,
                 ccnp: Polarity,
+                filter: u8,
             ) {
+                assert!(filter <= 0b1111);
+
                 // 2. Select the active input for TIMx_CCR1: write the CC1S bits to 01 in the TIMx_CCMR1
                 // register.
                 self.set_capture_compare_input(channel, mode);
@@ -1090,8 +1633,7 @@ macro_rules! cc_4_channels {
                 // cycles. We must program a filter duration longer than these 5 clock cycles. We can
                 // validate a transition on tim_ti1 when 8 consecutive samples with the new level have
                 // been detected (sampled at fDTS frequency). Then write IC1F bits to 0011 in the
-                // TIMx_CCMR1 register.
-                let filter = 0b0011; // todo experimenting.
+                // TIMx_CCMR1 register. (eg `filter` = 0b0011)
 
                 match channel {
                     TimChannel::C1 => {
@@ -1448,6 +1990,7 @@ macro_rules! cc_2_channels {
             /// L4 RM, section 26.3.8. H723 RM, section 43.3.7.
             /// Note: Does not handle TISEL (timer input selection register - you must do this manually
             /// using the PAC.
+            /// `filter` sets the `ICxF` field, 0 - 15; 0 disables filtering.
             #[cfg(not(any(feature = "f3", feature = "f4", feature = "l4x5", feature = "l5", feature = "g0")))]
             pub fn set_input_capture(
                 &mut self,
@@ -1457,10 +2000,11 @@ macro_rules! cc_2_channels {
                 // slave_mode: InputSlaveMode,
                 ccp: Polarity,
                 ccnp: Polarity,
+                filter: u8,
             ) {
-                self.set_capture_compare_input(channel, mode);
+                assert!(filter <= 0b1111);
 
-                let filter = 0b00;
+                self.set_capture_compare_input(channel, mode);
 
                 match channel {
                     TimChannel::C1 => {
@@ -1688,6 +2232,7 @@ macro_rules! cc_1_channel {
             /// L4 RM, section 26.3.8. H723 RM, section 43.3.7.
             /// Note: Does not handle TISEL (timer input selection register - you must do this manually
             /// using the PAC.
+            /// `filter` sets the `ICxF` field, 0 - 15; 0 disables filtering.
             #[cfg(not(any(feature = "f3", feature = "f4", feature = "l4x5", feature = "l5", feature = "g0")))]
             pub fn set_input_capture(
                 &mut self,
@@ -1697,10 +2242,11 @@ macro_rules! cc_1_channel {
                 // slave_mode: InputSlaveMode,
                 ccp: Polarity,
                 ccnp: Polarity,
+                filter: u8,
             ) {
-                self.set_capture_compare_input(channel, mode);
+                assert!(filter <= 0b1111);
 
-                let filter = 0b00;
+                self.set_capture_compare_input(channel, mode);
 
                 match channel {
                     TimChannel::C1 => {
@@ -1881,67 +2427,841 @@ macro_rules! cc_1_channel {
     }
 }
 
-/// Calculate values required to set the timer frequency: `PSC` and `ARR`. This can be
-/// used for initial timer setup, or changing the value later. If used in performance-sensitive
-/// code or frequently, set ARR and PSC directly instead of using this.
-fn calc_freq_vals(freq: f32, clock_speed: u32) -> Result<(u16, u16), ValueError> {
-    // `period` and `clock_speed` are both in Hz.
+/// Functionality only available on advanced-control timers, eg TIM1, 8, and 20: Dead-time
+/// insertion, break inputs, and complementary outputs. See G4 RM, section 28.3.16: Complementary
+/// outputs and dead-time insertion, and 28.3.17: Using the break function.
+macro_rules! advanced_timer {
+    ($TIMX:ident) => {
+        impl Timer<pac::$TIMX> {
+            /// Configure dead-time, break inputs, lock level, and off-state selection. Per the RM,
+            /// these are written in a single access, since the lock level may protect them from
+            /// subsequent writes. This doesn't change the main output enable; use `enable_main_output`.
+            pub fn set_break_deadtime(&mut self, cfg: &BreakDeadtimeCfg) -> Result<(), ValueError> {
+                let mut val = self.calc_dead_time(cfg.dead_time)? as u32;
+
+                val |= (cfg.lock as u32) << 8;
+                val |= (cfg.off_state_idle as u32) << 10;
+                val |= (cfg.off_state_run as u32) << 11;
+                val |= (cfg.automatic_output as u32) << 14;
+
+                if let Some(brk) = cfg.break1 {
+                    val |= 1 << 12 | (brk.polarity as u32) << 13;
+                    #[cfg(not(feature = "f4"))]
+                    {
+                        assert!(brk.filter <= 0b1111);
+                        val |= (brk.filter as u32) << 16;
+                    }
+                }
 
-    // PSC and ARR range: 0 to 65535
-    // (PSC+1)*(ARR+1) = TIMclk/Updatefrequency = TIMclk * period
-    // APB1 (pclk1) is used by Tim2, 3, 4, 6, 7.
-    // APB2 (pclk2) is used by Tim8, 15-20 etc.
+                #[cfg(not(feature = "f4"))]
+                if let Some(brk) = cfg.break2 {
+                    assert!(brk.filter <= 0b1111);
+                    val |= (brk.filter as u32) << 20 | 1 << 24 | (brk.polarity as u32) << 25;
+                }
 
-    // We need to factor the right-hand-side of the above equation
-    // into integers. There are likely clever algorithms available to do this.
-    // Some examples: https://cp-algorithms.com/algebra/factorization.html
-    // We've chosen something that attempts to maximize ARR, for precision when
-    // setting duty cycle. Alternative approaches might involve setting a frequency closest to the
-    // requested one.
+                // Preserve MOE.
+                let moe = self.regs.bdtr.read().bits() & (1 << 15);
+                self.regs.bdtr.write(|w| unsafe { w.bits(val | moe) });
 
-    // If you work with pure floats, there are an infinite number of solutions: Ie for any value of PSC,
-    // you can find an ARR to solve the equation.
-    // The actual values are integers that must be between 0 and 65_536
-    // Different combinations will result in different amounts of rounding error.
+                Ok(())
+            }
 
-    let max_val = 65_535.;
-    let rhs = clock_speed as f32 / freq;
+            /// Set the dead time only, in seconds. Has no effect if the lock level is 1 or higher.
+            pub fn set_dead_time(&mut self, dead_time: f32) -> Result<(), ValueError> {
+                let dtg = self.calc_dead_time(dead_time)?;
+                self.regs
+                    .bdtr
+                    .modify(|r, w| unsafe { w.bits((r.bits() & !0xff) | dtg as u32) });
+                Ok(())
+            }
 
-    let psc = ((rhs - 1.) / (1 << 16) as f32).round();
-    let arr = rhs / (psc + 1.) - 1.;
+            /// Calculate the `DTG` field value for a dead time in seconds, using its non-linear encoding.
+            /// The dead-time clock is the timer clock, divided by `TIMx_CR1`'s `CKD` field.
+            fn calc_dead_time(&self, dead_time: f32) -> Result<u8, ValueError> {
+                assert!(dead_time >= 0.);
 
-    if arr > max_val || psc > max_val {
-        return Err(ValueError {});
-    }
+                let ckd = match (self.regs.cr1.read().bits() >> 8) & 0b11 {
+                    0b00 => 1.,
+                    0b01 => 2.,
+                    _ => 4.,
+                };
+                let ticks = (dead_time * self.clock_speed as f32 / ckd).round() as u32;
 
-    Ok((psc as u16, arr as u16))
-}
+                // DTG[7:5] = 0xx: DT = DTG[7:0] x tDTS
+                // DTG[7:5] = 10x: DT = (64 + DTG[5:0]) x 2 x tDTS
+                // DTG[7:5] = 110: DT = (32 + DTG[4:0]) x 8 x tDTS
+                // DTG[7:5] = 111: DT = (32 + DTG[4:0]) x 16 x tDTS
+                let dtg = match ticks {
+                    0..=127 => ticks,
+                    128..=255 => 0b1000_0000 | (ticks / 2 - 64),
+                    256..=511 => 0b1100_0000 | (ticks / 8 - 32),
+                    512..=1023 => 0b1110_0000 | (ticks / 16 - 32),
+                    _ => return Err(ValueError {}),
+                };
 
-cfg_if! {
-    if #[cfg(not(any(
-        feature = "f401",
-        feature = "f410",
-        feature = "f411",
-        feature = "f413",
-        feature = "g031",
-        feature = "g041",
-        feature = "g070",
-        feature = "g030",
-        feature = "wb",
-        feature = "wl"
-    )))]  {
-        /// Represents a Basic timer, used primarily to trigger the onboard DAC. Eg Tim6 or Tim7.
-        pub struct BasicTimer<R> {
-            pub regs: R,
-            clock_speed: u32,
-        }
+                Ok(dtg as u8)
+            }
 
-        impl<R> BasicTimer<R>
-            where
-                R: Deref<Target = pac::tim6::RegisterBlock> + RccPeriph,
-        {
-            /// Initialize a Basic timer, including  enabling and resetting
-            /// its RCC peripheral clock.
+            /// Enable the main output (MOE). Channel outputs and complementary outputs are only active
+            /// while this is set. It's cleared by hardware when a break occurs.
+            pub fn enable_main_output(&mut self) {
+                self.regs.bdtr.modify(|_, w| w.moe().set_bit());
+            }
+
+            /// Disable the main output (MOE). Outputs go to the states set by `off_state_idle` and
+            /// their idle levels.
+            pub fn disable_main_output(&mut self) {
+                self.regs.bdtr.modify(|_, w| w.moe().clear_bit());
+            }
+
+            /// Check if the main output is enabled. Useful for detecting if a break has occurred.
+            pub fn main_output_is_enabled(&self) -> bool {
+                self.regs.bdtr.read().moe().bit_is_set()
+            }
+
+            /// Enable a channel's complementary output. Sets `TIMx_CCER` register, `CCxNE` field.
+            pub fn enable_complementary_output(&mut self, channel: TimChannel) {
+                match channel {
+                    TimChannel::C1 => self.regs.ccer.modify(|_, w| w.cc1ne().set_bit()),
+                    TimChannel::C2 => self.regs.ccer.modify(|_, w| w.cc2ne().set_bit()),
+                    TimChannel::C3 => self.regs.ccer.modify(|_, w| w.cc3ne().set_bit()),
+                    #[cfg(not(feature = "wl"))]
+                    TimChannel::C4 => panic!("Channel 4 has no complementary output."),
+                }
+            }
+
+            /// Disable a channel's complementary output.
+            pub fn disable_complementary_output(&mut self, channel: TimChannel) {
+                match channel {
+                    TimChannel::C1 => self.regs.ccer.modify(|_, w| w.cc1ne().clear_bit()),
+                    TimChannel::C2 => self.regs.ccer.modify(|_, w| w.cc2ne().clear_bit()),
+                    TimChannel::C3 => self.regs.ccer.modify(|_, w| w.cc3ne().clear_bit()),
+                    #[cfg(not(feature = "wl"))]
+                    TimChannel::C4 => panic!("Channel 4 has no complementary output."),
+                }
+            }
+
+            /// Enable the break interrupt. Sets `TIMx_DIER` register, `BIE` field. Note that on some
+            /// MCUs, this shares an interrupt vector with other timers; eg `TIM1_BRK_TIM15`.
+            pub fn enable_break_interrupt(&mut self) {
+                self.regs
+                    .dier
+                    .modify(|r, w| unsafe { w.bits(r.bits() | 1 << 7) });
+            }
+
+            /// Disable the break interrupt.
+            pub fn disable_break_interrupt(&mut self) {
+                self.regs
+                    .dier
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << 7)) });
+            }
+
+            /// Check if a break has occurred on break input 1 or 2. Reads `TIMx_SR` register, `BIF`
+            /// and `B2IF` fields.
+            pub fn break_occurred(&self) -> bool {
+                #[cfg(feature = "f4")]
+                let mask = 1 << 7;
+                #[cfg(not(feature = "f4"))]
+                let mask = 1 << 7 | 1 << 8;
+
+                self.regs.sr.read().bits() & mask != 0
+            }
+
+            /// Clear the break flags. Run this in the break interrupt handler. Note that MOE must
+            /// be set again (by software, or automatically if enabled) to re-enable outputs.
+            pub fn clear_break(&mut self) {
+                // See the note in `clear_interrupt` about writing 1s to the rest of the bits.
+                self.regs
+                    .sr
+                    .write(|w| unsafe { w.bits(!(1 << 7 | 1 << 8)) });
+            }
+        }
+    };
+}
+
+/// PWM input mode: Measure a signal's frequency and duty cycle on CH1, using IC1 and IC2, with the
+/// slave mode controller in reset mode. See G4 RM, section 28.3.8, or H743 RM, section 39.3.8:
+/// PWM input mode.
+macro_rules! pwm_input {
+    ($TIMX:ident, $res:ident) => {
+        impl Timer<pac::$TIMX> {
+            /// Configure PWM input mode, on CH1. Sets the auto-reload value to its maximum; a
+            /// period longer than this can't be measured. Run this with the timer disabled; enable it after.
+            pub fn set_pwm_input(&mut self, cfg: &PwmInputCfg) {
+                assert!(cfg.filter <= 0b1111);
+
+                // CC1S = 0b01: IC1 is mapped on TI1. CC2S = 0b10: IC2 is also mapped on TI1. The
+                // input prescalers (ICxPSC) are left at 0, since the counter is reset on every
+                // active edge, whether it's captured or not.
+                let ccmr = 0b01 | (cfg.filter as u32) << 4 | 0b10 << 8 | (cfg.filter as u32) << 12;
+                self.regs
+                    .ccmr1_input()
+                    .modify(|r, w| unsafe { w.bits((r.bits() & !0xffff) | ccmr) });
+
+                // IC1 captures on the active edge, and IC2 on the opposite one. Enable both captures.
+                let ccer = (cfg.polarity.bit() as u32) << 1 | (!cfg.polarity.bit() as u32) << 5;
+                self.regs.ccer.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b1111 | 0b1111 << 4)) | ccer | 1 | 1 << 4)
+                });
+
+                // TS = TI1FP1, and SMS = reset mode: The counter is reset on each period's
+                // active edge.
+                self.regs.smcr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(0b111 | 0b111 << 4 | 1 << 16 | 0b11 << 20))
+                            | (InputTrigger::FilteredTimerInput1 as u32) << 4
+                            | InputSlaveMode::Reset as u32,
+                    )
+                });
+
+                self.set_prescaler(cfg.psc);
+                self.set_auto_reload($res::MAX as u32);
+                self.reinitialize();
+            }
+
+            /// Read the measured PWM input, as (frequency in Hz, duty cycle in percent). Returns
+            /// `None` if no full period has been captured. If the signal stops, this continues
+            /// to return the last measurement; the `CaptureCompare1` interrupt can be used to
+            /// detect this.
+            pub fn read_pwm_input(&self) -> Option<(f32, f32)> {
+                let period = self.get_duty(TimChannel::C1) as u32;
+                let active = self.get_duty(TimChannel::C2) as u32;
+
+                if period == 0 {
+                    return None;
+                }
+
+                let tick_freq = self.clock_speed as f32 / (self.regs.psc.read().bits() + 1) as f32;
+
+                Some((
+                    tick_freq / period as f32,
+                    active as f32 / period as f32 * 100.,
+                ))
+            }
+        }
+    };
+}
+
+/// Master/slave synchronization between timers. See G4 RM, section 28.3.29: Timer
+/// synchronization, or H743 RM, section 39.3.19.
+macro_rules! timer_sync {
+    ($TIMX:ident, $num:ident) => {
+        impl TimerSync for Timer<pac::$TIMX> {
+            const NUM: TimerNum = TimerNum::$num;
+
+            fn set_master_mode(&mut self, mode: MasterModeSelection) {
+                self.regs.cr2.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b111 << 4)) | (mode as u32) << 4)
+                });
+            }
+
+            fn set_master_slave_mode(&mut self, enabled: bool) {
+                self.regs.smcr.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(1 << 7)) | (enabled as u32) << 7)
+                });
+            }
+
+            fn set_slave_mode(&mut self, trigger: InputTrigger, mode: InputSlaveMode) {
+                let trigger = trigger as u32;
+                let mode = mode as u32;
+
+                // `TS[4:3]` and `SMS[3]` are in separate bit positions, where present.
+                let val = (trigger & 0b111) << 4
+                    | (trigger >> 3) << 20
+                    | (mode & 0b111)
+                    | (mode >> 3) << 16;
+
+                // Per the RM, TS must only be changed when the slave mode is disabled.
+                self.regs
+                    .smcr
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(0b111 | 1 << 16)) });
+                self.regs.smcr.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b111 << 4 | 0b11 << 20 | 0b111 | 1 << 16)) | val)
+                });
+            }
+
+            fn counter(&self) -> u32 {
+                self.regs.cnt.read().bits()
+            }
+
+            fn auto_reload(&self) -> u32 {
+                self.regs.arr.read().bits()
+            }
+        }
+    };
+}
+
+/// RTIC monotonic support. The hardware counter is extended to 64 bits using a half-period count,
+/// which is incremented by the update interrupt, and a CC2 interrupt at the half-period mark. CC1
+/// is used for the compare. These interrupts must be serviced within half a timer period, and are
+/// on a separate interrupt vector from the compare on TIM1 and TIM8; route both to the handler.
+///
+/// `Timer` implements `rtic_monotonic::Monotonic` with the `monotonic` feature. With the
+/// `monotonic_v2` feature, eg `Tim2Backend` is an `rtic-time` v2 timer queue backend, for use with
+/// `TimerMonotonic` and its `fugit` time types.
+macro_rules! monotonic {
+    ($TIMX:ident) => {
+        paste! {
+            #[cfg(any(feature = "monotonic", feature = "monotonic_v2"))]
+            /// Half periods elapsed since the monotonic was started. Only modified in its ISR.
+            static [<$TIMX _HALF_PERIODS>]: AtomicU32 = AtomicU32::new(0);
+
+            #[cfg(any(feature = "monotonic", feature = "monotonic_v2"))]
+            impl Timer<pac::$TIMX> {
+                /// Configure the timer as a monotonic time base, and start it. Set the tick rate
+                /// first, eg using `set_tick_freq`. Enables the update and CC2 interrupts; unmask
+                /// the timer's interrupt(s) in the NVIC.
+                pub fn start_monotonic(&mut self) {
+                    self.disable();
+
+                    // 16-bit timers ignore the upper bits.
+                    self.regs.arr.write(|w| unsafe { w.bits(u32::MAX) });
+                    let half = self.regs.arr.read().bits() / 2 + 1;
+                    unsafe { core::ptr::write_volatile(Self::monotonic_ccr(2), half) };
+
+                    // Load the prescaler, and reset the counter.
+                    self.reinitialize();
+                    self.regs.sr.write(|w| unsafe { w.bits(!(1 << 1 | 1 << 2)) });
+                    [<$TIMX _HALF_PERIODS>].store(0, Ordering::Relaxed);
+
+                    // UIE and CC2IE.
+                    self.regs.dier.modify(|r, w| unsafe { w.bits(r.bits() | 1 | 1 << 2) });
+                    self.enable();
+                }
+
+                /// A pointer to a CCR register; their field names vary between timers and PACs.
+                fn monotonic_ccr(channel: usize) -> *mut u32 {
+                    unsafe { (pac::$TIMX::ptr() as *mut u8).add(0x34 + (channel - 1) * 4) as *mut u32 }
+                }
+
+                /// The 64-bit monotonic tick count.
+                fn monotonic_now() -> u64 {
+                    let regs = unsafe { &(*pac::$TIMX::ptr()) };
+
+                    // The half-period count must be read before the counter.
+                    let half_periods = [<$TIMX _HALF_PERIODS>].load(Ordering::Relaxed);
+                    compiler_fence(Ordering::SeqCst);
+                    let count = regs.cnt.read().bits();
+
+                    monotonic_ticks(half_periods, count, regs.arr.read().bits())
+                }
+
+                /// Program CC1 for a target tick count, if it's within one timer period. Otherwise,
+                /// this is checked again on the next update or half-period interrupt.
+                fn monotonic_set_compare(instant: u64) {
+                    let regs = unsafe { &(*pac::$TIMX::ptr()) };
+                    let range = regs.arr.read().bits() as u64 + 1;
+
+                    match instant.checked_sub(Self::monotonic_now()) {
+                        // In the past; generate a CC1 event, so the interrupt fires now.
+                        None | Some(0) => Self::monotonic_pend(),
+                        // CC1 matches once before the target, at the target.
+                        Some(ticks) if ticks < range => {
+                            unsafe { core::ptr::write_volatile(Self::monotonic_ccr(1), (instant % range) as u32) };
+                            regs.dier.modify(|r, w| unsafe { w.bits(r.bits() | 1 << 1) });
+
+                            // If the counter passed the target while we were arming CC1, it won't
+                            // match until the next period; fire the interrupt now instead.
+                            if instant <= Self::monotonic_now() {
+                                Self::monotonic_pend();
+                            }
+                        }
+                        _ => regs.dier.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << 1)) }),
+                    }
+                }
+
+                /// Fire the compare interrupt, by setting `TIMx_EGR` register, `CC1G` field.
+                fn monotonic_pend() {
+                    let regs = unsafe { &(*pac::$TIMX::ptr()) };
+                    regs.dier.modify(|r, w| unsafe { w.bits(r.bits() | 1 << 1) });
+                    regs.egr.write(|w| unsafe { w.bits(1 << 1) });
+                }
+
+                fn monotonic_clear_compare_flag() {
+                    let regs = unsafe { &(*pac::$TIMX::ptr()) };
+                    // See the note in `clear_interrupt` about writing 1s to the rest of the bits.
+                    regs.sr.write(|w| unsafe { w.bits(!(1 << 1)) });
+                }
+
+                /// Count overflows, and half-period marks.
+                fn monotonic_on_interrupt() {
+                    let regs = unsafe { &(*pac::$TIMX::ptr()) };
+                    let sr = regs.sr.read().bits();
+
+                    // Note: We don't use `fetch_add`, since it's not available on Cortex-M0.
+                    let count = &[<$TIMX _HALF_PERIODS>];
+                    if sr & 1 != 0 {
+                        regs.sr.write(|w| unsafe { w.bits(!1) });
+                        count.store(count.load(Ordering::Relaxed).wrapping_add(1), Ordering::Relaxed);
+                    }
+                    if sr & (1 << 2) != 0 {
+                        regs.sr.write(|w| unsafe { w.bits(!(1 << 2)) });
+                        count.store(count.load(Ordering::Relaxed).wrapping_add(1), Ordering::Relaxed);
+                    }
+                }
+            }
+
+            #[cfg(feature = "monotonic")]
+            impl Monotonic for Timer<pac::$TIMX> {
+                type Instant = Instant;
+                type Duration = core::time::Duration;
+
+                const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+                fn now(&mut self) -> Self::Instant {
+                    Instant::new(Self::monotonic_now(), self.tick_rate())
+                }
+
+                fn set_compare(&mut self, instant: Self::Instant) {
+                    Self::monotonic_set_compare(instant.ticks_at(self.tick_rate()));
+                }
+
+                fn clear_compare_flag(&mut self) {
+                    Self::monotonic_clear_compare_flag();
+                }
+
+                fn zero() -> Self::Instant {
+                    Instant::default()
+                }
+
+                unsafe fn reset(&mut self) {
+                    self.start_monotonic();
+                }
+
+                fn on_interrupt(&mut self) {
+                    Self::monotonic_on_interrupt();
+                }
+
+                fn enable_timer(&mut self) {
+                    self.enable();
+                }
+
+                fn disable_timer(&mut self) {
+                    self.disable();
+                }
+            }
+
+            #[cfg(feature = "monotonic_v2")]
+            static [<$TIMX _TIMER_QUEUE>]: TimerQueue<[<$TIMX:camel Backend>]> = TimerQueue::new();
+
+            #[cfg(feature = "monotonic_v2")]
+            /// `rtic-time` v2 timer queue backend. Use it with `TimerMonotonic`.
+            pub struct [<$TIMX:camel Backend>];
+
+            #[cfg(feature = "monotonic_v2")]
+            impl [<$TIMX:camel Backend>] {
+                /// Start the monotonic, with a tick rate in Hz. This must match the `FREQ`
+                /// parameter of the `TimerMonotonic` it's used with.
+                pub fn start(mut timer: Timer<pac::$TIMX>, tick_freq: u32) -> Result<(), ValueError> {
+                    timer.set_tick_freq(tick_freq)?;
+                    timer.start_monotonic();
+                    [<$TIMX _TIMER_QUEUE>].initialize(Self {});
+
+                    Ok(())
+                }
+
+                /// Run this in the timer's interrupt handler(s).
+                pub unsafe fn on_interrupt() {
+                    [<$TIMX _TIMER_QUEUE>].on_monotonic_interrupt();
+                }
+            }
+
+            #[cfg(feature = "monotonic_v2")]
+            impl TimerQueueBackend for [<$TIMX:camel Backend>] {
+                type Ticks = u64;
+
+                fn now() -> Self::Ticks {
+                    Timer::<pac::$TIMX>::monotonic_now()
+                }
+
+                fn set_compare(instant: Self::Ticks) {
+                    Timer::<pac::$TIMX>::monotonic_set_compare(instant);
+                }
+
+                fn clear_compare_flag() {
+                    Timer::<pac::$TIMX>::monotonic_clear_compare_flag();
+                }
+
+                fn pend_interrupt() {
+                    Timer::<pac::$TIMX>::monotonic_pend();
+                }
+
+                fn on_interrupt() {
+                    Timer::<pac::$TIMX>::monotonic_on_interrupt();
+                }
+
+                fn timer_queue() -> &'static TimerQueue<Self> {
+                    &[<$TIMX _TIMER_QUEUE>]
+                }
+            }
+        }
+    };
+}
+
+/// One-pulse mode: Output a single pulse, after a delay from a software or input trigger. The
+/// counter stops after each pulse, and waits for the next trigger. See G4 RM, section 28.3.19, or
+/// H743 RM, section 39.3.16: One-pulse mode.
+macro_rules! one_pulse {
+    ($TIMX:ident, $res:ident) => {
+        impl Timer<pac::$TIMX> {
+            /// Configure one-pulse mode, with delay and width in seconds. Sets the prescaler to fit
+            /// both in the counter's range. Each is at least 1 tick; shorter values are rounded up.
+            /// Note that on advanced-control timers, the main output must be enabled with
+            /// `enable_main_output`.
+            pub fn set_one_pulse(
+                &mut self,
+                cfg: &OnePulseCfg,
+                delay: f32,
+                width: f32,
+            ) -> Result<(), ValueError> {
+                assert!(delay >= 0. && width > 0.);
+
+                // 16-bit timers ignore the upper bits.
+                self.regs.arr.write(|w| unsafe { w.bits(u32::MAX) });
+                let max_ticks = self.regs.arr.read().bits() as f32 + 1.;
+
+                let ticks = (delay + width) * self.clock_speed as f32;
+                let mut psc = (ticks / max_ticks).ceil().max(1.) - 1.;
+
+                // Delay and width are rounded separately, so their total may be a tick over the
+                // counter's range; if so, use the next prescaler value.
+                let (delay, width) = loop {
+                    if psc > u16::MAX as f32 {
+                        return Err(ValueError {});
+                    }
+
+                    let tick_freq = self.clock_speed as f32 / (psc + 1.);
+                    let delay = ((delay * tick_freq).round() as u32).max(1);
+                    let width = ((width * tick_freq).round() as u32).max(1);
+
+                    if delay as f32 + width as f32 <= max_ticks {
+                        break (delay, width);
+                    }
+                    psc += 1.;
+                };
+                self.set_prescaler(psc as u16);
+
+                self.set_one_pulse_ticks(cfg, delay, width)
+            }
+
+            /// Configure one-pulse mode, with delay and width in timer ticks. Both must be at least 1;
+            /// with a delay of 0, the output would be active while waiting for the trigger. The delay
+            /// is measured from the trigger; there's a few ticks of latency before the counter starts.
+            /// With an input trigger, don't enable the timer; it's started by each trigger edge.
+            pub fn set_one_pulse_ticks(
+                &mut self,
+                cfg: &OnePulseCfg,
+                delay: u32,
+                width: u32,
+            ) -> Result<(), ValueError> {
+                assert!(delay > 0, "The one-pulse delay must be at least 1 tick.");
+                assert!(width > 0 && cfg.trigger_filter <= 0b1111);
+                assert!(
+                    !matches!(
+                        (cfg.trigger, cfg.channel),
+                        (PulseTrigger::Ti1, TimChannel::C1) | (PulseTrigger::Ti2, TimChannel::C2)
+                    ),
+                    "The one-pulse output channel can't be the trigger input's channel."
+                );
+
+                self.disable();
+
+                // 16-bit timers ignore the upper bits.
+                self.regs.arr.write(|w| unsafe { w.bits(u32::MAX) });
+                let max = self.regs.arr.read().bits();
+
+                // The output is inactive until CNT reaches CCR, then active until ARR.
+                let arr = delay.checked_add(width - 1).ok_or(ValueError {})?;
+                if arr > max {
+                    return Err(ValueError {});
+                }
+
+                // Set `TIMx_CR1` register, `OPM` field: Stop the counter at the next update event.
+                self.regs
+                    .cr1
+                    .modify(|r, w| unsafe { w.bits(r.bits() | 1 << 3) });
+
+                self.set_capture_compare_output(cfg.channel, CaptureCompare::Output);
+                self.set_preload(cfg.channel, true);
+                self.set_output_compare(cfg.channel, OutputCompare::Pwm2);
+                self.set_polarity(cfg.channel, cfg.polarity);
+                self.set_duty(cfg.channel, delay as $res);
+                self.set_auto_reload(arr);
+                self.enable_capture_compare(cfg.channel);
+
+                // Trigger input: CCxS = 0b01 maps IC1 on TI1, or IC2 on TI2. Set the filter,
+                // and edge polarity.
+                let filter = (cfg.trigger_filter as u32) << 4;
+                let trigger = match cfg.trigger {
+                    PulseTrigger::Software => None,
+                    PulseTrigger::Ti1 => Some((InputTrigger::FilteredTimerInput1, 0, 1)),
+                    PulseTrigger::Ti2 => Some((InputTrigger::FilteredTimerInput2, 8, 5)),
+                };
+
+                if let Some((_, ccmr_shift, ccer_shift)) = trigger {
+                    self.regs.ccmr1_input().modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0xff << ccmr_shift)) | (0b01 | filter) << ccmr_shift)
+                    });
+                    // CCxP, and CCxNP.
+                    self.regs.ccer.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !(0b101 << ccer_shift))
+                                | (cfg.trigger_polarity.bit() as u32) << ccer_shift,
+                        )
+                    });
+                }
+
+                // Trigger mode: The counter starts on the trigger input's edge. With the software
+                // trigger, the slave mode controller is disabled.
+                let smcr = match trigger {
+                    Some((ts, _, _)) => {
+                        let ts = ts as u32;
+                        (ts & 0b111) << 4 | (ts >> 3) << 20 | InputSlaveMode::Trigger as u32
+                    }
+                    None => 0,
+                };
+                self.regs.smcr.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b111 | 0b111 << 4 | 1 << 16 | 0b11 << 20)) | smcr)
+                });
+
+                // Load the prescaler and preloaded values, and reset the counter.
+                self.reinitialize();
+
+                Ok(())
+            }
+
+            /// Start a pulse, when using the software trigger. Has no effect if a pulse is in
+            /// progress.
+            pub fn fire_pulse(&mut self) {
+                self.enable();
+            }
+
+            /// Check if a pulse is in progress; ie the counter is running.
+            pub fn pulse_in_progress(&self) -> bool {
+                self.is_enabled()
+            }
+        }
+    };
+}
+
+/// Quadrature encoder interface mode, using CH1 and CH2 as the A and B inputs. See G4 RM, section
+/// 28.3.24: Encoder interface mode, or H743 RM, section 39.3.21.
+macro_rules! encoder {
+    ($TIMX:ident, $res:ident) => {
+        impl Timer<pac::$TIMX> {
+            /// Configure the timer as a quadrature encoder interface. Sets the prescaler to 0, and
+            /// the auto-reload value to its maximum. To track position past the counter's range,
+            /// enable the `Update` interrupt, and call `update_encoder_wraps` in its handler.
+            /// Run this with the timer disabled; enable it after.
+            pub fn set_encoder_mode(&mut self, cfg: &EncoderCfg) {
+                assert!(cfg.filter_a <= 0b1111 && cfg.filter_b <= 0b1111);
+
+                // CC1S = CC2S = 0b01: IC1 is mapped on TI1, and IC2 on TI2. No input prescaler.
+                let ccmr =
+                    0b01 | (cfg.filter_a as u32) << 4 | 0b01 << 8 | (cfg.filter_b as u32) << 12;
+                self.regs
+                    .ccmr1_input()
+                    .modify(|r, w| unsafe { w.bits((r.bits() & !0xffff) | ccmr) });
+
+                // CCxNP must be kept cleared in encoder mode.
+                let ccer = (cfg.polarity_a.bit() as u32) << 1 | (cfg.polarity_b.bit() as u32) << 5;
+                self.regs.ccer.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(1 << 1 | 1 << 3 | 1 << 5 | 1 << 7)) | ccer)
+                });
+
+                // Clear SMS[3] too, where present.
+                self.regs.smcr.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b111 | 1 << 16)) | cfg.mode as u32)
+                });
+
+                self.set_prescaler(0);
+                self.set_auto_reload($res::MAX as u32);
+                // Load the prescaler, and reset the counter.
+                self.reinitialize();
+
+                self.wrap_count = 0;
+            }
+
+            #[cfg(feature = "g4")]
+            /// Enable encoder index reset, using the ETR input as the index signal. This resets the
+            /// counter, but not the wrap count; to keep an absolute position, call
+            /// `reset_encoder_position` when the `TIMx_SR` register's `IDXF` flag is set.
+            pub fn enable_encoder_index(&mut self, index: EncoderIndex) {
+                assert!(index.filter <= 0b1111 && index.position <= 0b11);
+
+                self.regs.smcr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(0b1111 << 8 | 1 << 15))
+                            | (index.filter as u32) << 8
+                            | (index.inverted as u32) << 15,
+                    )
+                });
+
+                self.regs.ecr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(0b1 | 0b11 << 1 | 1 << 5 | 0b11 << 6))
+                            | 1
+                            | (index.direction as u32) << 1
+                            | (index.first_only as u32) << 5
+                            | (index.position as u32) << 6,
+                    )
+                });
+            }
+
+            #[cfg(feature = "g4")]
+            /// Disable encoder index reset. Sets `TIMx_ECR` register, `IE` field.
+            pub fn disable_encoder_index(&mut self) {
+                self.regs
+                    .ecr
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !1) });
+            }
+
+            /// Update the wrap count on counter overflow or underflow. Run this in the timer's
+            /// update interrupt handler, when in encoder mode. `wrap_count` is treated as signed.
+            pub fn update_encoder_wraps(&mut self) {
+                // The counter is near 0 after an overflow, and near ARR after an underflow. We
+                // check this instead of `DIR`, since the direction may have changed since the event.
+                if self.read_count() > self.regs.arr.read().bits() / 2 {
+                    self.wrap_count = self.wrap_count.wrapping_sub(1);
+                } else {
+                    self.wrap_count = self.wrap_count.wrapping_add(1);
+                }
+            }
+
+            /// Read the signed encoder position, in counts, including counter wraps. Accounts for
+            /// a wrap that has occurred, but that `update_encoder_wraps` hasn't handled yet.
+            pub fn read_encoder_position(&self) -> i64 {
+                let range = self.regs.arr.read().bits() as i64 + 1;
+                let half = self.regs.arr.read().bits() / 2;
+
+                loop {
+                    let uif = self.get_uif();
+                    let count = self.read_count();
+                    // Make sure a wrap didn't occur between reading the flag and the count.
+                    if self.get_uif() != uif {
+                        continue;
+                    }
+
+                    let mut wraps = self.wrap_count as i32 as i64;
+                    if uif {
+                        if count > half {
+                            wraps -= 1;
+                        } else {
+                            wraps += 1;
+                        }
+                    }
+
+                    return wraps * range + count as i64;
+                }
+            }
+
+            /// Set the encoder position to 0.
+            pub fn reset_encoder_position(&mut self) {
+                self.reset_count();
+                self.wrap_count = 0;
+            }
+
+            /// Get the current encoder direction. Reads `TIMx_CR1` register, `DIR` field.
+            pub fn encoder_direction(&self) -> CountDir {
+                if self.regs.cr1.read().bits() & (1 << 4) != 0 {
+                    CountDir::Down
+                } else {
+                    CountDir::Up
+                }
+            }
+        }
+    };
+}
+
+#[cfg(any(feature = "monotonic", feature = "monotonic_v2"))]
+/// Calculate a 64-bit tick count from a half-period count, and the counter value. `arr` is the
+/// counter's maximum value, eg `0xffff`. The half-period count overlaps with the counter's top bit;
+/// this makes the result correct, whether or not the latest update or half-period interrupt has
+/// been serviced, as long as the half-period count is read first.
+fn monotonic_ticks(half_periods: u32, count: u32, arr: u32) -> u64 {
+    let half = (arr as u64 + 1) / 2;
+    half_periods as u64 * half + (count as u64 ^ (half_periods as u64 & 1) * half)
+}
+
+#[cfg(feature = "monotonic_v2")]
+/// An `rtic-monotonics` (v2) compatible monotonic, with `fugit` time types. `B` is a timer's
+/// backend, eg `Tim2Backend`, and `FREQ` is its tick rate, in Hz. Example:
+/// `type Mono = TimerMonotonic<Tim2Backend, 1_000_000>;`, then `Tim2Backend::start(timer, 1_000_000)`,
+/// and run `Tim2Backend::on_interrupt()` in the `TIM2` interrupt handler.
+pub struct TimerMonotonic<B, const FREQ: u32>(PhantomData<B>);
+
+#[cfg(feature = "monotonic_v2")]
+impl<B: TimerQueueBackend<Ticks = u64>, const FREQ: u32> TimerQueueBasedMonotonic
+    for TimerMonotonic<B, FREQ>
+{
+    type Backend = B;
+    type Instant = fugit::Instant<u64, 1, FREQ>;
+    type Duration = fugit::Duration<u64, 1, FREQ>;
+}
+
+/// Calculate values required to set the timer frequency: `PSC` and `ARR`. This can be
+/// used for initial timer setup, or changing the value later. If used in performance-sensitive
+/// code or frequently, set ARR and PSC directly instead of using this.
+fn calc_freq_vals(freq: f32, clock_speed: u32) -> Result<(u16, u16), ValueError> {
+    // `period` and `clock_speed` are both in Hz.
+
+    // PSC and ARR range: 0 to 65535
+    // (PSC+1)*(ARR+1) = TIMclk/Updatefrequency = TIMclk * period
+    // APB1 (pclk1) is used by Tim2, 3, 4, 6, 7.
+    // APB2 (pclk2) is used by Tim8, 15-20 etc.
+
+    // We need to factor the right-hand-side of the above equation
+    // into integers. There are likely clever algorithms available to do this.
+    // Some examples: https://cp-algorithms.com/algebra/factorization.html
+    // We've chosen something that attempts to maximize ARR, for precision when
+    // setting duty cycle. Alternative approaches might involve setting a frequency closest to the
+    // requested one.
+
+    // If you work with pure floats, there are an infinite number of solutions: Ie for any value of PSC,
+    // you can find an ARR to solve the equation.
+    // The actual values are integers that must be between 0 and 65_536
+    // Different combinations will result in different amounts of rounding error.
+
+    let max_val = 65_535.;
+    let rhs = clock_speed as f32 / freq;
+
+    let psc = ((rhs - 1.) / (1 << 16) as f32).round();
+    let arr = rhs / (psc + 1.) - 1.;
+
+    if arr > max_val || psc > max_val {
+        return Err(ValueError {});
+    }
+
+    Ok((psc as u16, arr as u16))
+}
+
+cfg_if! {
+    if #[cfg(not(any(
+        feature = "f401",
+        feature = "f410",
+        feature = "f411",
+        feature = "f413",
+        feature = "g031",
+        feature = "g041",
+        feature = "g070",
+        feature = "g030",
+        feature = "wb",
+        feature = "wl"
+    )))]  {
+        /// Represents a Basic timer, used primarily to trigger the onboard DAC. Eg Tim6 or Tim7.
+        #[deprecated(note = "Use `Timer::new_tim6` or `Timer::new_tim7` instead.")]
+        pub struct BasicTimer<R> {
+            pub regs: R,
+            clock_speed: u32,
+        }
+
+        #[allow(deprecated)]
+        impl<R> BasicTimer<R>
+            where
+                R: Deref<Target = pac::tim6::RegisterBlock> + RccPeriph,
+        {
+            /// Initialize a Basic timer, including  enabling and resetting
+            /// its RCC peripheral clock.
             pub fn new(
                 regs: R,
                 freq: f32,
@@ -2036,6 +3356,177 @@ cfg_if! {
     }
 }
 
+/// Timer implementation for the basic timers, TIM6 and TIM7. These have no capture-compare
+/// channels or slave mode controller; they count up, and are used to trigger the DAC and ADC
+/// (via TRGO), or DMA transfers on update. (RM, section: Basic timers)
+#[cfg(not(any(
+    feature = "f401",
+    feature = "f410",
+    feature = "f411",
+    feature = "g031",
+    feature = "g041",
+    feature = "g070",
+    feature = "g030",
+    feature = "wb",
+    feature = "wl"
+)))]
+macro_rules! basic_timer {
+    ($TIMX:ident, $tim:ident) => {
+        impl Timer<pac::$TIMX> {
+            paste! {
+                /// Initialize a basic timer, including enabling and resetting
+                /// its RCC peripheral clock. `cfg`'s alignment, direction and capture-compare DMA
+                /// fields are ignored; basic timers only count up.
+                pub fn [<new_ $tim>](regs: pac::$TIMX, freq: f32, cfg: TimerConfig, clocks: &Clocks) -> Self {
+                    let rcc = unsafe { &(*RCC::ptr()) };
+                    pac::$TIMX::en_reset(rcc);
+
+                    // CR1: OPM is bit 3, URS is bit 2, ARPE is bit 7.
+                    regs.cr1.modify(|r, w| unsafe {
+                        w.bits(
+                            r.bits() & !((1 << 7) | (1 << 3) | (1 << 2))
+                                | ((cfg.auto_reload_preload as u32) << 7)
+                                | ((cfg.one_pulse_mode as u32) << 3)
+                                | ((cfg.update_request_source as u32) << 2),
+                        )
+                    });
+
+                    let mut result = Timer {
+                        clock_speed: clocks.apb1_timer(),
+                        cfg,
+                        regs,
+                        wrap_count: 0,
+                    };
+
+                    result.set_freq(freq).ok();
+                    result.reinitialize();
+
+                    result
+                }
+            }
+
+            /// Enable a specific type of Timer interrupt. Basic timers support `Update`,
+            /// and `UpdateDma`; other variants are ignored.
+            pub fn enable_interrupt(&mut self, interrupt: TimerInterrupt) {
+                // DIER: UIE is bit 0, UDE is bit 8.
+                let bit = match interrupt {
+                    TimerInterrupt::Update => 0,
+                    TimerInterrupt::UpdateDma => 8,
+                    _ => return,
+                };
+                self.regs.dier.modify(|r, w| unsafe { w.bits(r.bits() | (1 << bit)) });
+            }
+
+            /// Disable a specific type of Timer interrupt. Variants other than `Update` and
+            /// `UpdateDma` are ignored.
+            pub fn disable_interrupt(&mut self, interrupt: TimerInterrupt) {
+                let bit = match interrupt {
+                    TimerInterrupt::Update => 0,
+                    TimerInterrupt::UpdateDma => 8,
+                    _ => return,
+                };
+                self.regs.dier.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << bit)) });
+            }
+
+            /// Clears interrupt associated with this timer. Place this at the top of your timer's
+            /// interrupt handler. Basic timers only have the update interrupt flag; other variants
+            /// are ignored.
+            pub fn clear_interrupt(&mut self, interrupt: TimerInterrupt) {
+                if let TimerInterrupt::Update = interrupt {
+                    self.clear_uif();
+                }
+            }
+
+            /// Enable (start) the timer.
+            pub fn enable(&mut self) {
+                self.regs.cr1.modify(|_, w| w.cen().set_bit());
+            }
+
+            /// Disable (stop) the timer.
+            pub fn disable(&mut self) {
+                self.regs.cr1.modify(|_, w| w.cen().clear_bit());
+            }
+
+            /// Check if the timer is enabled.
+            pub fn is_enabled(&self) -> bool {
+                self.regs.cr1.read().cen().bit_is_set()
+            }
+
+            /// Set the timer frequency, in Hz. Overrides the period or frequency set
+            /// in the constructor.
+            pub fn set_freq(&mut self, freq: f32) -> Result<(), ValueError> {
+                assert!(freq > 0.);
+
+                let (psc, arr) = calc_freq_vals(freq, self.clock_speed)?;
+
+                self.regs.arr.write(|w| unsafe { w.bits(arr.into()) });
+                self.regs.psc.write(|w| unsafe { w.bits(psc.into()) });
+
+                Ok(())
+            }
+
+            /// Set the timer period, in seconds. Overrides the period or frequency set
+            /// in the constructor.
+            pub fn set_period(&mut self, period: f32) -> Result<(), ValueError> {
+                assert!(period > 0.);
+                self.set_freq(1. / period)
+            }
+
+            /// Set the auto-reload register value. Used for adjusting frequency.
+            pub fn set_auto_reload(&mut self, arr: u16) {
+                self.regs.arr.write(|w| unsafe { w.bits(arr.into()) });
+            }
+
+            /// Set the prescaler value. Used for adjusting frequency.
+            pub fn set_prescaler(&mut self, psc: u16) {
+                self.regs.psc.write(|w| unsafe { w.bits(psc.into()) });
+            }
+
+            /// Return the auto-reload value; the counter's maximum.
+            pub fn get_max_duty(&self) -> u16 {
+                self.regs.arr.read().bits() as u16
+            }
+
+            /// Reset the count; set the counter to 0.
+            pub fn reset_count(&mut self) {
+                self.regs.cnt.write(|w| unsafe { w.bits(0) });
+            }
+
+            /// Read the current counter value.
+            pub fn read_count(&self) -> u16 {
+                // Mask out UIFCPY, on variants that have it.
+                self.regs.cnt.read().bits() as u16
+            }
+
+            /// Read the update interrupt flag.
+            pub fn get_uif(&self) -> bool {
+                self.regs.sr.read().uif().bit_is_set()
+            }
+
+            /// Clear the update interrupt flag.
+            pub fn clear_uif(&mut self) {
+                // SR only contains UIF on basic timers.
+                self.regs.sr.write(|w| unsafe { w.bits(0) });
+            }
+
+            /// Re-initialize the counter and generate an update of the registers. This loads the
+            /// prescaler, and clears the update flag it generates.
+            pub fn reinitialize(&mut self) {
+                self.regs.egr.write(|w| w.ug().set_bit());
+                self.clear_uif();
+            }
+
+            /// Set the event sent to the DAC, ADC, or other timers on TRGO. Basic timers support
+            /// `Reset`, `Enable`, and `Update`. Use `Update` to trigger a DAC or ADC conversion each
+            /// period. Sets `TIMx_CR2` register, `MMS` field.
+            pub fn set_mastermode(&mut self, mode: MasterModeSelection) {
+                assert!((mode as u8) <= MasterModeSelection::Update as u8);
+                self.regs.cr2.modify(|_, w| unsafe { w.mms().bits(mode as u8) });
+            }
+        }
+    };
+}
+
 // pub enum TimerNum {
 // Tim1,
 // Tim2,
@@ -2073,6 +3564,18 @@ cc_4_channels!(TIM1, u16);
 // TIM1 on G4 is nominally 16-bits, but has ~20 bits on ARR, with PAC showing 32 bits?
 #[cfg(any(feature = "g0", feature = "g4"))]
 cc_2_channels!(TIM1, u16);
+#[cfg(not(any(feature = "f373")))]
+advanced_timer!(TIM1);
+#[cfg(not(any(feature = "f373")))]
+encoder!(TIM1, u16);
+#[cfg(not(any(feature = "f373")))]
+pwm_input!(TIM1, u16);
+#[cfg(not(any(feature = "f373")))]
+one_pulse!(TIM1, u16);
+#[cfg(not(any(feature = "f373")))]
+timer_sync!(TIM1, Tim1);
+#[cfg(not(any(feature = "f373")))]
+monotonic!(TIM1);
 
 cfg_if! {
     if #[cfg(not(any(
@@ -2083,6 +3586,11 @@ cfg_if! {
     )))] {
         make_timer!(TIM2, tim2, 1, u32);
         cc_4_channels!(TIM2, u32);
+        encoder!(TIM2, u32);
+        pwm_input!(TIM2, u32);
+        one_pulse!(TIM2, u32);
+        timer_sync!(TIM2, Tim2);
+        monotonic!(TIM2);
     }
 }
 
@@ -2101,6 +3609,11 @@ cfg_if! {
     )))] {
         make_timer!(TIM3, tim3, 1, u32);
         cc_4_channels!(TIM3, u32);
+        encoder!(TIM3, u32);
+        pwm_input!(TIM3, u32);
+        one_pulse!(TIM3, u32);
+        timer_sync!(TIM3, Tim3);
+        monotonic!(TIM3);
     }
 }
 
@@ -2120,6 +3633,11 @@ cfg_if! {
     )))] {
         make_timer!(TIM4, tim4, 1, u32);
         cc_4_channels!(TIM4, u32);
+        encoder!(TIM4, u32);
+        pwm_input!(TIM4, u32);
+        one_pulse!(TIM4, u32);
+        timer_sync!(TIM4, Tim4);
+        monotonic!(TIM4);
     }
 }
 
@@ -2139,9 +3657,42 @@ cfg_if! {
    ))] {
         make_timer!(TIM5, tim5, 1, u32);
         cc_4_channels!(TIM5, u32);
+        encoder!(TIM5, u32);
+        pwm_input!(TIM5, u32);
+        one_pulse!(TIM5, u32);
+        timer_sync!(TIM5, Tim5);
+        monotonic!(TIM5);
    }
 }
 
+#[cfg(not(any(
+    feature = "f401",
+    feature = "f410",
+    feature = "f411",
+    feature = "g031",
+    feature = "g041",
+    feature = "g070",
+    feature = "g030",
+    feature = "wb",
+    feature = "wl"
+)))]
+basic_timer!(TIM6, tim6);
+
+#[cfg(not(any(
+    feature = "f301",
+    feature = "f302",
+    feature = "f401",
+    feature = "f410",
+    feature = "f411",
+    feature = "g031",
+    feature = "g041",
+    feature = "g070",
+    feature = "g030",
+    feature = "wb",
+    feature = "wl"
+)))]
+basic_timer!(TIM7, tim7);
+
 cfg_if! {
     if #[cfg(any(
         feature = "f303",
@@ -2157,6 +3708,17 @@ cfg_if! {
         cc_4_channels!(TIM8, u16);
         #[cfg(feature = "l5")] // PAC bug.
         cc_1_channel!(TIM8, u16);
+        advanced_timer!(TIM8);
+        #[cfg(not(feature = "l5"))]
+        encoder!(TIM8, u16);
+        #[cfg(not(feature = "l5"))]
+        pwm_input!(TIM8, u16);
+        #[cfg(not(feature = "l5"))]
+        one_pulse!(TIM8, u16);
+        #[cfg(not(feature = "l5"))]
+        timer_sync!(TIM8, Tim8);
+        #[cfg(not(feature = "l5"))]
+        monotonic!(TIM8);
     }
 }
 
@@ -2165,6 +3727,12 @@ cfg_if! {
     if #[cfg(feature = "g4")] {
         make_timer!(TIM8, tim8, 2, u32);
         cc_4_channels!(TIM8, u32);
+        advanced_timer!(TIM8);
+        encoder!(TIM8, u32);
+        pwm_input!(TIM8, u32);
+        one_pulse!(TIM8, u32);
+        timer_sync!(TIM8, Tim8);
+        monotonic!(TIM8);
     }
 }
 
@@ -2240,5 +3808,17 @@ cfg_if! {
 make_timer!(TIM20, tim20, 2, u16);
 #[cfg(any(feature = "f303"))]
 cc_4_channels!(TIM20, u16);
+#[cfg(any(feature = "f303"))]
+advanced_timer!(TIM20);
+#[cfg(any(feature = "f303"))]
+encoder!(TIM20, u16);
+#[cfg(any(feature = "f303"))]
+pwm_input!(TIM20, u16);
+#[cfg(any(feature = "f303"))]
+one_pulse!(TIM20, u16);
+#[cfg(any(feature = "f303"))]
+timer_sync!(TIM20, Tim20);
+#[cfg(any(feature = "f303"))]
+monotonic!(TIM20);
 
 // todo: Remove the final "true/false" for adv ctrl. You need a sep macro like you do for ccx_channel!.
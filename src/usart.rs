@@ -20,8 +20,8 @@ use nb;
 
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::dma::DmaInput;
-#[cfg(not(any(feature = "f4", feature = "l552", feature = "h5")))]
-use crate::dma::{self, ChannelCfg, DmaChannel};
+#[cfg(not(feature = "h5"))]
+use crate::dma::{self, ChannelCfg, ChannelToken};
 #[cfg(feature = "g0")]
 use crate::pac::DMA as DMA1;
 #[cfg(not(any(feature = "g0", feature = "h5")))]
@@ -515,28 +515,30 @@ where
         }
     }
 
-    #[cfg(not(any(feature = "f4", feature = "l552", feature = "h5")))]
+    #[cfg(not(feature = "h5"))]
     /// Transmit data using DMA. (L44 RM, section 38.5.15)
-    /// Note that the `channel` argument is unused on F3 and L4, since it is hard-coded,
-    /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
+    /// On F3 and L4, the `channel` token must be for the channel hard-wired to this peripheral on
+    /// its DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise. `dma::claim_channel_for()` claims the right one.
     pub unsafe fn write_dma(
         &mut self,
         buf: &[u8],
-        channel: DmaChannel,
+        channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
     ) {
+        let dma_periph = channel.periph();
+
         let (ptr, len) = (buf.as_ptr(), buf.len());
 
         // To map a DMA channel for USART transmission, use
         // the following procedure (x denotes the channel number):
 
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let channel = channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel = R::write_chan();
-        #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
+        let channel = dma::check_fixed_channel(channel, dma_periph, R::write_chan(dma_periph));
         #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::write_sel(dma_periph);
 
         #[cfg(feature = "h7")]
         let num_data = len as u32;
@@ -551,7 +553,16 @@ where
 
         // 6. Clear the TC flag in the USART_ISR register by setting the TCCF bit in the
         // USART_ICR register.
+        #[cfg(not(feature = "f4"))]
         self.regs.icr.write(|w| w.tccf().set_bit());
+        // (F4: The TC bit is cleared by writing 0 to it in the SR register.)
+        #[cfg(feature = "f4")]
+        self.regs.sr.modify(|_, w| w.tc().clear_bit());
+
+        #[cfg(not(feature = "f4"))]
+        let periph_addr = &self.regs.tdr as *const _ as u32;
+        #[cfg(feature = "f4")]
+        let periph_addr = &self.regs.dr as *const _ as u32;
 
         match dma_periph {
             dma::DmaPeriph::Dma1 => {
@@ -562,7 +573,7 @@ where
                     // 1. Write the USART_TDR register address in the DMA control register to configure it as
                     // the destination of the transfer. The data is moved to this address from memory after
                     // each TXE event.
-                    &self.regs.tdr as *const _ as u32,
+                    periph_addr,
                     // 2. Write the memory address in the DMA control register to configure it as the source of
                     // the transfer. The data is loaded into the USART_TDR register from this memory area
                     // after each TXE event.
@@ -583,7 +594,7 @@ where
                 dma::cfg_channel(
                     &mut regs,
                     channel,
-                    &self.regs.tdr as *const _ as u32,
+                    periph_addr,
                     ptr as u32,
                     num_data,
                     dma::Direction::ReadFromMem,
@@ -611,25 +622,27 @@ where
         // of the last frame.
     }
 
-    #[cfg(not(any(feature = "f4", feature = "l552", feature = "h5")))]
+    #[cfg(not(feature = "h5"))]
     /// Receive data using DMA. (L44 RM, section 38.5.15; G4 RM section 37.5.19.
-    /// Note that the `channel` argument is unused on F3 and L4, since it is hard-coded,
-    /// and can't be configured using the DMAMUX peripheral. (`dma::mux()` fn).
+    /// On F3 and L4, the `channel` token must be for the channel hard-wired to this peripheral on
+    /// its DMA controller, since it can't be configured using the DMAMUX peripheral; this panics
+    /// otherwise. `dma::claim_channel_for()` claims the right one.
     pub unsafe fn read_dma(
         &mut self,
         buf: &mut [u8],
-        channel: DmaChannel,
+        channel: &ChannelToken,
         channel_cfg: ChannelCfg,
-        dma_periph: dma::DmaPeriph,
     ) {
+        let dma_periph = channel.periph();
+
         let (ptr, len) = (buf.as_mut_ptr(), buf.len());
 
+        #[cfg(not(any(feature = "f3", feature = "l4")))]
+        let channel = channel.channel();
         #[cfg(any(feature = "f3", feature = "l4"))]
-        let channel = R::read_chan();
+        let channel = dma::check_fixed_channel(channel, dma_periph, R::read_chan(dma_periph));
         #[cfg(feature = "l4")]
-        let mut dma_regs = unsafe { &(*DMA1::ptr()) }; // todo: Hardcoded DMA1
-        #[cfg(feature = "l4")]
-        R::write_sel(&mut dma_regs);
+        R::read_sel(dma_periph);
 
         #[cfg(feature = "h7")]
         let num_data = len as u32;
@@ -639,6 +652,11 @@ where
         // DMA mode can be enabled for reception by setting the DMAR bit in USART_CR3 register.
         self.regs.cr3.modify(|_, w| w.dmar().set_bit());
 
+        #[cfg(not(feature = "f4"))]
+        let periph_addr = &self.regs.rdr as *const _ as u32;
+        #[cfg(feature = "f4")]
+        let periph_addr = &self.regs.dr as *const _ as u32;
+
         match dma_periph {
             dma::DmaPeriph::Dma1 => {
                 let mut regs = unsafe { &(*DMA1::ptr()) };
@@ -648,7 +666,7 @@ where
                     // 1. Write the USART_RDR register address in the DMA control register to configure it as
                     // the source of the transfer. The data is moved from this address to the memory after
                     // each RXNE event.
-                    &self.regs.rdr as *const _ as u32,
+                    periph_addr,
                     // 2. Write the memory address in the DMA control register to configure it as the destination
                     // of the transfer. The data is loaded from USART_RDR to this memory area after each
                     // RXNE event.
@@ -667,7 +685,7 @@ where
                 dma::cfg_channel(
                     &mut regs,
                     channel,
-                    &self.regs.rdr as *const _ as u32,
+                    periph_addr,
                     ptr as u32,
                     num_data,
                     dma::Direction::ReadFromPeriph,
@@ -693,6 +711,44 @@ where
         // controller generates an interrupt on the DMA channel interrupt vector.
     }
 
+    #[cfg(not(feature = "h5"))]
+    /// Transmit data using DMA, taking ownership of the buffer and the USART for the duration
+    /// of the transfer. Use the returned `Transfer` to check for completion, and to get them back.
+    /// See `write_dma` for details.
+    pub fn write_dma_owned(
+        mut self,
+        buf: &'static [u8],
+        channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static [u8]> {
+        unsafe { self.write_dma(buf, &channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, channel).with_cleanup(Self::end_dma)
+    }
+
+    #[cfg(not(feature = "h5"))]
+    /// Receive data using DMA, taking ownership of the buffer and the USART for the duration
+    /// of the transfer. See `write_dma_owned`, and `read_dma`.
+    pub fn read_dma_owned(
+        mut self,
+        buf: &'static mut [u8],
+        channel: ChannelToken,
+        channel_cfg: ChannelCfg,
+    ) -> dma::Transfer<Self, &'static mut [u8]> {
+        unsafe { self.read_dma(buf, &channel, channel_cfg.single_buf()) };
+
+        dma::Transfer::new(self, buf, channel).with_cleanup(Self::end_dma)
+    }
+
+    #[cfg(not(feature = "h5"))]
+    /// Disable DMA requests, once an owned transfer has ended.
+    fn end_dma(&mut self) {
+        self.regs.cr3.modify(|_, w| {
+            w.dmat().clear_bit();
+            w.dmar().clear_bit()
+        });
+    }
+
     /// Flush the transmit buffer.
     pub fn flush(&self) {
         #[cfg(not(feature = "f4"))]
@@ -1,15 +1,10 @@
 //! This is an internal module that contains utility functionality used by other modules.
 
-#[cfg(feature = "l4")]
-use core::ops::Deref;
-
 use cfg_if::cfg_if;
 
 // todo: L5 has a PAC bug on CCR registers past 1.
 #[cfg(any(feature = "f3", feature = "l4"))]
-use crate::dma::{self, Dma, DmaChannel, DmaInput};
-#[cfg(feature = "l4")]
-use crate::pac::dma1;
+use crate::dma::{self, Dma, DmaChannel, DmaInput, DmaPeriph};
 #[cfg(any(feature = "f3", feature = "l4"))]
 use crate::pac::DMA1;
 use crate::{
@@ -129,6 +124,13 @@ macro_rules! rcc_en_reset {
             }
         }}
     };
+    (ahb4, $periph:expr, $rcc:expr) => {
+        paste::paste! {
+            $rcc.ahb4enr.modify(|_, w| w.[<$periph en>]().set_bit());
+            $rcc.ahb4rstr.modify(|_, w| w.[<$periph rst>]().set_bit());
+            $rcc.ahb4rstr.modify(|_, w| w.[<$periph rst>]().clear_bit());
+        }
+    };
 }
 
 pub(crate) use rcc_en_reset;
@@ -271,13 +273,13 @@ pub trait RccPeriph {
     fn en_reset(rcc: &RegisterBlock);
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel;
+    fn read_chan(periph: DmaPeriph) -> DmaChannel;
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel;
+    fn write_chan(periph: DmaPeriph) -> DmaChannel;
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D);
+    fn read_sel(periph: DmaPeriph);
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D);
+    fn write_sel(periph: DmaPeriph);
 }
 
 #[cfg(not(any(
@@ -297,22 +299,22 @@ impl RccPeriph for pac::TIM6 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
+    fn read_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
+    fn write_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn read_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn write_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 }
@@ -336,22 +338,22 @@ impl RccPeriph for pac::TIM7 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
+    fn read_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
+    fn write_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn read_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn write_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 }
@@ -362,23 +364,23 @@ impl RccPeriph for pac::I2C1 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::I2c1Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::I2c1Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::I2c1Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::I2c1Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::I2c1Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::I2c1Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::I2c1Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::I2c1Tx);
     }
 }
 
@@ -389,23 +391,23 @@ impl RccPeriph for pac::I2C2 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::I2c2Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::I2c2Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::I2c2Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::I2c2Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::I2c2Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::I2c2Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::I2c2Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::I2c2Tx);
     }
 }
 
@@ -423,23 +425,23 @@ impl RccPeriph for pac::SPI1 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::Spi1Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Spi1Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::Spi1Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Spi1Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Spi1Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Spi1Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Spi1Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Spi1Tx);
     }
 }
 
@@ -450,23 +452,23 @@ impl RccPeriph for pac::SPI2 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::Spi2Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Spi2Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::Spi2Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Spi2Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Spi2Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Spi2Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Spi2Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Spi2Tx);
     }
 }
 
@@ -492,23 +494,23 @@ impl RccPeriph for pac::SPI3 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::Spi3Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Spi3Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::Spi3Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Spi3Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Spi3Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Spi3Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Spi3Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Spi3Tx);
     }
 }
 
@@ -542,22 +544,22 @@ impl RccPeriph for pac::SAI1 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
+    fn read_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
+    fn write_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn read_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn write_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 }
@@ -569,22 +571,22 @@ impl RccPeriph for pac::SAI2 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
+    fn read_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
+    fn write_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn read_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn write_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 }
@@ -596,22 +598,22 @@ impl RccPeriph for pac::SAI3 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
+    fn read_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
+    fn write_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn read_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn write_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 }
@@ -623,22 +625,22 @@ impl RccPeriph for pac::SAI4 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
+    fn read_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
+    fn write_chan(_periph: DmaPeriph) -> DmaChannel {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn read_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+    fn write_sel(_periph: DmaPeriph) {
         unimplemented!()
     }
 }
@@ -656,22 +658,22 @@ impl RccPeriph for pac::SAI4 {
 //     }
 //
 //     #[cfg(any(feature = "f3", feature = "l4"))]
-//     fn read_chan() -> DmaChannel {
+//     fn read_chan(periph: DmaPeriph) -> DmaChannel {
 //         unimplemented!()
 //     }
 //
 //     #[cfg(any(feature = "f3", feature = "l4"))]
-//     fn write_chan() -> DmaChannel {
+//     fn write_chan(periph: DmaPeriph) -> DmaChannel {
 //         unimplemented!()
 //     }
 //
 //     #[cfg(feature = "l4")]
-//     fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+//     fn read_sel(_periph: DmaPeriph) {
 //         unimplemented!()
 //     }
 //
 //     #[cfg(feature = "l4")]
-//     fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {
+//     fn write_sel(_periph: DmaPeriph) {
 //         unimplemented!()
 //     }
 // }
@@ -682,23 +684,23 @@ impl RccPeriph for pac::USART1 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::Usart1Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Usart1Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::Usart1Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Usart1Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Usart1Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Usart1Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Usart1Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Usart1Tx);
     }
 }
 
@@ -718,23 +720,23 @@ impl RccPeriph for pac::USART2 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::Usart2Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Usart2Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::Usart2Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Usart2Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Usart2Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Usart2Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Usart2Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Usart2Tx);
     }
 }
 
@@ -763,23 +765,23 @@ impl RccPeriph for pac::USART3 {
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn read_chan() -> DmaChannel {
-        DmaInput::Usart3Rx.dma1_channel()
+    fn read_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Usart3Rx.fixed_channel(periph)
     }
 
     #[cfg(any(feature = "f3", feature = "l4"))]
-    fn write_chan() -> DmaChannel {
-        DmaInput::Usart3Tx.dma1_channel()
+    fn write_chan(periph: DmaPeriph) -> DmaChannel {
+        DmaInput::Usart3Tx.fixed_channel(periph)
     }
 
     #[cfg(feature = "l4")]
-    fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Usart3Rx);
+    fn read_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Usart3Rx);
     }
 
     #[cfg(feature = "l4")]
-    fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-        dma::channel_select(regs, DmaInput::Usart3Tx);
+    fn write_sel(periph: DmaPeriph) {
+        dma::channel_select(periph, DmaInput::Usart3Tx);
     }
 }
 
@@ -791,23 +793,23 @@ cfg_if! {
             }
 
              #[cfg(feature = "l4")]
-            fn read_chan() -> DmaChannel {
-                DmaInput::Uart4Rx.dma1_channel()
+            fn read_chan(periph: DmaPeriph) -> DmaChannel {
+                DmaInput::Uart4Rx.fixed_channel(periph)
             }
 
             #[cfg(feature = "l4")]
-            fn write_chan() -> DmaChannel {
-                DmaInput::Uart4Tx.dma1_channel()
+            fn write_chan(periph: DmaPeriph) -> DmaChannel {
+                DmaInput::Uart4Tx.fixed_channel(periph)
             }
 
             #[cfg(feature = "l4")]
-            fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-                dma::channel_select(regs, DmaInput::Uart4Rx);
+            fn read_sel(periph: DmaPeriph) {
+                dma::channel_select(periph, DmaInput::Uart4Rx);
             }
 
             #[cfg(feature = "l4")]
-            fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-                dma::channel_select(regs, DmaInput::Uart4Tx);
+            fn write_sel(periph: DmaPeriph) {
+                dma::channel_select(periph, DmaInput::Uart4Tx);
             }
         }
 
@@ -817,23 +819,23 @@ cfg_if! {
             }
 
             #[cfg(feature = "l4")]
-            fn read_chan() -> DmaChannel {
-                DmaInput::Uart5Rx.dma1_channel()
+            fn read_chan(periph: DmaPeriph) -> DmaChannel {
+                DmaInput::Uart5Rx.fixed_channel(periph)
             }
 
             #[cfg(feature = "l4")]
-            fn write_chan() -> DmaChannel {
-                DmaInput::Uart5Tx.dma1_channel()
+            fn write_chan(periph: DmaPeriph) -> DmaChannel {
+                DmaInput::Uart5Tx.fixed_channel(periph)
             }
 
             #[cfg(feature = "l4")]
-            fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-                dma::channel_select(regs, DmaInput::Uart5Rx);
+            fn read_sel(periph: DmaPeriph) {
+                dma::channel_select(periph, DmaInput::Uart5Rx);
             }
 
             #[cfg(feature = "l4")]
-            fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(regs: &mut D) {
-                dma::channel_select(regs, DmaInput::Uart5Tx);
+            fn write_sel(periph: DmaPeriph) {
+                dma::channel_select(periph, DmaInput::Uart5Tx);
             }
         }
 
@@ -894,9 +896,9 @@ cfg_if! {
             fn en_reset(rcc: &RegisterBlock) {
                 rcc_en_reset!(apb1, dac1, rcc);
             }
-            fn read_chan() -> DmaChannel {unimplemented!()}
+            fn read_chan(_periph: DmaPeriph) -> DmaChannel {unimplemented!()}
 
-            fn write_chan() -> DmaChannel {unimplemented!()}
+            fn write_chan(_periph: DmaPeriph) -> DmaChannel {unimplemented!()}
         }
 
         #[cfg(any(feature = "f303", feature = "f373", feature = "f3x4"))]
@@ -904,9 +906,9 @@ cfg_if! {
             fn en_reset(rcc: &RegisterBlock) {
                 rcc_en_reset!(apb1, dac2, rcc);
             }
-            fn read_chan() -> DmaChannel {unimplemented!()}
+            fn read_chan(_periph: DmaPeriph) -> DmaChannel {unimplemented!()}
 
-            fn write_chan() -> DmaChannel {unimplemented!()}
+            fn write_chan(_periph: DmaPeriph) -> DmaChannel {unimplemented!()}
         }
     } else if #[cfg(feature = "g4")] {
         impl RccPeriph for DAC1 {
@@ -956,16 +958,16 @@ cfg_if! {
             }
 
             #[cfg(feature = "l4")]
-            fn read_chan() -> DmaChannel {unimplemented!()}
+            fn read_chan(_periph: DmaPeriph) -> DmaChannel {unimplemented!()}
 
             #[cfg(feature = "l4")]
-            fn write_chan() -> DmaChannel {unimplemented!()}
+            fn write_chan(_periph: DmaPeriph) -> DmaChannel {unimplemented!()}
 
             #[cfg(feature = "l4")]
-            fn read_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {unimplemented!()}
+            fn read_sel(_periph: DmaPeriph) {unimplemented!()}
 
             #[cfg(feature = "l4")]
-            fn write_sel<D: Deref<Target = dma1::RegisterBlock>>(_regs: &mut D) {unimplemented!()}
+            fn write_sel(_periph: DmaPeriph) {unimplemented!()}
         }
     }
 }
@@ -982,12 +984,12 @@ cfg_if! {
 // #[cfg(any(feature = "f3", feature = "l4"))]
 // impl DmaPeriph for ADC1 {
 //     #[cfg(any(feature = "f3", feature = "l4"))]
-//     fn read_chan() -> DmaChannel {
-//         DmaInput::Adc1.dma1_channel()
+//     fn read_chan(periph: DmaPeriph) -> DmaChannel {
+//         DmaInput::Adc1.fixed_channel(periph)
 //     }
 //
 //     #[cfg(any(feature = "f3", feature = "l4"))]
-//     fn write_chan() -> DmaChannel {
+//     fn write_chan(periph: DmaPeriph) -> DmaChannel {
 //         unimplemented!()
 //     }
 //
@@ -1011,12 +1013,12 @@ cfg_if! {
 // ))]
 // impl DmaPeriph for pac::ADC2 {
 //     #[cfg(any(feature = "f3", feature = "l4"))]
-//     fn read_chan() -> DmaChannel {
-//         DmaInput::Adc2.dma1_channel()
+//     fn read_chan(periph: DmaPeriph) -> DmaChannel {
+//         DmaInput::Adc2.fixed_channel(periph)
 //     }
 //
 //     #[cfg(any(feature = "f3", feature = "l4"))]
-//     fn write_chan() -> DmaChannel {
+//     fn write_chan(periph: DmaPeriph) -> DmaChannel {
 //         unimplemented!()
 //     }
 //